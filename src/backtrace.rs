@@ -0,0 +1,203 @@
+use std::fmt;
+
+use pyo3::{intern, prelude::*};
+
+use crate::{debug::SourceLocation, float_bits, module::ParsedModule};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A backtrace of the WebAssembly frames on the stack when a guest trapped.
+///
+/// The web browser's JavaScript engine only reports the indices of the
+/// WebAssembly functions on the stack, e.g. `wasm-function[123]:0x4567`. A
+/// backtrace maps these frames back to the function names in the module's
+/// `name` custom section.
+///
+/// A backtrace is attached as context to the error returned by [`Func::call`]
+/// if the guest trapped inside an exported function. It can also be created
+/// from a JavaScript stack trace with [`Module::symbolize`].
+///
+/// Since the JavaScript stack does not identify modules, all frames are
+/// symbolized using the module which exported the called function, even if
+/// some of them belong to other modules.
+///
+/// [`Func::call`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.call
+/// [`Module::symbolize`]: crate::Module::symbolize
+pub struct WasmBacktrace {
+    /// The frames, starting with the innermost frame
+    frames: Vec<FrameInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A WebAssembly frame in a [`WasmBacktrace`].
+pub struct FrameInfo {
+    /// The index of the function in the module's function index space
+    func_index: u32,
+    /// The name of the function, if known
+    func_name: Option<String>,
+    /// The byte offset of the frame's instruction in the module, if known
+    module_offset: Option<usize>,
+    /// The source location of the frame's instruction, if known
+    location: Option<SourceLocation>,
+}
+
+impl WasmBacktrace {
+    /// Symbolizes the WebAssembly frames in the JavaScript stack trace `stack`
+    /// using the function names of the `module`
+    pub(crate) fn from_stack(module: &ParsedModule, stack: &str) -> Self {
+        // frames of the adapters that preserve float bits are skipped
+        let frames = stack
+            .lines()
+            .filter(|line| !line.contains(float_bits::ADAPTER_MODULE_NAME))
+            .filter_map(parse_frame)
+            .map(|(func_index, module_offset)| {
+                let module_offset = module_offset.map(|offset| module.original_offset(offset));
+
+                FrameInfo {
+                    func_index,
+                    func_name: module.function_name(func_index).map(String::from),
+                    module_offset,
+                    location: module_offset
+                        .and_then(|offset| module.source_location(offset))
+                        .cloned(),
+                }
+            })
+            .collect();
+
+        Self { frames }
+    }
+
+    /// Symbolizes the stack trace of the JavaScript error `err`, if it
+    /// contains any WebAssembly frames
+    pub(crate) fn from_js_error(py: Python, module: &ParsedModule, err: &PyErr) -> Option<Self> {
+        let stack: String = err
+            .value(py)
+            .getattr(intern!(py, "stack"))
+            .ok()?
+            .extract()
+            .ok()?;
+
+        let backtrace = Self::from_stack(module, &stack);
+
+        if backtrace.frames.is_empty() {
+            return None;
+        }
+
+        Some(backtrace)
+    }
+
+    #[must_use]
+    /// Returns the frames of the backtrace, starting with the innermost
+    /// frame.
+    pub fn frames(&self) -> &[FrameInfo] {
+        &self.frames
+    }
+}
+
+impl fmt::Display for WasmBacktrace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("error while executing at wasm backtrace:")?;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            write!(fmt, "\n  {i:>3}: ")?;
+            if let Some(offset) = frame.module_offset {
+                write!(fmt, "{offset:#8x} - ")?;
+            }
+            write!(fmt, "{frame}")?;
+            if let Some(location) = &frame.location {
+                write!(fmt, "\n                    at {location}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FrameInfo {
+    #[must_use]
+    /// Returns the index of the function in the module's function index
+    /// space.
+    pub const fn func_index(&self) -> u32 {
+        self.func_index
+    }
+
+    #[must_use]
+    /// Returns the name of the function from the module's `name` custom
+    /// section, if known.
+    pub fn func_name(&self) -> Option<&str> {
+        self.func_name.as_deref()
+    }
+
+    #[must_use]
+    /// Returns the byte offset of the frame's instruction in the module, if
+    /// known.
+    ///
+    /// The offset refers to the module's original bytes, even if the module
+    /// has been instrumented, e.g. for [`Config::consume_fuel`].
+    ///
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    pub const fn module_offset(&self) -> Option<usize> {
+        self.module_offset
+    }
+
+    #[must_use]
+    /// Returns the source location of the frame's instruction, if the module
+    /// has debug information, see [`Config::debug_info`] and
+    /// [`Module::with_source_map`].
+    ///
+    /// [`Config::debug_info`]: crate::Config::debug_info
+    /// [`Module::with_source_map`]: crate::Module::with_source_map
+    pub const fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+}
+
+impl fmt::Display for FrameInfo {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match &self.func_name {
+            Some(name) => fmt.write_str(name),
+            None => write!(fmt, "<wasm function {}>", self.func_index),
+        }
+    }
+}
+
+/// Parses the function index and module offset of a WebAssembly frame in a
+/// JavaScript stack trace `line`
+///
+/// Chromium and Firefox format frames as `wasm-function[123]:0x4567`, while
+/// Safari omits the offset.
+fn parse_frame(line: &str) -> Option<(u32, Option<usize>)> {
+    let (_, frame) = line.split_once("wasm-function[")?;
+    let (index, rest) = frame.split_once(']')?;
+    let index = index.parse().ok()?;
+
+    let offset = rest.strip_prefix(":0x").and_then(|rest| {
+        let end = rest
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(rest.len());
+        usize::from_str_radix(&rest[..end], 16).ok()
+    });
+
+    Some((index, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stack_frames() {
+        assert_eq!(
+            parse_frame("    at add (wasm://wasm/8c1f2b3e:wasm-function[3]:0x8a)"),
+            Some((3, Some(0x8A)))
+        );
+        assert_eq!(
+            parse_frame("add@http://localhost/plugin.wasm:wasm-function[12]:0x1f0"),
+            Some((12, Some(0x1F0)))
+        );
+        assert_eq!(
+            parse_frame("<?>.wasm-function[7]@[wasm code]"),
+            Some((7, None))
+        );
+        assert_eq!(parse_frame("    at Object.call (<anonymous>)"), None);
+    }
+}
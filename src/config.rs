@@ -0,0 +1,183 @@
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+/// Configuration for an [`Engine`].
+///
+/// All options are disabled by default.
+///
+/// [`Engine`]: crate::Engine
+pub struct Config {
+    /// Whether modules are instrumented to consume fuel
+    consume_fuel: bool,
+    /// Whether modules are instrumented to be interruptible by epochs
+    epoch_interruption: bool,
+    /// The maximum depth of nested guest and host calls
+    max_call_depth: Option<usize>,
+    /// Whether DWARF debug information is read from modules
+    debug_info: bool,
+    /// Whether the bits of floats are preserved across the JS boundary
+    preserve_float_bits: bool,
+}
+
+impl Config {
+    #[must_use]
+    /// Creates a new default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures whether WASM modules consume fuel while executing.
+    ///
+    /// The web browser's [`WebAssembly`] runtime does not support fuel. When
+    /// this option is enabled, every [`Module`] is instead instrumented to
+    /// decrement a per-[`Store`] fuel counter as it executes. Executing a
+    /// guest function with insufficient fuel traps with
+    /// [`Trap::OutOfFuel`].
+    ///
+    /// The fuel of a store starts at zero and can be changed with e.g.
+    /// [`Store::set_fuel`].
+    ///
+    /// [`WebAssembly`]: https://developer.mozilla.org/en-US/docs/WebAssembly
+    /// [`Module`]: crate::Module
+    /// [`Store`]: crate::Store
+    /// [`Trap::OutOfFuel`]: crate::Trap::OutOfFuel
+    /// [`Store::set_fuel`]: crate::Store::set_fuel
+    pub fn consume_fuel(&mut self, enable: bool) -> &mut Self {
+        self.consume_fuel = enable;
+        self
+    }
+
+    #[must_use]
+    /// Returns whether WASM modules consume fuel while executing.
+    pub const fn consumes_fuel(&self) -> bool {
+        self.consume_fuel
+    }
+
+    /// Configures whether WASM modules can be interrupted once the [`Engine`]'s
+    /// epoch reaches a per-[`Store`] deadline.
+    ///
+    /// The web browser's [`WebAssembly`] runtime does not support interrupting
+    /// guest code. When this option is enabled, every [`Module`] is instead
+    /// instrumented to compare the engine's epoch with the store's deadline
+    /// at every function entry and loop header. Once the deadline has been
+    /// reached, the guest traps with [`Trap::Interrupt`].
+    ///
+    /// The epoch is incremented with [`Engine::increment_epoch`] and the
+    /// deadline is set with e.g. [`Store::set_epoch_deadline`]. The deadline
+    /// of a store starts at zero, i.e. guest code is interrupted immediately
+    /// until a deadline has been set.
+    ///
+    /// Since JavaScript is single-threaded, the epoch can only be incremented
+    /// while guest code is running by a host function that is called by the
+    /// guest, e.g. a host function that checks a timer, or by another store
+    /// whose guest is running inside a re-entrant call.
+    ///
+    /// [`Engine`]: crate::Engine
+    /// [`Store`]: crate::Store
+    /// [`WebAssembly`]: https://developer.mozilla.org/en-US/docs/WebAssembly
+    /// [`Module`]: crate::Module
+    /// [`Trap::Interrupt`]: crate::Trap::Interrupt
+    /// [`Engine::increment_epoch`]: crate::Engine::increment_epoch
+    /// [`Store::set_epoch_deadline`]: crate::Store::set_epoch_deadline
+    pub fn epoch_interruption(&mut self, enable: bool) -> &mut Self {
+        self.epoch_interruption = enable;
+        self
+    }
+
+    #[must_use]
+    /// Returns whether WASM modules can be interrupted by epochs.
+    pub const fn epoch_interruption_enabled(&self) -> bool {
+        self.epoch_interruption
+    }
+
+    /// Configures the maximum depth of nested guest and host calls in a
+    /// [`Store`].
+    ///
+    /// Every call into a guest function with [`Func::call`] and every call
+    /// from a guest into a host function counts towards the depth. Exceeding
+    /// the maximum traps with [`Trap::StackOverflow`].
+    ///
+    /// Without a maximum, deeply nested calls eventually exhaust the call
+    /// stack of the web browser's JavaScript engine or the Python
+    /// interpreter, which is also reported as [`Trap::StackOverflow`].
+    ///
+    /// [`Store`]: crate::Store
+    /// [`Func::call`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.call
+    /// [`Trap::StackOverflow`]: crate::Trap::StackOverflow
+    pub fn max_call_depth(&mut self, depth: Option<usize>) -> &mut Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    #[must_use]
+    /// Returns the maximum depth of nested guest and host calls, if any.
+    pub const fn call_depth_limit(&self) -> Option<usize> {
+        self.max_call_depth
+    }
+
+    /// Configures whether the DWARF debug information of WASM modules is
+    /// read when a [`Module`] is created.
+    ///
+    /// When this option is enabled, the line tables in the DWARF custom
+    /// sections of a module are parsed in [`Module::new`]. The
+    /// [`WasmBacktrace`] that is attached to errors from [`Func::call`] then
+    /// contains the source locations of the WebAssembly frames. Modules that
+    /// are described by a source map instead can use
+    /// [`Module::with_source_map`].
+    ///
+    /// [`Module`]: crate::Module
+    /// [`Module::new`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Module.html#method.new
+    /// [`WasmBacktrace`]: crate::WasmBacktrace
+    /// [`Func::call`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.call
+    /// [`Module::with_source_map`]: crate::Module::with_source_map
+    pub fn debug_info(&mut self, enable: bool) -> &mut Self {
+        self.debug_info = enable;
+        self
+    }
+
+    #[must_use]
+    /// Returns whether the DWARF debug information of WASM modules is read.
+    pub const fn debug_info_enabled(&self) -> bool {
+        self.debug_info
+    }
+
+    /// Configures whether the exact bits of `f32` and `f64` values are
+    /// preserved when they cross the boundary between WebAssembly and the
+    /// host.
+    ///
+    /// By default, floats are passed to and from the web browser's
+    /// [`WebAssembly`] runtime as JavaScript numbers. Since an `f32` is
+    /// promoted to an `f64` number and JavaScript engines may canonicalize
+    /// NaNs, signalling NaNs and NaN payloads can be altered on the way.
+    ///
+    /// When this option is enabled, floats are instead passed as their integer
+    /// bit patterns and reinterpreted inside small generated WebAssembly
+    /// adapter modules, which preserves every bit:
+    ///
+    /// - host functions from [`Func::new`] with float parameters or results are
+    ///   wrapped in a WebAssembly function
+    /// - [`Func::call`] calls functions with float parameters or results
+    ///   through a WebAssembly function
+    /// - float [`Global`]s are created, read, and written through WebAssembly
+    ///
+    /// [`Table`]s only contain references, which are always passed unchanged.
+    /// Each adapter module is compiled once per function or global type, but
+    /// creating an immutable float global compiles a new module for its
+    /// value.
+    ///
+    /// [`WebAssembly`]: https://developer.mozilla.org/en-US/docs/WebAssembly
+    /// [`Func::new`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.new
+    /// [`Func::call`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.call
+    /// [`Global`]: crate::Global
+    /// [`Table`]: crate::Table
+    pub fn preserve_float_bits(&mut self, enable: bool) -> &mut Self {
+        self.preserve_float_bits = enable;
+        self
+    }
+
+    #[must_use]
+    /// Returns whether the exact bits of floats are preserved across the
+    /// boundary between WebAssembly and the host.
+    pub const fn preserves_float_bits(&self) -> bool {
+        self.preserve_float_bits
+    }
+}
@@ -1,12 +1,25 @@
 use std::convert::Infallible;
 
-use pyo3::{exceptions::PyRuntimeError, intern, prelude::*, sync::GILOnceCell, types::IntoPyDict};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
+    ffi, intern,
+    prelude::*,
+    types::{IntoPyDict, PyBool, PyInt, PyTuple},
+};
+use pyo3_error::PyErrChain;
 use wasm_runtime_layer::{
     backend::{Extern, Value},
-    ValueType,
+    FuncType, ValueType,
 };
 
-use crate::{Engine, ExternRef};
+use crate::{
+    js::{
+        self, array_buffer, array_buffer_is_view, big_int, is_instance_of, object_new,
+        object_wrapped_big_int, to_js, uint8_array_new,
+    },
+    reflection::{func_type_from_js, type_descriptor},
+    Engine, ExternRef, Func,
+};
 
 /// Converts a Rust type to Python
 pub trait ToPy {
@@ -100,13 +113,24 @@ impl ValueExt for Value<Engine> {
             },
             ValueType::FuncRef => {
                 if value.is_none() {
-                    Ok(Self::FuncRef(None))
-                } else {
-                    Err(PyRuntimeError::new_err(
+                    return Ok(Self::FuncRef(None));
+                }
+
+                // the function's type signature can only be recovered with
+                // JS Type Reflection support
+                let Some(descriptor) = type_descriptor(&value)? else {
+                    return Err(PyRuntimeError::new_err(
                         "conversion to a function outside of a module export is not permitted as \
                          its type signature is unknown",
-                    ))
-                }
+                    ));
+                };
+
+                let py = value.py();
+                let func = func_type_from_js(&descriptor)
+                    .and_then(|ty| Func::from_exported_function(value, ty, None, None))
+                    .map_err(|err| PyErrChain::pyerr_from_err(py, err))?;
+
+                Ok(Self::FuncRef(Some(func)))
             },
         }
     }
@@ -132,85 +156,229 @@ impl ValueTypeExt for ValueType {
     }
 }
 
+/// The largest integer that Pyodide converts into a JavaScript number instead
+/// of a `BigInt`, i.e. `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Converts the `i64` into a JavaScript `BigInt`, which costs one call into
+/// JavaScript through the cached [`object_wrapped_big_int`] for every value
+/// that is a safe integer.
+///
+/// Function arguments and results avoid this cost by passing unwrapped ints
+/// to functions that are wrapped with [`with_big_int_params`] or
+/// [`with_big_int_results`], see [`to_py_unwrapped`]. Tables cannot contain
+/// `i64`s, so the only remaining callers are [`WasmGlobal::new`] and
+/// [`WasmGlobal::set`], which convert a single value per call.
+///
+/// [`WasmGlobal::new`]: wasm_runtime_layer::backend::WasmGlobal::new
+/// [`WasmGlobal::set`]: wasm_runtime_layer::backend::WasmGlobal::set
 fn i64_to_js_bigint(py: Python, v: i64) -> Bound<PyAny> {
-    fn object_wrapped_bigint(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-        static OBJECT_WRAPPED_BIGINT: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-
-        OBJECT_WRAPPED_BIGINT
-            .get_or_try_init(py, || {
-                Ok(py
-                    .import(intern!(py, "pyodide"))?
-                    .getattr(intern!(py, "code"))?
-                    .getattr(intern!(py, "run_js"))?
-                    .call1((
-                        "function objectWrappedBigInt(v){ return Object(BigInt(v)); } \
-                         objectWrappedBigInt",
-                    ))?
-                    .unbind())
-            })
-            .map(|x| x.bind(py))
+    // Pyodide already converts all larger ints into BigInts
+    if !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v) {
+        return v
+            .into_pyobject(py)
+            .map_or_else(|err| match err {}, Bound::into_any);
     }
 
-    let bigint = (|| object_wrapped_bigint(py)?.call1((v,)))();
+    let bigint = (|| object_wrapped_big_int(py)?.call1((v,)))();
 
     bigint.expect("conversion from i64 to Object(BigInt(v)) should not fail")
 }
 
-fn try_i64_from_js_bigint(v: Bound<PyAny>) -> Result<i64, PyErr> {
-    fn js_bigint(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-        static JS_BIG_INT: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-        JS_BIG_INT.import(py, "js", "BigInt")
+/// Converts a JavaScript number, `BigInt`, or `Object`-wrapped `BigInt`
+/// into an `i64`
+pub fn try_i64_from_js_bigint(v: Bound<PyAny>) -> Result<i64, PyErr> {
+    // Pyodide already converts BigInts into ints
+    if let Ok(v) = v.downcast::<PyInt>() {
+        return v.extract();
     }
 
     // First wrap inside a BigInt to force coersion, then try to convert into an i64
-    js_bigint(v.py())?.call1((v,))?.extract()
+    big_int(v.py())?.call1((v,))?.extract()
 }
 
-pub fn js_uint8_array_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static JS_UINT8_ARRAY_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    JS_UINT8_ARRAY_NEW.import(py, "js.Uint8Array", "new")
+/// Converts the `value` to Python like [`ToPy::to_py`], but keeps an `i64` as
+/// a Python int, which JavaScript receives as a number or a `BigInt`.
+///
+/// The `i64`s must then be converted into `BigInt`s inside JavaScript, e.g.
+/// by wrapping the function that receives them with [`with_big_int_params`]
+/// or [`with_big_int_results`]. Unlike [`ToPy::to_py`], this requires no
+/// call into JavaScript for every `i64`.
+pub fn to_py_unwrapped(py: Python, value: &Value<Engine>) -> Py<PyAny> {
+    match value {
+        Value::I64(v) => v
+            .into_pyobject(py)
+            .map_or_else(|err| match err {}, |v| v.into_any().unbind()),
+        value => value.to_py(py),
+    }
 }
 
-/// Check if `object` is an instance of the JavaScript class with `constructor`.
-pub fn instanceof(object: &Bound<PyAny>, constructor: &Bound<PyAny>) -> Result<bool, PyErr> {
-    fn is_instance_of(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-        static IS_INSTANCE_OF: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-
-        IS_INSTANCE_OF
-            .get_or_try_init(py, || {
-                Ok(py
-                    .import(intern!(py, "pyodide"))?
-                    .getattr(intern!(py, "code"))?
-                    .getattr(intern!(py, "run_js"))?
-                    .call1((
-                        "function isInstanceOf(object, constructor){ return (object instanceof \
-                         constructor); } isInstanceOf",
-                    ))?
-                    .unbind())
-            })
-            .map(|x| x.bind(py))
+/// Wraps the JavaScript function `func` of type `ty` such that its `i64`
+/// parameters can be passed from [`to_py_unwrapped`]
+pub fn with_big_int_params<'py>(
+    func: &Bound<'py, PyAny>,
+    ty: &FuncType,
+) -> Result<Bound<'py, PyAny>, PyErr> {
+    let py = func.py();
+    let indices = i64_indices(py, ty.params());
+
+    if indices.is_empty() {
+        return Ok(func.clone());
+    }
+
+    let mut args = vec![func.clone().unbind()];
+    args.extend(indices);
+
+    js::big_int_params(py)?.call1(PyTuple::new(py, args)?)
+}
+
+/// Wraps the JavaScript function `func` of type `ty` such that its `i64`
+/// results can be returned from [`to_py_unwrapped`]
+pub fn with_big_int_results<'py>(
+    func: &Bound<'py, PyAny>,
+    ty: &FuncType,
+) -> Result<Bound<'py, PyAny>, PyErr> {
+    let py = func.py();
+    let indices = i64_indices(py, ty.results());
+
+    if indices.is_empty() {
+        return Ok(func.clone());
+    }
+
+    let single = ty.results().len() == 1;
+
+    let mut args = vec![
+        func.clone().unbind(),
+        PyBool::new(py, single).to_owned().into_any().unbind(),
+    ];
+    args.extend(indices);
+
+    js::big_int_results(py)?.call1(PyTuple::new(py, args)?)
+}
+
+/// Returns the indices of the `i64`s in `tys` as Python ints
+fn i64_indices(py: Python, tys: &[ValueType]) -> Vec<Py<PyAny>> {
+    tys.iter()
+        .enumerate()
+        .filter(|(_, ty)| **ty == ValueType::I64)
+        .map(|(index, _)| {
+            index
+                .into_pyobject(py)
+                .map_or_else(|err| match err {}, |index| index.into_any().unbind())
+        })
+        .collect()
+}
+
+/// Copies `bytes` into a new JavaScript `Uint8Array`.
+///
+/// The bytes are copied directly into the array through a Python `memoryview`,
+/// without first being copied into a Python `bytes` object.
+pub fn js_uint8_array_from_slice<'py>(
+    py: Python<'py>,
+    bytes: &[u8],
+) -> Result<Bound<'py, PyAny>, PyErr> {
+    let array = uint8_array_new(py)?.call1((bytes.len(),))?;
+
+    // Safety: the memoryview only exists for the duration of the closure
+    unsafe {
+        with_py_memoryview(
+            py,
+            bytes.as_ptr().cast_mut(),
+            bytes.len(),
+            ffi::PyBUF_READ,
+            |view| array.call_method1(intern!(py, "assign"), (view,)),
+        )
+    }?;
+
+    Ok(array)
+}
+
+/// Creates a `Uint8Array` view of the JavaScript `ArrayBuffer` or
+/// `ArrayBuffer` view `buffer`, without copying its contents.
+pub fn js_uint8_array_view<'py>(buffer: &Bound<'py, PyAny>) -> Result<Bound<'py, PyAny>, PyErr> {
+    let py = buffer.py();
+
+    if instanceof(buffer, array_buffer(py)?)? {
+        return uint8_array_new(py)?.call1((buffer,));
     }
 
+    if array_buffer_is_view(py)?.call1((buffer,))?.extract()? {
+        return uint8_array_new(py)?.call1((
+            buffer.getattr(intern!(py, "buffer"))?,
+            buffer.getattr(intern!(py, "byteOffset"))?,
+            buffer.getattr(intern!(py, "byteLength"))?,
+        ));
+    }
+
+    Err(PyTypeError::new_err(
+        "expected a JavaScript ArrayBuffer or ArrayBuffer view",
+    ))
+}
+
+/// Copies the contents of the JavaScript `Uint8Array` `array` into a new
+/// [`Vec`], without going through a Python `bytes` object.
+pub fn js_uint8_array_to_vec(array: &Bound<PyAny>) -> Result<Vec<u8>, PyErr> {
+    let py = array.py();
+
+    let mut bytes = vec![0_u8; array.getattr(intern!(py, "length"))?.extract()?];
+
+    // Safety: the memoryview only exists for the duration of the closure
+    unsafe {
+        with_py_memoryview(
+            py,
+            bytes.as_mut_ptr(),
+            bytes.len(),
+            ffi::PyBUF_WRITE,
+            |view| array.call_method1(intern!(py, "assign_to"), (view,)),
+        )
+    }?;
+
+    Ok(bytes)
+}
+
+/// Calls `f` with a Python `memoryview` over the `len` bytes at `data`, which
+/// is released afterwards.
+///
+/// # Safety
+///
+/// `data` must be valid for reads of `len` bytes, and for writes if `flags`
+/// is [`ffi::PyBUF_WRITE`], for the duration of the call.
+unsafe fn with_py_memoryview<'py, T>(
+    py: Python<'py>,
+    data: *mut u8,
+    len: usize,
+    flags: std::os::raw::c_int,
+    f: impl FnOnce(&Bound<'py, PyAny>) -> Result<T, PyErr>,
+) -> Result<T, PyErr> {
+    let len = ffi::Py_ssize_t::try_from(len)
+        .map_err(|_| PyValueError::new_err("the buffer is too large for a memoryview"))?;
+
+    // Safety: the caller guarantees that the memory is valid
+    let view = unsafe {
+        Bound::from_owned_ptr_or_err(py, ffi::PyMemoryView_FromMemory(data.cast(), len, flags))
+    }?;
+
+    let result = f(&view);
+
+    // releasing the memoryview ensures that it cannot outlive the memory,
+    // even if a reference to it was retained
+    view.call_method0(intern!(py, "release"))?;
+
+    result
+}
+
+/// Check if `object` is an instance of the JavaScript class with `constructor`.
+pub fn instanceof(object: &Bound<PyAny>, constructor: &Bound<PyAny>) -> Result<bool, PyErr> {
     is_instance_of(object.py())?
         .call1((object, constructor))?
         .extract()
 }
 
 pub fn create_js_object(py: Python) -> Result<Bound<PyAny>, PyErr> {
-    fn js_object_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-        static JS_OBJECT_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-        JS_OBJECT_NEW.import(py, "js.Object", "new")
-    }
-
-    js_object_new(py)?.call0()
+    object_new(py)?.call0()
 }
 
 pub fn py_to_js_proxy<T>(object: Bound<T>) -> Result<Bound<PyAny>, PyErr> {
-    fn to_js(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-        static TO_JS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-        TO_JS.import(py, "pyodide.ffi", "to_js")
-    }
-
     let py = object.py();
 
     to_js(py)?.call(
@@ -218,3 +386,122 @@ pub fn py_to_js_proxy<T>(object: Bound<T>) -> Result<Bound<PyAny>, PyErr> {
         Some(&[(intern!(py, "create_pyproxies"), true)].into_py_dict(py)?),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use wasm_runtime_layer::{Extern, Func, Global, Imports, Instance, Module, Value};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn i64_boundary_values() {
+        let mut store = test_utils::store();
+
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (import "env" "swap" (func $swap (param i64 i32 i64) (result i64 i32 i64)))
+                (import "env" "negate" (func $negate (param i64) (result i64)))
+                (func (export "swap") (param i64 i32 i64) (result i64 i32 i64)
+                    (call $swap (local.get 0) (local.get 1) (local.get 2)))
+                (func (export "negate") (param i64) (result i64)
+                    (call $negate (local.get 0)))
+            )"#,
+        )
+        .unwrap();
+        let module = Module::new(store.engine(), bytes.as_slice()).unwrap();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let swap = Func::new(
+            &mut store,
+            FuncType::new(
+                [ValueType::I64, ValueType::I32, ValueType::I64],
+                [ValueType::I64, ValueType::I32, ValueType::I64],
+            ),
+            move |_caller, args, results| {
+                let (Value::I64(a), Value::I64(b)) = (&args[0], &args[2]) else {
+                    anyhow::bail!("expected i64 arguments");
+                };
+                received_clone.lock().unwrap().push((*a, *b));
+                results[0] = args[2].clone();
+                results[1] = args[1].clone();
+                results[2] = args[0].clone();
+                Ok(())
+            },
+        );
+        let negate = Func::new(
+            &mut store,
+            FuncType::new([ValueType::I64], [ValueType::I64]),
+            |_caller, args, results| {
+                let Value::I64(a) = args[0] else {
+                    anyhow::bail!("expected an i64 argument");
+                };
+                results[0] = Value::I64(a.wrapping_neg());
+                Ok(())
+            },
+        );
+
+        let mut imports = Imports::new();
+        imports.define("env", "swap", Extern::Func(swap.clone()));
+        imports.define("env", "negate", Extern::Func(negate.clone()));
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let export = |name| {
+            instance
+                .get_export(&store, name)
+                .and_then(Extern::into_func)
+        };
+        let (guest_swap, guest_negate) = (export("swap").unwrap(), export("negate").unwrap());
+
+        for value in [
+            i64::MIN,
+            i64::MIN + 1,
+            -MAX_SAFE_INTEGER - 2,
+            -MAX_SAFE_INTEGER - 1,
+            -MAX_SAFE_INTEGER,
+            -1,
+            0,
+            1,
+            MAX_SAFE_INTEGER,
+            MAX_SAFE_INTEGER + 1,
+            MAX_SAFE_INTEGER + 2,
+            i64::MAX - 1,
+            i64::MAX,
+        ] {
+            let other = !value;
+
+            for func in [&guest_swap, &swap] {
+                let mut results = [Value::I64(0), Value::I32(0), Value::I64(0)];
+                func.call(
+                    &mut store,
+                    &[Value::I64(value), Value::I32(-1), Value::I64(other)],
+                    &mut results,
+                )
+                .unwrap();
+                assert!(
+                    matches!(results, [Value::I64(a), Value::I32(-1), Value::I64(b)] if a == other && b == value),
+                    "{value} and {other} were swapped into {results:?}"
+                );
+            }
+            assert_eq!(
+                received.lock().unwrap().split_off(0),
+                [(value, other), (value, other)]
+            );
+
+            for func in [&guest_negate, &negate] {
+                let mut results = [Value::I64(0)];
+                func.call(&mut store, &[Value::I64(value)], &mut results)
+                    .unwrap();
+                assert!(matches!(results, [Value::I64(a)] if a == value.wrapping_neg()));
+            }
+
+            let global = Global::new(&mut store, Value::I64(value), true);
+            assert!(matches!(global.get(&mut store), Value::I64(a) if a == value));
+            global.set(&mut store, Value::I64(other)).unwrap();
+            assert!(matches!(global.get(&mut store), Value::I64(a) if a == other));
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use std::{fmt, sync::Arc};
+
+use fxhash::FxHashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A location in the source code of a WASM guest.
+pub struct SourceLocation {
+    /// The path of the source file
+    file: Arc<str>,
+    /// The one-based line number, if known
+    line: Option<u32>,
+    /// The one-based column number, if known
+    column: Option<u32>,
+}
+
+impl SourceLocation {
+    #[must_use]
+    /// Returns the path of the source file.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    #[must_use]
+    /// Returns the one-based line number, if known.
+    pub const fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    #[must_use]
+    /// Returns the one-based column number, if known.
+    pub const fn column(&self) -> Option<u32> {
+        self.column
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.file)?;
+
+        if let Some(line) = self.line {
+            write!(fmt, ":{line}")?;
+
+            if let Some(column) = self.column {
+                write!(fmt, ":{column}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Debug information that maps byte offsets in a module to source locations
+pub struct DebugInfo {
+    /// Rows that start at a module offset, sorted by their offset, where
+    /// `None` marks the end of a sequence of rows
+    rows: Vec<(usize, Option<SourceLocation>)>,
+}
+
+impl DebugInfo {
+    /// Returns the source location of the instruction at the module `offset`
+    pub fn location(&self, offset: usize) -> Option<&SourceLocation> {
+        let index = self.rows.partition_point(|(start, _)| *start <= offset);
+
+        self.rows[..index]
+            .last()
+            .and_then(|(_, location)| location.as_ref())
+    }
+
+    /// Reads the DWARF line tables from the module's `custom_sections`, where
+    /// DWARF addresses are relative to the start of the code section at the
+    /// module offset `code_section_start`
+    ///
+    /// Returns `Ok(None)` if the module has no DWARF line tables.
+    pub fn from_dwarf(
+        custom_sections: &[(String, Vec<u8>)],
+        code_section_start: usize,
+    ) -> anyhow::Result<Option<Self>> {
+        if !custom_sections
+            .iter()
+            .any(|(name, _)| name == gimli::SectionId::DebugLine.name())
+        {
+            return Ok(None);
+        }
+
+        let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+            let data = custom_sections
+                .iter()
+                .find(|(name, _)| name == id.name())
+                .map_or(&[][..], |(_, data)| data.as_slice());
+
+            Ok(gimli::EndianSlice::new(data, gimli::LittleEndian))
+        })?;
+
+        let mut rows = Vec::new();
+
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+
+            let mut files = FxHashMap::default();
+
+            let mut program_rows = program.rows();
+            while let Some((header, row)) = program_rows.next_row()? {
+                let offset = usize::try_from(row.address())?.saturating_add(code_section_start);
+
+                if row.end_sequence() {
+                    rows.push((offset, None));
+                    continue;
+                }
+
+                let file = if let Some(file) = files.get(&row.file_index()) {
+                    Arc::clone(file)
+                } else {
+                    let file: Arc<str> = match row.file(header) {
+                        Some(file) => Arc::from(file_path(&dwarf, &unit, header, file)?),
+                        None => Arc::from("<unknown>"),
+                    };
+                    files.insert(row.file_index(), Arc::clone(&file));
+                    file
+                };
+
+                let line = row.line().and_then(|line| u32::try_from(line.get()).ok());
+                let column = match row.column() {
+                    gimli::ColumnType::LeftEdge => None,
+                    gimli::ColumnType::Column(column) => u32::try_from(column.get()).ok(),
+                };
+
+                rows.push((offset, Some(SourceLocation { file, line, column })));
+            }
+        }
+
+        // the stable sort keeps the end of a sequence before the start of the
+        // next sequence at the same offset
+        rows.sort_by_key(|(offset, _)| *offset);
+
+        Ok(Some(Self { rows }))
+    }
+
+    /// Parses a [source map] for a WASM module, in which the generated
+    /// columns are byte offsets in the module
+    ///
+    /// [source map]: https://tc39.es/source-map/
+    pub fn from_source_map(source_map: &str) -> anyhow::Result<Self> {
+        let source_map: serde_json::Value = serde_json::from_str(source_map)?;
+
+        if source_map
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            != Some(3)
+        {
+            anyhow::bail!("only version 3 source maps are supported");
+        }
+
+        let source_root = source_map
+            .get("sourceRoot")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("");
+
+        let sources = source_map
+            .get("sources")
+            .and_then(serde_json::Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("the source map has no sources"))?
+            .iter()
+            .map(|source| {
+                let source = source.as_str().unwrap_or("<unknown>");
+                if source_root.is_empty() {
+                    Arc::from(source)
+                } else {
+                    Arc::from(format!("{}/{source}", source_root.trim_end_matches('/')))
+                }
+            })
+            .collect::<Vec<Arc<str>>>();
+
+        let mappings = source_map
+            .get("mappings")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("the source map has no mappings"))?;
+
+        let mut rows = Vec::new();
+
+        let mut source = 0_i64;
+        let mut line = 0_i64;
+        let mut column = 0_i64;
+
+        for generated_line in mappings.split(';') {
+            let mut offset = 0_i64;
+
+            for segment in generated_line.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq_segment(segment)?;
+
+                offset += fields[0];
+                let module_offset = usize::try_from(offset)?;
+
+                if fields.len() < 4 {
+                    rows.push((module_offset, None));
+                    continue;
+                }
+
+                source += fields[1];
+                line += fields[2];
+                column += fields[3];
+
+                let file = sources
+                    .get(usize::try_from(source)?)
+                    .ok_or_else(|| anyhow::anyhow!("the source map has an invalid source"))?;
+
+                rows.push((
+                    module_offset,
+                    Some(SourceLocation {
+                        file: Arc::clone(file),
+                        line: u32::try_from(line + 1).ok(),
+                        column: u32::try_from(column + 1).ok(),
+                    }),
+                ));
+            }
+        }
+
+        rows.sort_by_key(|(offset, _)| *offset);
+
+        Ok(Self { rows })
+    }
+}
+
+/// Returns the full path of a DWARF line program `file`
+fn file_path<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    header: &gimli::LineProgramHeader<R>,
+    file: &gimli::FileEntry<R>,
+) -> anyhow::Result<String> {
+    let name = dwarf.attr_string(unit, file.path_name())?;
+    let name = name.to_string_lossy()?;
+
+    if name.starts_with('/') {
+        return Ok(name.into_owned());
+    }
+
+    let directory = match file.directory(header) {
+        Some(directory) => dwarf
+            .attr_string(unit, directory)?
+            .to_string_lossy()?
+            .into_owned(),
+        None => String::new(),
+    };
+
+    if directory.is_empty() {
+        Ok(name.into_owned())
+    } else {
+        Ok(format!("{}/{name}", directory.trim_end_matches('/')))
+    }
+}
+
+/// Decodes a base64 VLQ `segment` of a source map's mappings
+fn decode_vlq_segment(segment: &str) -> anyhow::Result<Vec<i64>> {
+    let mut fields = Vec::new();
+
+    let mut value = 0_i64;
+    let mut shift = 0;
+
+    for byte in segment.bytes() {
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => anyhow::bail!("the source map has invalid mappings"),
+        };
+
+        if shift > 60 {
+            anyhow::bail!("the source map has invalid mappings");
+        }
+
+        value |= i64::from(digit & 0b1_1111) << shift;
+        shift += 5;
+
+        if digit & 0b10_0000 == 0 {
+            let negative = value & 1 == 1;
+            value >>= 1;
+            fields.push(if negative { -value } else { value });
+
+            value = 0;
+            shift = 0;
+        }
+    }
+
+    if shift != 0 || fields.is_empty() {
+        anyhow::bail!("the source map has invalid mappings");
+    }
+
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::backend::WasmModule;
+
+    use super::*;
+    use crate::{test_utils, Config};
+
+    #[test]
+    fn source_map_locations() {
+        // offsets 0x20, 0x25 and 0x30 map to lib.rs 3:5, 4:1 and main.rs 1:1
+        let source_map = r#"{
+            "version": 3,
+            "sources": ["lib.rs", "main.rs"],
+            "names": [],
+            "mappings": "gCAEI,KACJ,WCHA"
+        }"#;
+
+        let debug_info = DebugInfo::from_source_map(source_map).unwrap();
+
+        assert_eq!(debug_info.location(0x10), None);
+        assert_eq!(
+            debug_info
+                .location(0x22)
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("lib.rs:3:5")
+        );
+        assert_eq!(
+            debug_info
+                .location(0x25)
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("lib.rs:4:1")
+        );
+        assert_eq!(
+            debug_info
+                .location(0x100)
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("main.rs:1:1")
+        );
+    }
+
+    #[test]
+    fn dwarf_locations() {
+        let mut bytes =
+            wat::parse_str(r#"(module (func (export "trap") nop unreachable))"#).unwrap();
+
+        // find the offset of the unreachable instruction in the code section
+        let mut code_section_start = 0;
+        let mut unreachable = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+            match payload.unwrap() {
+                wasmparser::Payload::CodeSectionStart { range, .. } => {
+                    code_section_start = range.start;
+                },
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    let mut reader = body.get_operators_reader().unwrap();
+                    while !reader.eof() {
+                        let offset = reader.original_position();
+                        if reader.read().unwrap() == wasmparser::Operator::Unreachable {
+                            unreachable = offset - code_section_start;
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // the line table maps the function to lib.rs:1 and the unreachable
+        // instruction to lib.rs:3:5
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut program = gimli::write::LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            gimli::write::LineString::String(b"/src".to_vec()),
+            gimli::write::LineString::String(b"lib.rs".to_vec()),
+            None,
+        );
+        let directory = program.default_directory();
+        let file = program.add_file(
+            gimli::write::LineString::String(b"lib.rs".to_vec()),
+            directory,
+            None,
+        );
+        program.begin_sequence(Some(gimli::write::Address::Constant(0)));
+        program.row().file = file;
+        program.row().line = 1;
+        program.generate_row();
+        program.row().address_offset = unreachable as u64;
+        program.row().line = 3;
+        program.row().column = 5;
+        program.generate_row();
+        program.end_sequence(unreachable as u64 + 1);
+
+        // the compilation directory is the default directory of DWARF 4
+        let mut dwarf = gimli::write::DwarfUnit::new(encoding);
+        dwarf.unit.line_program = program;
+        let root = dwarf.unit.root();
+        dwarf.unit.get_mut(root).set(
+            gimli::DW_AT_comp_dir,
+            gimli::write::AttributeValue::String(b"/src".to_vec()),
+        );
+        let mut sections =
+            gimli::write::Sections::new(gimli::write::EndianVec::new(gimli::LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+        sections
+            .for_each(|id, data| -> Result<(), std::convert::Infallible> {
+                if !data.slice().is_empty() {
+                    wasm_encoder::Section::append_to(
+                        &wasm_encoder::CustomSection {
+                            name: id.name().into(),
+                            data: data.slice().into(),
+                        },
+                        &mut bytes,
+                    );
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        let mut config = Config::new();
+        config.debug_info(true);
+        let store = test_utils::store_with(&config, ());
+
+        let module =
+            crate::Module::new(&store.engine().clone().into_backend(), bytes.as_slice()).unwrap();
+
+        // JavaScript stack traces contain the module offset of each frame
+        let stack = format!(
+            "RuntimeError: unreachable\n    at wasm://wasm/5e6f7a8b:wasm-function[0]:{:#x}",
+            code_section_start + unreachable
+        );
+        let backtrace = module.symbolize(&stack);
+        let location = backtrace.frames()[0].location().unwrap();
+        assert_eq!(location.file(), "/src/lib.rs");
+        assert_eq!((location.line(), location.column()), (Some(3), Some(5)));
+    }
+}
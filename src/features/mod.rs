@@ -3,7 +3,7 @@ use std::{error::Error, fmt};
 use flagset::FlagSet;
 use pyo3::{prelude::*, sync::GILOnceCell};
 
-use crate::conversion::js_uint8_array_new;
+use crate::js::{uint8_array_new, web_assembly_module_new, web_assembly_validate};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UnsupportedWasmFeatureExtensionError {
@@ -171,13 +171,13 @@ impl WasmFeatureExtension {
     }
 
     fn try_validate_wasm_bytes(py: Python, bytes: &[u8]) -> Result<bool, PyErr> {
-        let buffer = js_uint8_array_new(py)?.call1((bytes,))?;
+        let buffer = uint8_array_new(py)?.call1((bytes,))?;
         let valid = web_assembly_validate(py)?.call1((buffer,))?.extract()?;
         Ok(valid)
     }
 
     fn try_create_wasm_module_from_bytes(py: Python, bytes: &[u8]) -> Result<bool, PyErr> {
-        let buffer = js_uint8_array_new(py)?.call1((bytes,))?;
+        let buffer = uint8_array_new(py)?.call1((bytes,))?;
         let module = web_assembly_module_new(py)?.call1((buffer,));
         Ok(module.is_ok())
     }
@@ -210,16 +210,6 @@ impl fmt::Display for WasmFeatureExtension {
     }
 }
 
-fn web_assembly_validate(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_VALIDATE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_VALIDATE.import(py, "js.WebAssembly", "validate")
-}
-
-fn web_assembly_module_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_MODULE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_MODULE.import(py, "js.WebAssembly.Module", "new")
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,606 @@
+//! Bit-preserving transfer of floats across the JavaScript boundary
+//!
+//! WebAssembly `f32` and `f64` values become JavaScript numbers whenever they
+//! are passed to or from JavaScript. An `f32` is promoted to an `f64` on the
+//! way, which quiets signalling NaNs, and JavaScript engines may canonicalize
+//! NaNs altogether, so NaN payloads can be lost. Inside WebAssembly, however,
+//! reinterpreting a float as an integer of the same width, calling another
+//! WebAssembly function, and accessing a global all preserve every bit.
+//!
+//! The small adapter modules that are generated here therefore only pass the
+//! integer bit patterns of floats through JavaScript and reinterpret them
+//! inside WebAssembly. Adapter modules are compiled once per signature and
+//! then cached, while each adapter instance is cached by its user.
+
+use std::sync::Mutex;
+
+use fxhash::FxHashMap;
+use pyo3::{intern, prelude::*, sync::GILOnceCell};
+use wasm_encoder::{
+    CodeSection, ConstExpr, EntityType, ExportKind, ExportSection, Function, FunctionSection,
+    GlobalSection, ImportSection, Instruction, Module, NameSection, TypeSection, ValType,
+};
+use wasm_runtime_layer::{backend::Value, FuncType, GlobalType, ValueType};
+
+use crate::{
+    conversion::{create_js_object, to_py_unwrapped, ToPy, ValueExt},
+    js::{uint8_array_new, web_assembly_instance_new, web_assembly_module_new},
+    Engine,
+};
+
+/// The name of all adapter modules, by which their frames are recognized in
+/// JavaScript stack traces
+pub const ADAPTER_MODULE_NAME: &str = "__pyodide_webassembly_runtime_layer_float_bits";
+
+/// The module name of the import of an adapter module
+const ADAPTER_MODULE: &str = "adapter";
+
+/// The name of the imported or exported item of an adapter module
+const ADAPTER_ITEM: &str = "item";
+
+/// The export name of the getter of a global accessor module
+const GETTER: &str = "get";
+
+/// The export name of the setter of a global accessor module
+const SETTER: &str = "set";
+
+/// Returns the integer type that carries the bits of values of type `ty`
+pub const fn bits_type(ty: ValueType) -> ValueType {
+    match ty {
+        ValueType::F32 => ValueType::I32,
+        ValueType::F64 => ValueType::I64,
+        ty => ty,
+    }
+}
+
+/// Returns whether `ty` is a float type
+pub const fn is_float(ty: ValueType) -> bool {
+    matches!(ty, ValueType::F32 | ValueType::F64)
+}
+
+/// Returns whether the function type `ty` has any float parameters or
+/// results
+pub fn has_floats(ty: &FuncType) -> bool {
+    ty.params()
+        .iter()
+        .chain(ty.results())
+        .any(|ty| is_float(*ty))
+}
+
+/// Returns the function type in which all floats of `ty` are replaced by
+/// their bits
+pub fn bits_func_type(ty: &FuncType) -> FuncType {
+    FuncType::new(
+        ty.params().iter().copied().map(bits_type),
+        ty.results().iter().copied().map(bits_type),
+    )
+}
+
+/// Returns the bits of `value` if it is a float, or the `value` otherwise
+#[allow(clippy::cast_possible_wrap)]
+pub fn to_bits(value: &Value<Engine>) -> Value<Engine> {
+    match value {
+        Value::F32(value) => Value::I32(value.to_bits() as i32),
+        Value::F64(value) => Value::I64(value.to_bits() as i64),
+        value => value.clone(),
+    }
+}
+
+/// Reinterprets the `bits` as a value of type `ty`, which may be a float
+#[allow(clippy::cast_sign_loss)]
+pub fn from_bits(bits: Value<Engine>, ty: ValueType) -> Value<Engine> {
+    match (bits, ty) {
+        (Value::I32(bits), ValueType::F32) => Value::F32(f32::from_bits(bits as u32)),
+        (Value::I64(bits), ValueType::F64) => Value::F64(f64::from_bits(bits as u64)),
+        (value, _) => value,
+    }
+}
+
+/// Converts the JavaScript `value` into a value of type `ty`, which has been
+/// passed as its bits if `preserve_float_bits` is set
+pub fn from_py(
+    value: Bound<PyAny>,
+    ty: ValueType,
+    preserve_float_bits: bool,
+) -> Result<Value<Engine>, PyErr> {
+    if preserve_float_bits {
+        Value::from_py_typed(value, bits_type(ty)).map(|bits| from_bits(bits, ty))
+    } else {
+        Value::from_py_typed(value, ty)
+    }
+}
+
+/// Converts the `value` with [`to_py_unwrapped`], after replacing it with its
+/// bits if `preserve_float_bits` is set
+pub fn to_py(py: Python, value: &Value<Engine>, preserve_float_bits: bool) -> Py<PyAny> {
+    if preserve_float_bits {
+        to_py_unwrapped(py, &to_bits(value))
+    } else {
+        to_py_unwrapped(py, value)
+    }
+}
+
+/// Wraps the JavaScript function `func`, which takes and returns the bits of
+/// the floats of `ty`, in a WebAssembly function of type `ty`
+pub fn lift<'py>(func: &Bound<'py, PyAny>, ty: &FuncType) -> Result<Bound<'py, PyAny>, PyErr> {
+    let bytes = func_adapter(&bits_func_type(ty), ty);
+    instantiate(func, &bytes)?.getattr(ADAPTER_ITEM)
+}
+
+/// Wraps the function `func` of type `ty` in a WebAssembly function that
+/// takes and returns the bits of its floats
+pub fn lower<'py>(func: &Bound<'py, PyAny>, ty: &FuncType) -> Result<Bound<'py, PyAny>, PyErr> {
+    let bytes = func_adapter(ty, &bits_func_type(ty));
+    instantiate(func, &bytes)?.getattr(ADAPTER_ITEM)
+}
+
+/// Creates a new immutable [`WebAssembly.Global`] with the float `value`
+///
+/// Mutable globals should instead be created with a zero value, which crosses
+/// the JavaScript boundary unchanged, and then be set through their
+/// [`global_accessor`].
+///
+/// [`WebAssembly.Global`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Global
+pub fn new_immutable_global<'py>(
+    py: Python<'py>,
+    value: &Value<Engine>,
+) -> Result<Bound<'py, PyAny>, PyErr> {
+    let (val_type, init) = match value {
+        Value::F32(value) => (ValType::F32, ConstExpr::f32_const(*value)),
+        Value::F64(value) => (ValType::F64, ConstExpr::f64_const(*value)),
+        value => unreachable!("{value:?} is not a float"),
+    };
+
+    let mut globals = GlobalSection::new();
+    globals.global(
+        wasm_encoder::GlobalType {
+            val_type,
+            mutable: false,
+            shared: false,
+        },
+        &init,
+    );
+
+    let mut exports = ExportSection::new();
+    exports.export(ADAPTER_ITEM, ExportKind::Global, 0);
+
+    let mut module = Module::new();
+    module.section(&globals).section(&exports);
+
+    // every initial value requires a different module, so it is not cached
+    let buffer = uint8_array_new(py)?.call1((module.finish(),))?;
+    let module = web_assembly_module_new(py)?.call1((buffer,))?;
+    let instance = web_assembly_instance_new(py)?.call1((module,))?;
+
+    instance
+        .getattr(intern!(py, "exports"))?
+        .getattr(ADAPTER_ITEM)
+}
+
+/// Instantiates an accessor for the float `global` of type `ty` and returns
+/// its exports, which are used with [`get_global`] and [`set_global`]
+pub fn global_accessor<'py>(
+    global: &Bound<'py, PyAny>,
+    ty: GlobalType,
+) -> Result<Bound<'py, PyAny>, PyErr> {
+    instantiate(global, &global_accessor_module(ty))
+}
+
+/// Returns the current value of the float global of type `ty` through its
+/// `accessor`
+pub fn get_global(accessor: &Bound<PyAny>, ty: GlobalType) -> Result<Value<Engine>, PyErr> {
+    let bits = accessor.call_method0(GETTER)?;
+
+    from_py(bits, ty.content(), true)
+}
+
+/// Sets the value of the mutable float global through its `accessor`
+pub fn set_global(accessor: &Bound<PyAny>, value: &Value<Engine>) -> Result<(), PyErr> {
+    let bits = to_bits(value).to_py(accessor.py());
+
+    accessor.call_method1(SETTER, (bits,))?;
+
+    Ok(())
+}
+
+/// Instantiates the adapter module `bytes` with its `import` and returns the
+/// exports of the instance
+fn instantiate<'py>(import: &Bound<'py, PyAny>, bytes: &[u8]) -> Result<Bound<'py, PyAny>, PyErr> {
+    static ADAPTERS: GILOnceCell<Mutex<FxHashMap<Vec<u8>, Py<PyAny>>>> = GILOnceCell::new();
+
+    let py = import.py();
+
+    let adapters = ADAPTERS.get_or_init(py, || Mutex::new(FxHashMap::default()));
+
+    let cached = adapters
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .get(bytes)
+        .map(|module| module.clone_ref(py));
+
+    let module = if let Some(module) = cached {
+        module.into_bound(py)
+    } else {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(len = bytes.len(), "compiling float bits adapter");
+
+        let buffer = uint8_array_new(py)?.call1((bytes,))?;
+        let module = web_assembly_module_new(py)?.call1((buffer,))?;
+
+        adapters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(bytes.to_vec(), module.clone().unbind());
+
+        module
+    };
+
+    let imports = create_js_object(py)?;
+    let adapter = create_js_object(py)?;
+    adapter.setattr(ADAPTER_ITEM, import)?;
+    imports.setattr(ADAPTER_MODULE, adapter)?;
+
+    web_assembly_instance_new(py)?
+        .call1((module, imports))?
+        .getattr(intern!(py, "exports"))
+}
+
+/// Generates an adapter module that imports a function of type `import` and
+/// exports a function of type `export`, which forwards all parameters and
+/// results and reinterprets those whose types differ
+fn func_adapter(import: &FuncType, export: &FuncType) -> Vec<u8> {
+    let mut types = TypeSection::new();
+    types
+        .ty()
+        .function(val_types(import.params()), val_types(import.results()));
+    types
+        .ty()
+        .function(val_types(export.params()), val_types(export.results()));
+
+    let mut imports = ImportSection::new();
+    imports.import(ADAPTER_MODULE, ADAPTER_ITEM, EntityType::Function(0));
+
+    let mut functions = FunctionSection::new();
+    functions.function(1);
+
+    let mut exports = ExportSection::new();
+    exports.export(ADAPTER_ITEM, ExportKind::Func, 1);
+
+    // multiple results are popped into locals in reverse, and then pushed
+    // again in order after they have been reinterpreted
+    let params = export.params().len();
+    let mut body = if import.results().len() > 1 {
+        Function::new_with_locals_types(val_types(import.results()))
+    } else {
+        Function::new([])
+    };
+
+    for (index, (from, to)) in export.params().iter().zip(import.params()).enumerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        body.instruction(&Instruction::LocalGet(index as u32));
+        reinterpret(&mut body, *from, *to);
+    }
+
+    body.instruction(&Instruction::Call(0));
+
+    match (import.results(), export.results()) {
+        ([from], [to]) => reinterpret(&mut body, *from, *to),
+        (from, to) => {
+            #[allow(clippy::cast_possible_truncation)]
+            let local = |index: usize| (params + index) as u32;
+
+            for index in (0..from.len()).rev() {
+                body.instruction(&Instruction::LocalSet(local(index)));
+            }
+            for (index, (from, to)) in from.iter().zip(to).enumerate() {
+                body.instruction(&Instruction::LocalGet(local(index)));
+                reinterpret(&mut body, *from, *to);
+            }
+        },
+    }
+
+    body.instruction(&Instruction::End);
+
+    let mut code = CodeSection::new();
+    code.function(&body);
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&exports)
+        .section(&code)
+        .section(&adapter_names());
+    module.finish()
+}
+
+/// Generates an accessor module that imports a global of type `ty` and
+/// exports a getter and, if the global is mutable, a setter for its bits
+fn global_accessor_module(ty: GlobalType) -> Vec<u8> {
+    let content = ty.content();
+    let bits = val_type(bits_type(content));
+
+    let mut types = TypeSection::new();
+    types.ty().function([], [bits]);
+    types.ty().function([bits], []);
+
+    let mut imports = ImportSection::new();
+    imports.import(
+        ADAPTER_MODULE,
+        ADAPTER_ITEM,
+        EntityType::Global(wasm_encoder::GlobalType {
+            val_type: val_type(content),
+            mutable: ty.mutable(),
+            shared: false,
+        }),
+    );
+
+    let mut functions = FunctionSection::new();
+    let mut exports = ExportSection::new();
+    let mut code = CodeSection::new();
+
+    functions.function(0);
+    exports.export(GETTER, ExportKind::Func, 0);
+    let mut getter = Function::new([]);
+    getter.instruction(&Instruction::GlobalGet(0));
+    reinterpret(&mut getter, content, bits_type(content));
+    getter.instruction(&Instruction::End);
+    code.function(&getter);
+
+    if ty.mutable() {
+        functions.function(1);
+        exports.export(SETTER, ExportKind::Func, 1);
+        let mut setter = Function::new([]);
+        setter.instruction(&Instruction::LocalGet(0));
+        reinterpret(&mut setter, bits_type(content), content);
+        setter.instruction(&Instruction::GlobalSet(0));
+        setter.instruction(&Instruction::End);
+        code.function(&setter);
+    }
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&exports)
+        .section(&code)
+        .section(&adapter_names());
+    module.finish()
+}
+
+/// Returns the name section of an adapter module
+fn adapter_names() -> NameSection {
+    let mut names = NameSection::new();
+    names.module(ADAPTER_MODULE_NAME);
+    names
+}
+
+/// Emits the instruction that reinterprets a value of type `from` as type
+/// `to`, if they differ
+fn reinterpret(body: &mut Function, from: ValueType, to: ValueType) {
+    match (from, to) {
+        (ValueType::F32, ValueType::I32) => body.instruction(&Instruction::I32ReinterpretF32),
+        (ValueType::I32, ValueType::F32) => body.instruction(&Instruction::F32ReinterpretI32),
+        (ValueType::F64, ValueType::I64) => body.instruction(&Instruction::I64ReinterpretF64),
+        (ValueType::I64, ValueType::F64) => body.instruction(&Instruction::F64ReinterpretI64),
+        _ => body,
+    };
+}
+
+fn val_types(tys: &[ValueType]) -> impl ExactSizeIterator<Item = ValType> + '_ {
+    tys.iter().copied().map(val_type)
+}
+
+const fn val_type(ty: ValueType) -> ValType {
+    match ty {
+        ValueType::I32 => ValType::I32,
+        ValueType::I64 => ValType::I64,
+        ValueType::F32 => ValType::F32,
+        ValueType::F64 => ValType::F64,
+        ValueType::FuncRef => ValType::FUNCREF,
+        ValueType::ExternRef => ValType::EXTERNREF,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use wasm_runtime_layer::{
+        backend::WasmModule, Extern, Func, Global, Imports, Instance, Module, Value,
+    };
+
+    use super::*;
+    use crate::{test_utils, Config};
+
+    const F32_NANS: [u32; 7] = [
+        0x7FC0_0000,
+        0xFFC0_0000,
+        0x7F80_0001,
+        0xFF80_0001,
+        0x7FBF_FFFF,
+        0x7FC0_0001,
+        0x7FFF_FFFF,
+    ];
+
+    const F64_NANS: [u64; 7] = [
+        0x7FF8_0000_0000_0000,
+        0xFFF8_0000_0000_0000,
+        0x7FF0_0000_0000_0001,
+        0xFFF0_0000_0000_0001,
+        0x7FF7_FFFF_FFFF_FFFF,
+        0x7FF8_0000_0000_0001,
+        0x7FFF_FFFF_FFFF_FFFF,
+    ];
+
+    const WAT: &str = r#"
+    (module
+        (import "env" "swap" (func $swap (param f32 f64) (result f64 f32)))
+        (global (export "g32") (mut f32) (f32.const 0))
+        (global (export "g64") (mut f64) (f64.const 0))
+        (func (export "identity") (param f32 f64) (result f32 f64)
+            (local.get 0) (local.get 1))
+        (func (export "bits") (param f32 f64) (result i32 i64)
+            (i32.reinterpret_f32 (local.get 0)) (i64.reinterpret_f64 (local.get 1)))
+        (func (export "swap") (param f32 f64) (result f64 f32)
+            (call $swap (local.get 0) (local.get 1)))
+        (func (export "globals") (result i32 i64)
+            (i32.reinterpret_f32 (global.get 0)) (i64.reinterpret_f64 (global.get 1)))
+    )"#;
+
+    fn bits(value: &Value) -> u64 {
+        match value {
+            Value::F32(value) => u64::from(value.to_bits()),
+            Value::F64(value) => value.to_bits(),
+            #[allow(clippy::cast_sign_loss)]
+            Value::I32(value) => u64::from(*value as u32),
+            #[allow(clippy::cast_sign_loss)]
+            Value::I64(value) => *value as u64,
+            value => panic!("{value:?} has no bits"),
+        }
+    }
+
+    fn call(store: &mut test_utils::Store, func: &Func, args: &[Value]) -> [u64; 2] {
+        let mut results = [Value::I32(0), Value::I32(0)];
+        func.call(&mut *store, args, &mut results).unwrap();
+        [bits(&results[0]), bits(&results[1])]
+    }
+
+    #[test]
+    fn nan_bits_round_trip() {
+        let mut store = test_utils::store_with(Config::new().preserve_float_bits(true), ());
+
+        let module = Module::new(store.engine(), wat::parse_str(WAT).unwrap().as_slice()).unwrap();
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        let swap = Func::new(
+            &mut store,
+            FuncType::new(
+                [ValueType::F32, ValueType::F64],
+                [ValueType::F64, ValueType::F32],
+            ),
+            move |_caller, args, results| {
+                received_clone
+                    .lock()
+                    .unwrap()
+                    .push([bits(&args[0]), bits(&args[1])]);
+                results[0] = args[1].clone();
+                results[1] = args[0].clone();
+                Ok(())
+            },
+        );
+
+        let mut imports = Imports::new();
+        imports.define("env", "swap", Extern::Func(swap.clone()));
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let export = |name| instance.get_export(&store, name).unwrap();
+
+        let identity = export("identity").into_func().unwrap();
+        let guest_bits = export("bits").into_func().unwrap();
+        let guest_swap = export("swap").into_func().unwrap();
+        let globals = export("globals").into_func().unwrap();
+        let g32 = export("g32").into_global().unwrap();
+        let g64 = export("g64").into_global().unwrap();
+
+        for (f32_nan, f64_nan) in F32_NANS.into_iter().zip(F64_NANS) {
+            let args = [
+                Value::F32(f32::from_bits(f32_nan)),
+                Value::F64(f64::from_bits(f64_nan)),
+            ];
+            let expected = [u64::from(f32_nan), f64_nan];
+            let swapped = [f64_nan, u64::from(f32_nan)];
+
+            // guest calls
+            assert_eq!(call(&mut store, &identity, &args), expected);
+            assert_eq!(call(&mut store, &guest_bits, &args), expected);
+
+            // host calls from the guest and from the host
+            assert_eq!(call(&mut store, &guest_swap, &args), swapped);
+            assert_eq!(call(&mut store, &swap, &args), swapped);
+            assert_eq!(received.lock().unwrap().split_off(0), [expected, expected]);
+
+            // exported globals
+            g32.set(&mut store, args[0].clone()).unwrap();
+            g64.set(&mut store, args[1].clone()).unwrap();
+            assert_eq!(call(&mut store, &globals, &[]), expected);
+            assert_eq!(bits(&g32.get(&mut store)), expected[0]);
+            assert_eq!(bits(&g64.get(&mut store)), expected[1]);
+
+            // new globals
+            for (value, expected) in args.iter().zip(expected) {
+                for mutable in [false, true] {
+                    let global = Global::new(&mut store, value.clone(), mutable);
+                    assert_eq!(bits(&global.get(&mut store)), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nan_without_preserved_bits() {
+        let mut store = test_utils::store();
+
+        let module = Module::new(store.engine(), wat::parse_str(WAT).unwrap().as_slice()).unwrap();
+        let swap = Func::new(
+            &mut store,
+            FuncType::new(
+                [ValueType::F32, ValueType::F64],
+                [ValueType::F64, ValueType::F32],
+            ),
+            |_caller, _args, _results| Ok(()),
+        );
+        let mut imports = Imports::new();
+        imports.define("env", "swap", Extern::Func(swap));
+        let instance = Instance::new(&mut store, &module, &imports).unwrap();
+        let identity = instance
+            .get_export(&store, "identity")
+            .and_then(Extern::into_func)
+            .unwrap();
+
+        let args = [
+            Value::F32(f32::from_bits(0x7F80_0001)),
+            Value::F64(f64::from_bits(0x7FF0_0000_0000_0001)),
+        ];
+        // the JavaScript API only guarantees that a NaN stays a NaN
+        let mut results = [Value::I32(0), Value::I32(0)];
+        identity.call(&mut store, &args, &mut results).unwrap();
+        assert!(matches!(results, [Value::F32(a), Value::F64(b)] if a.is_nan() && b.is_nan()));
+    }
+
+    #[test]
+    fn backtrace_skips_adapters() {
+        let store = test_utils::store_with(Config::new().preserve_float_bits(true), ());
+
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (func $fail (param f32) (unreachable))
+                (func (export "trap") (param f32) (call $fail (local.get 0)))
+            )"#,
+        )
+        .unwrap();
+        let module =
+            crate::Module::new(&store.engine().clone().into_backend(), bytes.as_slice()).unwrap();
+
+        // the adapter that reinterprets the bits of the f32 parameter calls
+        // the exported function, and is named by its name section
+        let stack = format!(
+            "RuntimeError: unreachable\n    at wasm://wasm/4b1d2c3e:wasm-function[0]:0x2a\n    at \
+             wasm://wasm/4b1d2c3e:wasm-function[1]:0x31\n    at \
+             wasm://wasm/{ADAPTER_MODULE_NAME}-9f8e7d6c:wasm-function[1]:0x4c\n    at \
+             <anonymous>:1:1"
+        );
+        assert_eq!(
+            module
+                .symbolize(&stack)
+                .frames()
+                .iter()
+                .map(crate::FrameInfo::func_index)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+    }
+}
@@ -1,10 +1,16 @@
 use std::{
     any::TypeId,
     marker::PhantomData,
-    sync::{Arc, Weak},
+    sync::{Arc, OnceLock, Weak},
 };
 
-use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyTuple, PyTypeInfo};
+use pyo3::{
+    exceptions::PyRuntimeError,
+    intern,
+    prelude::*,
+    types::{IntoPyDict, PyList, PyTuple},
+    PyTypeInfo,
+};
 use pyo3_error::PyErrChain;
 use wasm_runtime_layer::{
     backend::{AsContext, AsContextMut, Value, WasmFunc, WasmStoreContext},
@@ -13,9 +19,18 @@ use wasm_runtime_layer::{
 use wobbly::sync::Wobbly;
 
 use crate::{
-    conversion::{py_to_js_proxy, ToPy, ValueExt},
+    backtrace::WasmBacktrace,
+    conversion::{
+        instanceof, py_to_js_proxy, to_py_unwrapped, with_big_int_params, with_big_int_results,
+        ToPy, ValueExt,
+    },
+    float_bits,
+    js::{call_batch, function, run_js, to_js},
+    module::ParsedModule,
+    reflection::{func_type_from_js, reflect_type},
     store::StoreContextMut,
-    Engine,
+    typed_func::{TypedFunc, WasmParams, WasmResults},
+    CallFrame, Engine,
 };
 
 /// A bound function, which may be an export from a WASM [`Instance`] or a host
@@ -23,13 +38,21 @@ use crate::{
 ///
 /// [`Instance`]: crate::instance::Instance
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 pub struct Func {
     /// The inner function
     func: Py<PyAny>,
     /// The function signature
     ty: FuncType,
+    /// The export name of the function, if known
+    name: Option<Arc<str>>,
+    /// The module that exports the function, if known
+    module: Option<Arc<ParsedModule>>,
     /// The user state type of the context
     user_state: Option<TypeId>,
+    /// The functions that [`WasmFunc::call`] calls with unwrapped `i64`s,
+    /// without and with the bits of floats, once created
+    caller: Arc<[OnceLock<Py<PyAny>>; 2]>,
 }
 
 impl Clone for Func {
@@ -37,13 +60,435 @@ impl Clone for Func {
         Python::with_gil(|py| Self {
             func: self.func.clone_ref(py),
             ty: self.ty.clone(),
+            name: self.name.clone(),
+            module: self.module.clone(),
             user_state: self.user_state,
+            caller: self.caller.clone(),
         })
     }
 }
 
 impl WasmFunc<Engine> for Func {
     fn new<T>(
+        ctx: impl AsContextMut<Engine, UserState = T>,
+        ty: FuncType,
+        func: impl 'static
+            + Send
+            + Sync
+            + Fn(StoreContextMut<T>, &[Value<Engine>], &mut [Value<Engine>]) -> anyhow::Result<()>,
+    ) -> Self {
+        Self::new_host(ctx, ty, func)
+    }
+
+    fn ty(&self, _ctx: impl AsContext<Engine>) -> FuncType {
+        self.ty.clone()
+    }
+
+    fn call<T>(
+        &self,
+        mut ctx: impl AsContextMut<Engine>,
+        args: &[Value<Engine>],
+        results: &mut [Value<Engine>],
+    ) -> anyhow::Result<()> {
+        Python::with_gil(|py| {
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+
+            self.assert_user_state(&store);
+
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("call_guest", name = ?self.name, ?args, ?self.ty).entered();
+
+            // https://webassembly.github.io/spec/js-api/#exported-function-exotic-objects
+            assert_eq!(self.ty.params().len(), args.len());
+            assert_eq!(self.ty.results().len(), results.len());
+
+            let preserve_float_bits =
+                store.engine().config().preserves_float_bits() && float_bits::has_floats(&self.ty);
+            let func = self.caller(py, preserve_float_bits)?;
+
+            let args = args
+                .iter()
+                .map(|arg| float_bits::to_py(py, arg, preserve_float_bits));
+            let args = PyTuple::new(py, args)?;
+
+            let res = self.call_in_frame(&mut store, &func, args)?;
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(%res, ?self.ty);
+
+            match (self.ty.results(), results) {
+                ([], []) => (),
+                ([ty], [result]) => *result = float_bits::from_py(res, *ty, preserve_float_bits)?,
+                (tys, results) => {
+                    let res: Bound<PyTuple> = PyTuple::type_object(py).call1((res,))?.extract()?;
+
+                    // https://webassembly.github.io/spec/js-api/#exported-function-exotic-objects
+                    assert_eq!(tys.len(), res.len());
+
+                    for ((ty, result), value) in self
+                        .ty
+                        .results()
+                        .iter()
+                        .zip(results.iter_mut())
+                        .zip(res.iter())
+                    {
+                        *result = float_bits::from_py(value, *ty, preserve_float_bits)?;
+                    }
+                },
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl ToPy for Func {
+    fn to_py(&self, py: Python) -> Py<PyAny> {
+        self.func.clone_ref(py)
+    }
+}
+
+impl Func {
+    /// Wraps an existing exported WebAssembly function from JavaScript with
+    /// the given type `ty`, or with the type that is inferred using JS Type
+    /// Reflection if `ty` is `None`.
+    ///
+    /// Only where the web browser supports JS Type Reflection is `ty` checked
+    /// against the reflected type of `func`. Otherwise, only the number of
+    /// parameters of `func` is checked and `ty` is trusted, i.e. wrapping a
+    /// function with the wrong parameter or result types, or a JavaScript
+    /// function that is not exported from WebAssembly, is not detected until
+    /// calls to it fail or produce unexpected results.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `func` is not callable, if its number of parameters
+    /// differs from `ty`, if its reflected type differs from `ty`, or if `ty`
+    /// is `None` and the type cannot be inferred since the web browser does
+    /// not support JS Type Reflection.
+    pub fn from_js(func: &Bound<PyAny>, ty: Option<FuncType>) -> anyhow::Result<Self> {
+        if !func.is_callable() {
+            anyhow::bail!("expected WebAssembly.Function but found {func} which is not callable");
+        }
+
+        let ty = reflect_type(func, ty, func_type_from_js)?;
+
+        // the length of an exported WebAssembly function is its number of
+        // parameters
+        let length: usize = func.getattr(intern!(func.py(), "length"))?.extract()?;
+        if length != ty.params().len() {
+            anyhow::bail!(
+                "{func} has {length} parameters but {} are required",
+                ty.params().len()
+            );
+        }
+
+        Self::from_exported_function(func.clone(), ty, None, None)
+    }
+
+    /// Creates a new host function of type `ty` that is implemented directly
+    /// by the JavaScript function `func`, e.g. `Math.random`.
+    ///
+    /// Unlike [`WasmFunc::new`], WebAssembly calls the function without going
+    /// through a Rust trampoline, so it runs at the speed of any other
+    /// JavaScript import. The function therefore also receives and must
+    /// return JavaScript values as WebAssembly passes them, i.e. `BigInt`s for
+    /// `i64`s and numbers for all other number types.
+    ///
+    /// Note that JavaScript methods which depend on `this`, e.g.
+    /// `performance.now`, must first be bound to their object, which is
+    /// easiest with [`Func::from_js_source`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `func` is not a JavaScript `Function`. Python
+    /// callables must instead be wrapped with [`Func::from_py_callable`].
+    pub fn from_js_function(func: &Bound<PyAny>, ty: FuncType) -> anyhow::Result<Self> {
+        if !instanceof(func, function(func.py())?)? {
+            anyhow::bail!(
+                "expected a JavaScript function but found {func}, use `Func::from_py_callable` \
+                 for Python callables"
+            );
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%func, ?ty, "Func::from_js_function");
+
+        Ok(Self {
+            func: func.clone().unbind(),
+            ty,
+            name: None,
+            module: None,
+            user_state: None,
+            caller: Arc::default(),
+        })
+    }
+
+    /// Creates a new host function of type `ty` that is implemented directly
+    /// in JavaScript by the `source` code, which must evaluate to a function,
+    /// e.g. `"() => performance.now()"`.
+    ///
+    /// See [`Func::from_js_function`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if evaluating the `source` fails or if it does not
+    /// evaluate to a function.
+    pub fn from_js_source(py: Python, source: &str, ty: FuncType) -> anyhow::Result<Self> {
+        let func = run_js(py, source)?;
+
+        Self::from_js_function(func.bind(py), ty)
+    }
+
+    /// Creates a new host function of type `ty` that is implemented by the
+    /// Python `callable`.
+    ///
+    /// The `callable` is called with one Python argument per parameter and
+    /// must return `None` if the function has no results, the result itself
+    /// if it has one, or an iterable of all results otherwise. Numbers are
+    /// passed as Python `int`s and `float`s, while references are passed as
+    /// their JavaScript objects.
+    ///
+    /// The `callable` is wrapped like the closure of [`WasmFunc::new`], so
+    /// it is only kept alive by this function and its clones and never by
+    /// the store, and a Python exception that it raises traps the guest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `callable` is not callable.
+    pub fn from_py_callable<T>(
+        ctx: impl AsContextMut<Engine, UserState = T>,
+        ty: FuncType,
+        callable: &Bound<PyAny>,
+    ) -> anyhow::Result<Self> {
+        if !callable.is_callable() {
+            anyhow::bail!("expected a Python callable but found {callable} which is not callable");
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%callable, ?ty, "Func::from_py_callable");
+
+        let callable = callable.clone().unbind();
+        let result_tys = ty.results().to_vec();
+
+        Ok(Self::new_host(ctx, ty, move |_store, args, results| {
+            Python::with_gil(|py| {
+                let args = PyTuple::new(py, args.iter().map(|arg| to_py_unwrapped(py, arg)))?;
+                let res = callable.bind(py).call1(args)?;
+
+                match (result_tys.as_slice(), results) {
+                    ([], []) => (),
+                    ([ty], [result]) => *result = Value::from_py_typed(res, *ty)?,
+                    (tys, results) => {
+                        let res = res.try_iter()?.collect::<Result<Vec<_>, _>>()?;
+
+                        if res.len() != tys.len() {
+                            anyhow::bail!(
+                                "{} results were returned but {} are required",
+                                res.len(),
+                                tys.len()
+                            );
+                        }
+
+                        for ((ty, result), value) in tys.iter().zip(results.iter_mut()).zip(res) {
+                            *result = Value::from_py_typed(value, *ty)?;
+                        }
+                    },
+                }
+
+                Ok(())
+            })
+        }))
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript function.
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.func.bind(py)
+    }
+
+    /// Creates a new function of the trusted type `ty` from a Python value,
+    /// which is exported under the `name` from the `module`, if known
+    pub(crate) fn from_exported_function(
+        func: Bound<PyAny>,
+        ty: FuncType,
+        name: Option<&str>,
+        module: Option<Arc<ParsedModule>>,
+    ) -> anyhow::Result<Self> {
+        if !func.is_callable() {
+            anyhow::bail!("expected WebAssembly.Function but found {func:?} which is not callable");
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%func, ?ty, "Func::from_exported_function");
+
+        Ok(Self {
+            func: func.unbind(),
+            ty,
+            name: name.map(Arc::from),
+            module,
+            user_state: None,
+            caller: Arc::default(),
+        })
+    }
+
+    /// Calls this function once for each of the argument lists in `args` and
+    /// writes the results of each call into the corresponding result list in
+    /// `results`.
+    ///
+    /// Unlike calling [`WasmFunc::call`] repeatedly, the whole batch crosses
+    /// the JavaScript boundary only once, which amortizes the fixed overhead
+    /// of a call when a cheap function is called many times.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any call fails or traps, in which case the batch
+    /// is aborted and the contents of `results` are unspecified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `args` and `results` have different lengths, or if any
+    /// argument or result list does not match the function's type.
+    pub fn call_batch<A: AsRef<[Value<Engine>]>, R: AsMut<[Value<Engine>]>>(
+        &self,
+        mut ctx: impl AsContextMut<Engine>,
+        args: &[A],
+        results: &mut [R],
+    ) -> anyhow::Result<()> {
+        Python::with_gil(|py| {
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+
+            self.assert_user_state(&store);
+
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("call_guest_batch", name = ?self.name, calls = args.len(), ?self.ty).entered();
+
+            assert_eq!(args.len(), results.len());
+
+            let preserve_float_bits =
+                store.engine().config().preserves_float_bits() && float_bits::has_floats(&self.ty);
+            let func = self.caller(py, preserve_float_bits)?;
+
+            let mut flat_args = Vec::with_capacity(args.len() * self.ty.params().len());
+            for args in args {
+                let args = args.as_ref();
+                assert_eq!(self.ty.params().len(), args.len());
+
+                flat_args.extend(
+                    args.iter()
+                        .map(|arg| float_bits::to_py(py, arg, preserve_float_bits)),
+                );
+            }
+            let flat_args = to_js(py)?.call1((PyList::new(py, flat_args)?,))?;
+
+            let args = PyTuple::new(
+                py,
+                [
+                    func.into_any(),
+                    args.len().into_pyobject(py)?.into_any(),
+                    self.ty.params().len().into_pyobject(py)?.into_any(),
+                    self.ty.results().len().into_pyobject(py)?.into_any(),
+                    flat_args,
+                ],
+            )?;
+
+            let batch = self.call_in_frame(&mut store, call_batch(py)?, args)?;
+
+            // only convert the array itself, since its elements may be
+            // arbitrary JavaScript objects if they are references
+            let batch = batch.call_method(
+                intern!(py, "to_py"),
+                (),
+                Some(&[(intern!(py, "depth"), 1)].into_py_dict(py)?),
+            )?;
+            let batch: Bound<PyList> = batch.downcast_into().map_err(PyErr::from)?;
+
+            assert_eq!(batch.len(), results.len() * self.ty.results().len());
+
+            let mut batch = batch.iter();
+            for results in results {
+                let results = results.as_mut();
+                assert_eq!(self.ty.results().len(), results.len());
+
+                for (ty, result) in self.ty.results().iter().zip(results.iter_mut()) {
+                    let value = batch.next().expect("batch should have enough results");
+                    *result = float_bits::from_py(value, *ty, preserve_float_bits)?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Creates a [`TypedFunc`] with the parameter and result types `Params`
+    /// and `Results`, which are checked against the function's type once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the function's type differs from the `Params` and
+    /// `Results` types.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn typed<Params: WasmParams, Results: WasmResults>(
+        &self,
+        ctx: impl AsContext<Engine>,
+    ) -> anyhow::Result<TypedFunc<Params, Results>> {
+        TypedFunc::new(self.clone(), ctx.as_context().engine())
+    }
+
+    /// Returns the function type
+    pub(crate) const fn func_type(&self) -> &FuncType {
+        &self.ty
+    }
+
+    /// Asserts that the user state type of the `store` is the one that this
+    /// function was created with, if any
+    pub(crate) fn assert_user_state<T>(&self, store: &StoreContextMut<T>) {
+        if let Some(user_state) = self.user_state {
+            assert_eq!(user_state, non_static_type_id(store.data()));
+        }
+    }
+
+    /// Calls the JavaScript `func` with the `args` inside a call frame of
+    /// this function in the `store`, and annotates any error that it raises
+    pub(crate) fn call_in_frame<'py, T>(
+        &self,
+        store: &mut StoreContextMut<T>,
+        func: &Bound<'py, PyAny>,
+        args: Bound<'py, PyTuple>,
+    ) -> anyhow::Result<Bound<'py, PyAny>> {
+        let py = func.py();
+
+        let mut call = store.enter_call(CallFrame::Guest {
+            name: self.name.clone(),
+            ty: self.ty.clone(),
+        })?;
+
+        let res = func.call1(args).map_err(|err| {
+            let backtrace = self
+                .module
+                .as_deref()
+                .and_then(|module| WasmBacktrace::from_js_error(py, module, &err));
+
+            let err = call.annotate_trap(py, err);
+
+            match backtrace {
+                Some(backtrace) => err.context(backtrace),
+                None => err,
+            }
+        });
+
+        call.exit(res.is_ok());
+
+        res
+    }
+
+    /// Creates a new host function, like [`WasmFunc::new`]
+    ///
+    /// Callers in this crate use this function instead of [`WasmFunc::new`],
+    /// whose closure type is bounded through the engine's generic store
+    /// context, which would require the user state to be `'static`.
+    fn new_host<T>(
         mut ctx: impl AsContextMut<Engine, UserState = T>,
         ty: FuncType,
         func: impl 'static
@@ -62,6 +507,9 @@ impl WasmFunc<Engine> for Func {
             let user_state = non_static_type_id(store.data());
             let ty_clone = ty.clone();
 
+            let preserve_float_bits =
+                store.engine().config().preserves_float_bits() && float_bits::has_floats(&ty);
+
             let func = Arc::new(move |args: Bound<PyTuple>| -> Result<Py<PyAny>, PyErr> {
                 let py = args.py();
 
@@ -76,7 +524,7 @@ impl WasmFunc<Engine> for Func {
                 // - The proof is constructed from a mutable store context
                 // - Calling a host function (from the host or from WASM) provides that call
                 //   with a mutable reborrow of the store context
-                let store = unsafe { StoreContextMut::from_proof_unchecked(&mut strong_store) };
+                let mut store = unsafe { StoreContextMut::from_proof_unchecked(&mut strong_store) };
 
                 let ty = &ty_clone;
 
@@ -84,14 +532,22 @@ impl WasmFunc<Engine> for Func {
                     .params()
                     .iter()
                     .zip(args.iter())
-                    .map(|(ty, arg)| Value::from_py_typed(arg, *ty))
+                    .map(|(ty, arg)| float_bits::from_py(arg, *ty, preserve_float_bits))
                     .collect::<Result<Vec<_>, _>>()?;
                 let mut results = vec![Value::I32(0); ty.results().len()];
 
                 #[cfg(feature = "tracing")]
                 let _span = tracing::debug_span!("call_host", ?args, ?ty).entered();
 
-                match func(store, &args, &mut results) {
+                let mut call = store
+                    .enter_call(CallFrame::Host { ty: ty.clone() })
+                    .map_err(|err| PyErrChain::pyerr_from_err(py, err))?;
+
+                let result = func(call.as_context_mut(), &args, &mut results);
+
+                call.exit(result.is_ok());
+
+                match result {
                     Ok(()) => {
                         #[cfg(feature = "tracing")]
                         tracing::debug!(?results, "result");
@@ -103,10 +559,12 @@ impl WasmFunc<Engine> for Func {
                     },
                 }
 
+                let to_py = |res| float_bits::to_py(py, res, preserve_float_bits);
+
                 let results = match results.as_slice() {
                     [] => py.None(),
-                    [res] => res.to_py(py),
-                    results => PyTuple::new(py, results.iter().map(|res| res.to_py(py)))?
+                    [res] => to_py(res),
+                    results => PyTuple::new(py, results.iter().map(to_py))?
                         .into_any()
                         .unbind(),
                 };
@@ -124,94 +582,54 @@ impl WasmFunc<Engine> for Func {
             )?;
             let func = py_to_js_proxy(func)?;
 
+            // the trampoline can be called directly by the host, while its
+            // unwrapped i64 results must first be converted into BigInts
+            // before they are returned to WebAssembly
+            let caller: Arc<[OnceLock<Py<PyAny>>; 2]> = Arc::default();
+            let _ = caller[usize::from(preserve_float_bits)].set(func.clone().unbind());
+
+            let func = if preserve_float_bits {
+                // the trampoline takes and returns the bits of floats, so it
+                // is wrapped in a WebAssembly function that reinterprets them
+                let func = with_big_int_results(&func, &float_bits::bits_func_type(&ty))?;
+                float_bits::lift(&func, &ty)?
+            } else {
+                with_big_int_results(&func, &ty)?
+            };
+
             Ok(Self {
                 func: func.unbind(),
                 ty,
+                name: None,
+                module: None,
                 user_state: Some(user_state),
+                caller,
             })
         })
         .expect("Func::new should not fail")
     }
 
-    fn ty(&self, _ctx: impl AsContext<Engine>) -> FuncType {
-        self.ty.clone()
-    }
-
-    fn call<T>(
+    /// Returns the function that [`WasmFunc::call`] calls with unwrapped
+    /// `i64`s and, if `preserve_float_bits` is set, the bits of floats,
+    /// which wraps this function on first use
+    pub(crate) fn caller<'py>(
         &self,
-        mut ctx: impl AsContextMut<Engine>,
-        args: &[Value<Engine>],
-        results: &mut [Value<Engine>],
-    ) -> anyhow::Result<()> {
-        Python::with_gil(|py| {
-            let store: StoreContextMut<_> = ctx.as_context_mut();
-
-            if let Some(user_state) = self.user_state {
-                assert_eq!(user_state, non_static_type_id(store.data()));
-            }
-
-            #[cfg(feature = "tracing")]
-            let _span = tracing::debug_span!("call_guest", ?args, ?self.ty).entered();
-
-            // https://webassembly.github.io/spec/js-api/#exported-function-exotic-objects
-            assert_eq!(self.ty.params().len(), args.len());
-            assert_eq!(self.ty.results().len(), results.len());
-
-            let args = args.iter().map(|arg| arg.to_py(py));
-            let args = PyTuple::new(py, args)?;
-
-            let res = self.func.bind(py).call1(args)?;
-
-            #[cfg(feature = "tracing")]
-            tracing::debug!(%res, ?self.ty);
-
-            match (self.ty.results(), results) {
-                ([], []) => (),
-                ([ty], [result]) => *result = Value::from_py_typed(res, *ty)?,
-                (tys, results) => {
-                    let res: Bound<PyTuple> = PyTuple::type_object(py).call1((res,))?.extract()?;
-
-                    // https://webassembly.github.io/spec/js-api/#exported-function-exotic-objects
-                    assert_eq!(tys.len(), res.len());
-
-                    for ((ty, result), value) in self
-                        .ty
-                        .results()
-                        .iter()
-                        .zip(results.iter_mut())
-                        .zip(res.iter())
-                    {
-                        *result = Value::from_py_typed(value, *ty)?;
-                    }
-                },
-            }
-
-            Ok(())
-        })
-    }
-}
-
-impl ToPy for Func {
-    fn to_py(&self, py: Python) -> Py<PyAny> {
-        self.func.clone_ref(py)
-    }
-}
-
-impl Func {
-    /// Creates a new function from a Python value
-    pub(crate) fn from_exported_function(func: Bound<PyAny>, ty: FuncType) -> anyhow::Result<Self> {
-        if !func.is_callable() {
-            anyhow::bail!("expected WebAssembly.Function but found {func:?} which is not callable");
+        py: Python<'py>,
+        preserve_float_bits: bool,
+    ) -> Result<Bound<'py, PyAny>, PyErr> {
+        let cell = &self.caller[usize::from(preserve_float_bits)];
+        if let Some(caller) = cell.get() {
+            return Ok(caller.bind(py).clone());
         }
 
-        #[cfg(feature = "tracing")]
-        tracing::debug!(%func, ?ty, "Func::from_exported_function");
+        let caller = if preserve_float_bits {
+            let func = float_bits::lower(self.func.bind(py), &self.ty)?;
+            with_big_int_params(&func, &float_bits::bits_func_type(&self.ty))?
+        } else {
+            with_big_int_params(self.func.bind(py), &self.ty)?
+        };
 
-        Ok(Self {
-            func: func.unbind(),
-            ty,
-            user_state: None,
-        })
+        Ok(cell.get_or_init(|| caller.unbind()).bind(py).clone())
     }
 }
 
@@ -264,3 +682,365 @@ fn non_static_type_id<T: ?Sized>(_x: &T) -> TypeId {
         core::mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&phantom_data)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::{
+        self as frontend,
+        backend::{Extern, Imports, Value},
+        ValueType,
+    };
+
+    use super::*;
+    use crate::{test_utils, Config};
+
+    #[test]
+    fn guest_and_host_calls() {
+        let mut store = test_utils::store_with(&Config::default(), 0_i64);
+
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (import "env" "add" (func $add (param i64 i64) (result i64)))
+                (func $fail (unreachable))
+                (func (export "sum") (param i32 i64 f32 f64) (result i64)
+                    (call $add
+                        (i64.extend_i32_s (local.get 0))
+                        (i64.add (local.get 1)
+                            (i64.add (i64.trunc_f32_s (local.get 2)) (i64.trunc_f64_s (local.get 3))))))
+                (func (export "trap") (call $fail))
+            )"#,
+        )
+        .unwrap();
+        let module = frontend::Module::new(store.engine(), bytes.as_slice()).unwrap();
+
+        let add = frontend::Func::new(
+            &mut store,
+            FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]),
+            |mut caller, args, results| {
+                let (frontend::Value::I64(a), frontend::Value::I64(b)) = (&args[0], &args[1])
+                else {
+                    anyhow::bail!("expected two i64 arguments");
+                };
+                *caller.data_mut() += 1;
+                results[0] = frontend::Value::I64(a.wrapping_add(*b));
+                Ok(())
+            },
+        );
+
+        let mut imports = frontend::Imports::new();
+        imports.define("env", "add", frontend::Extern::Func(add));
+        let instance = frontend::Instance::new(&mut store, &module, &imports).unwrap();
+
+        let sum = instance
+            .get_export(&store, "sum")
+            .and_then(frontend::Extern::into_func)
+            .unwrap();
+        let mut results = [frontend::Value::I64(0)];
+        sum.call(
+            &mut store,
+            &[
+                frontend::Value::I32(-1),
+                frontend::Value::I64(i64::MAX - 41),
+                frontend::Value::F32(20.5),
+                frontend::Value::F64(22.5),
+            ],
+            &mut results,
+        )
+        .unwrap();
+        assert!(matches!(results, [frontend::Value::I64(i64::MAX)]));
+        assert_eq!(*store.data(), 1);
+
+        let trap = instance
+            .get_export(&store, "trap")
+            .and_then(frontend::Extern::into_func)
+            .unwrap();
+        let err = trap.call(&mut store, &[], &mut []).unwrap_err();
+        assert!(format!("{err:?}").contains("unreachable"), "{err:?}");
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn batched_guest_calls() {
+        let mut store = test_utils::store();
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (func (export "divmod") (param i64 i64) (result i64 i64)
+                    (i64.div_s (local.get 0) (local.get 1))
+                    (i64.rem_s (local.get 0) (local.get 1)))
+                (func (export "half") (param f32) (result f32)
+                    (f32.mul (local.get 0) (f32.const 0.5)))
+                (func (export "nop"))
+            )"#,
+            &Imports::default(),
+        )
+        .unwrap();
+
+        let [divmod, half, nop] =
+            ["divmod", "half", "nop"].map(|name| test_utils::export_func(&store, &instance, name));
+
+        let mut results = [
+            [Value::I64(0), Value::I64(0)],
+            [Value::I64(0), Value::I64(0)],
+        ];
+        divmod
+            .call_batch(
+                &mut store,
+                &[
+                    [Value::I64(i64::MAX), Value::I64(10)],
+                    [Value::I64(-7), Value::I64(2)],
+                ],
+                &mut results,
+            )
+            .unwrap();
+        assert!(matches!(
+            results,
+            [
+                [Value::I64(922_337_203_685_477_580), Value::I64(7)],
+                [Value::I64(-3), Value::I64(-1)],
+            ]
+        ));
+
+        let mut results = vec![vec![Value::F32(0.0)]; 3];
+        half.call_batch(
+            &mut store,
+            &[[Value::F32(1.0)], [Value::F32(-3.0)], [Value::F32(0.25)]],
+            &mut results,
+        )
+        .unwrap();
+        let halves = results
+            .iter()
+            .map(|results| match results.as_slice() {
+                [Value::F32(half)] => *half,
+                results => panic!("expected one f32 result but found {results:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(halves, [0.5, -1.5, 0.125]);
+
+        let mut results: [[Value<Engine>; 0]; 2] = [[], []];
+        nop.call_batch(&mut store, &[[], []], &mut results).unwrap();
+
+        // the results of a host function reach JavaScript as a Python tuple
+        let swap = Func::new(
+            &mut store,
+            FuncType::new(
+                [ValueType::I32, ValueType::I64],
+                [ValueType::I64, ValueType::I32],
+            ),
+            |_caller, args, results| {
+                results[0] = args[1].clone();
+                results[1] = args[0].clone();
+                Ok(())
+            },
+        );
+        let mut results = [
+            [Value::I64(0), Value::I32(0)],
+            [Value::I64(0), Value::I32(0)],
+        ];
+        swap.call_batch(
+            &mut store,
+            &[
+                [Value::I32(1), Value::I64(-2)],
+                [Value::I32(-3), Value::I64(i64::MIN)],
+            ],
+            &mut results,
+        )
+        .unwrap();
+        assert!(matches!(
+            results,
+            [
+                [Value::I64(-2), Value::I32(1)],
+                [Value::I64(i64::MIN), Value::I32(-3)],
+            ]
+        ));
+
+        // a trap in any call aborts the whole batch
+        let mut results = [
+            [Value::I64(0), Value::I64(0)],
+            [Value::I64(0), Value::I64(0)],
+        ];
+        let err = divmod
+            .call_batch(
+                &mut store,
+                &[
+                    [Value::I64(1), Value::I64(1)],
+                    [Value::I64(1), Value::I64(0)],
+                ],
+                &mut results,
+            )
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("divide by zero"), "{err:?}");
+    }
+
+    #[test]
+    fn javascript_host_functions() {
+        let mut store = test_utils::store();
+
+        // an exported WebAssembly function is a genuine JavaScript function,
+        // which the mock can create without evaluating JavaScript source
+        let mul = Python::with_gil(|py| {
+            let bytes = wat::parse_str(
+                r#"
+                (module
+                    (func (export "mul") (param i64 i64) (result i64)
+                        (i64.mul (local.get 0) (local.get 1)))
+                )"#,
+            )
+            .unwrap();
+            let buffer = crate::js::uint8_array_new(py)
+                .unwrap()
+                .call1((bytes.as_slice(),))
+                .unwrap();
+            let module = crate::js::web_assembly_module_new(py)
+                .unwrap()
+                .call1((buffer,))
+                .unwrap();
+            let mul = crate::js::web_assembly_instance_new(py)
+                .unwrap()
+                .call1((module,))
+                .unwrap()
+                .getattr("exports")
+                .unwrap()
+                .getattr("mul")
+                .unwrap();
+            Func::from_js_function(
+                &mul,
+                FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]),
+            )
+            .unwrap()
+        });
+
+        let mut imports = Imports::default();
+        imports.define("env", "mul", Extern::Func(mul.clone()));
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (import "env" "mul" (func $mul (param i64 i64) (result i64)))
+                (func (export "square") (param i64) (result i64)
+                    (call $mul (local.get 0) (local.get 0)))
+            )"#,
+            &imports,
+        )
+        .unwrap();
+        let square = test_utils::export_func(&store, &instance, "square");
+
+        let mut results = [Value::I64(0)];
+        square
+            .call::<()>(&mut store, &[Value::I64(-3_037_000_499)], &mut results)
+            .unwrap();
+        assert!(matches!(results, [Value::I64(9_223_372_030_926_249_001)]));
+
+        let mut results = [Value::I64(0)];
+        mul.call::<()>(
+            &mut store,
+            &[Value::I64(1 << 53), Value::I64(-2)],
+            &mut results,
+        )
+        .unwrap();
+        assert!(matches!(results, [Value::I64(x)] if x == -(1 << 54)));
+
+        Python::with_gil(|py| {
+            assert!(
+                Func::from_js_function(&py.None().into_bound(py), FuncType::new([], [])).is_err()
+            );
+
+            let lambda = py
+                .eval(pyo3::ffi::c_str!("lambda: None"), None, None)
+                .unwrap();
+            let err = Func::from_js_function(&lambda, FuncType::new([], [])).unwrap_err();
+            assert!(
+                err.to_string().contains("Func::from_py_callable"),
+                "{err:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn python_host_functions() {
+        let mut store = test_utils::store();
+
+        let (half_rem, weak_callable) = Python::with_gil(|py| {
+            let callable = py
+                .eval(
+                    pyo3::ffi::c_str!("lambda a, b: (a // 2, 2.0 % b)"),
+                    None,
+                    None,
+                )
+                .unwrap();
+            let weak_callable = py
+                .import("weakref")
+                .unwrap()
+                .call_method1("ref", (&callable,))
+                .unwrap()
+                .unbind();
+
+            let half_rem = Func::from_py_callable(
+                &mut store,
+                FuncType::new(
+                    [ValueType::I64, ValueType::F64],
+                    [ValueType::I64, ValueType::F64],
+                ),
+                &callable,
+            )
+            .unwrap();
+
+            (half_rem, weak_callable)
+        });
+
+        let mut imports = Imports::default();
+        imports.define("env", "half_rem", Extern::Func(half_rem));
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (import "env" "half_rem" (func $half_rem (param i64 f64) (result i64 f64)))
+                (func (export "half_rem") (param i64 f64) (result i64 f64)
+                    (call $half_rem (local.get 0) (local.get 1)))
+            )"#,
+            &imports,
+        )
+        .unwrap();
+        let guest_half_rem = test_utils::export_func(&store, &instance, "half_rem");
+
+        let mut results = [Value::I64(0), Value::F64(0.0)];
+        guest_half_rem
+            .call::<()>(
+                &mut store,
+                &[Value::I64(i64::MAX), Value::F64(3.0)],
+                &mut results,
+            )
+            .unwrap();
+        assert!(
+            matches!(results, [Value::I64(q), Value::F64(r)] if q == i64::MAX / 2 && r.to_bits() == 2.0_f64.to_bits())
+        );
+
+        // a Python exception traps the guest
+        let err = guest_half_rem
+            .call::<()>(&mut store, &[Value::I64(1), Value::F64(0.0)], &mut results)
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("ZeroDivisionError"), "{err:?}");
+
+        // the callable is freed once the function is no longer referenced,
+        // even while its store is still alive (the error is dropped as well
+        // since its traceback references the frames of the call)
+        drop((instance, guest_half_rem, imports, err));
+        Python::with_gil(|py| {
+            py.import("gc").unwrap().call_method0("collect").unwrap();
+            assert!(weak_callable.bind(py).call0().unwrap().is_none());
+        });
+        drop(store);
+
+        // the user state of the store need not be 'static
+        let mut calls = 0_u32;
+        let mut store = test_utils::store_with(&Config::default(), &mut calls);
+        Python::with_gil(|py| {
+            let callable = py
+                .eval(pyo3::ffi::c_str!("lambda: None"), None, None)
+                .unwrap();
+            Func::from_py_callable(&mut store, FuncType::new([], []), &callable).unwrap();
+        });
+    }
+}
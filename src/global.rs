@@ -1,11 +1,16 @@
-use pyo3::{intern, prelude::*, sync::GILOnceCell};
+use std::sync::{Arc, OnceLock};
+
+use pyo3::{intern, prelude::*};
 use wasm_runtime_layer::{
-    backend::{AsContext, AsContextMut, Value, WasmGlobal},
-    GlobalType,
+    backend::{AsContext, AsContextMut, Value, WasmGlobal, WasmStoreContext},
+    GlobalType, ValueType,
 };
 
 use crate::{
     conversion::{create_js_object, instanceof, ToPy, ValueExt, ValueTypeExt},
+    float_bits,
+    js::{web_assembly_global, web_assembly_global_new},
+    reflection::{global_type_from_js, reflect_type},
     Engine,
 };
 
@@ -15,11 +20,15 @@ use crate::{
 ///
 /// [`WebAssembly.Global`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Global
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 pub struct Global {
     /// The global value
     global: Py<PyAny>,
     /// The global type
     ty: GlobalType,
+    /// The exports of the [`float_bits`] accessor that reads and writes the
+    /// bits of a float global, once created
+    accessor: Arc<OnceLock<Py<PyAny>>>,
 }
 
 impl Clone for Global {
@@ -27,33 +36,37 @@ impl Clone for Global {
         Python::with_gil(|py| Self {
             global: self.global.clone_ref(py),
             ty: self.ty,
+            accessor: self.accessor.clone(),
         })
     }
 }
 
 impl WasmGlobal<Engine> for Global {
-    fn new(_ctx: impl AsContextMut<Engine>, value: Value<Engine>, mutable: bool) -> Self {
-        Python::with_gil(|py| -> Result<Self, PyErr> {
-            #[cfg(feature = "tracing")]
-            tracing::debug!(?value, mutable, "Global::new");
-
-            let ty = GlobalType::new(ValueExt::ty(&value), mutable);
+    fn new(ctx: impl AsContextMut<Engine>, value: Value<Engine>, mutable: bool) -> Self {
+        let preserve_float_bits = preserves_float_bits(&ctx, ValueExt::ty(&value));
 
-            let desc = create_js_object(py)?;
-            desc.setattr(
-                intern!(py, "value"),
-                ValueExt::ty(&value).as_js_descriptor(),
-            )?;
-            desc.setattr(intern!(py, "mutable"), mutable)?;
-
-            let value = value.to_py(py);
+        Python::with_gil(|py| {
+            if preserve_float_bits && !mutable {
+                let global = float_bits::new_immutable_global(py, &value)?;
+                return Ok(Self {
+                    global: global.unbind(),
+                    ty: GlobalType::new(ValueExt::ty(&value), false),
+                    accessor: Arc::new(OnceLock::new()),
+                });
+            }
 
-            let global = web_assembly_global_new(py)?.call1((desc, value))?;
+            if preserve_float_bits {
+                // zero crosses the JavaScript boundary unchanged
+                let zero = match value {
+                    Value::F32(_) => Value::F32(0.0),
+                    _ => Value::F64(0.0),
+                };
+                let global = Self::new_with_value(py, &zero, true)?;
+                float_bits::set_global(&global.accessor(py)?, &value)?;
+                return Ok(global);
+            }
 
-            Ok(Self {
-                global: global.unbind(),
-                ty,
-            })
+            Self::new_with_value(py, &value, mutable)
         })
         .expect("Global::new should not fail")
     }
@@ -62,40 +75,49 @@ impl WasmGlobal<Engine> for Global {
         self.ty
     }
 
-    fn set(&self, _ctx: impl AsContextMut<Engine>, new_value: Value<Engine>) -> anyhow::Result<()> {
+    fn set(&self, ctx: impl AsContextMut<Engine>, new_value: Value<Engine>) -> anyhow::Result<()> {
         if !self.ty.mutable() {
             return Err(anyhow::anyhow!("Global is not mutable"));
         }
 
-        Python::with_gil(|py| {
-            let global = self.global.bind(py);
+        if preserves_float_bits(&ctx, self.ty.content()) {
+            if ValueExt::ty(&new_value) != self.ty.content() {
+                anyhow::bail!(
+                    "cannot set a global of type {} to {new_value:?}",
+                    self.ty.content()
+                );
+            }
 
-            #[cfg(feature = "tracing")]
-            tracing::debug!(global = %global, ?self.ty, ?new_value, "Global::set");
+            Python::with_gil(|py| float_bits::set_global(&self.accessor(py)?, &new_value))?;
 
-            let new_value = new_value.to_py(py);
+            return Ok(());
+        }
 
-            global.setattr(intern!(py, "value"), new_value)?;
+        Python::with_gil(|py| self.set_value(py, &new_value))?;
 
-            Ok(())
-        })
+        Ok(())
     }
 
-    fn get(&self, _ctx: impl AsContextMut<Engine>) -> Value<Engine> {
-        Python::with_gil(|py| {
-            let global = self.global.bind(py);
-
-            #[cfg(feature = "tracing")]
-            tracing::debug!(global = %global, ?self.ty, "Global::get");
+    fn get(&self, ctx: impl AsContextMut<Engine>) -> Value<Engine> {
+        let preserve_float_bits = preserves_float_bits(&ctx, self.ty.content());
 
-            let value = global.getattr(intern!(py, "value"))?;
+        Python::with_gil(|py| {
+            if preserve_float_bits {
+                return float_bits::get_global(&self.accessor(py)?, self.ty);
+            }
 
-            Value::from_py_typed(value, self.ty.content())
+            self.get_value(py)
         })
         .expect("Global::get should not fail")
     }
 }
 
+/// Checks whether values of type `ty` are accessed through
+/// [`float_bits`] in the store of `ctx`
+fn preserves_float_bits(ctx: &impl AsContext<Engine>, ty: ValueType) -> bool {
+    float_bits::is_float(ty) && ctx.as_context().engine().config().preserves_float_bits()
+}
+
 impl ToPy for Global {
     fn to_py(&self, py: Python) -> Py<PyAny> {
         #[cfg(feature = "tracing")]
@@ -106,7 +128,111 @@ impl ToPy for Global {
 }
 
 impl Global {
-    /// Creates a new global from a Python value
+    /// Creates a new global with an initial `value`
+    pub(crate) fn new_with_value(
+        py: Python,
+        value: &Value<Engine>,
+        mutable: bool,
+    ) -> Result<Self, PyErr> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?value, mutable, "Global::new");
+
+        let ty = GlobalType::new(ValueExt::ty(value), mutable);
+
+        let desc = create_js_object(py)?;
+        desc.setattr(intern!(py, "value"), ValueExt::ty(value).as_js_descriptor())?;
+        desc.setattr(intern!(py, "mutable"), mutable)?;
+
+        let value = value.to_py(py);
+
+        let global = web_assembly_global_new(py)?.call1((desc, value))?;
+
+        Ok(Self {
+            global: global.unbind(),
+            ty,
+            accessor: Arc::new(OnceLock::new()),
+        })
+    }
+
+    /// Returns the exports of the [`float_bits`] accessor of this float
+    /// global, which is instantiated on first use
+    fn accessor<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyAny>, PyErr> {
+        if let Some(accessor) = self.accessor.get() {
+            return Ok(accessor.bind(py).clone());
+        }
+
+        let accessor = float_bits::global_accessor(self.global.bind(py), self.ty)?;
+
+        Ok(self
+            .accessor
+            .get_or_init(|| accessor.unbind())
+            .bind(py)
+            .clone())
+    }
+
+    /// Returns the current value of the global
+    pub(crate) fn get_value(&self, py: Python) -> Result<Value<Engine>, PyErr> {
+        let global = self.global.bind(py);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(global = %global, ?self.ty, "Global::get");
+
+        let value = global.getattr(intern!(py, "value"))?;
+
+        Value::from_py_typed(value, self.ty.content())
+    }
+
+    /// Sets the value of the global, without checking its mutability
+    pub(crate) fn set_value(&self, py: Python, new_value: &Value<Engine>) -> Result<(), PyErr> {
+        let global = self.global.bind(py);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(global = %global, ?self.ty, ?new_value, "Global::set");
+
+        let new_value = new_value.to_py(py);
+
+        global.setattr(intern!(py, "value"), new_value)
+    }
+
+    /// Wraps an existing JavaScript [`WebAssembly.Global`] with the given
+    /// type `ty`, or with the type that is inferred using JS Type Reflection
+    /// if `ty` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `global` is not a [`WebAssembly.Global`], if its
+    /// value does not have the content type of `ty`, if its type differs from
+    /// `ty` where JS Type Reflection is supported, or if `ty` is `None`
+    /// and the type cannot be inferred since the web browser does not
+    /// support JS Type Reflection.
+    ///
+    /// [`WebAssembly.Global`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Global
+    pub fn from_js(global: &Bound<PyAny>, ty: Option<GlobalType>) -> anyhow::Result<Self> {
+        if !instanceof(global, web_assembly_global(global.py())?)? {
+            anyhow::bail!("expected WebAssembly.Global but found {global}");
+        }
+
+        let ty = reflect_type(global, ty, global_type_from_js)?;
+
+        // funcref values cannot be converted without knowing their type
+        let value = global.getattr(intern!(global.py(), "value"))?;
+        if ty.content() != ValueType::FuncRef && Value::from_py_typed(value, ty.content()).is_err()
+        {
+            anyhow::bail!("the value of {global} is not of type {}", ty.content());
+        }
+
+        Self::from_exported_global(global.clone(), ty)
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript [`WebAssembly.Global`].
+    ///
+    /// [`WebAssembly.Global`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Global
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.global.bind(py)
+    }
+
+    /// Creates a new global of the trusted type `ty` from a Python value
     pub(crate) fn from_exported_global(
         global: Bound<PyAny>,
         ty: GlobalType,
@@ -121,16 +247,62 @@ impl Global {
         Ok(Self {
             global: global.unbind(),
             ty,
+            accessor: Arc::new(OnceLock::new()),
         })
     }
 }
 
-fn web_assembly_global(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_GLOBAL: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_GLOBAL.import(py, "js.WebAssembly", "Global")
-}
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::{backend, Value};
+
+    use super::*;
+    use crate::test_utils;
 
-fn web_assembly_global_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_GLOBAL_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_GLOBAL_NEW.import(py, "js.WebAssembly.Global", "new")
+    #[test]
+    fn value_conversions() {
+        let mut store = test_utils::store();
+
+        for value in [
+            Value::I32(i32::MIN),
+            Value::I64(i64::MIN),
+            Value::I64(1 << 60),
+            Value::F32(-0.5),
+            Value::F64(f64::MAX),
+        ] {
+            let global = wasm_runtime_layer::Global::new(&mut store, value.clone(), false);
+            assert_eq!(global.get(&mut store), value);
+            assert!(global.set(&mut store, value).is_err());
+        }
+
+        let counter = wasm_runtime_layer::Global::new(&mut store, Value::I64(0), true);
+        counter.set(&mut store, Value::I64(-1)).unwrap();
+        assert_eq!(counter.get(&mut store), Value::I64(-1));
+        assert!(counter.set(&mut store, Value::I32(0)).is_err());
+        assert_eq!(counter.ty(&store), GlobalType::new(ValueType::I64, true));
+    }
+
+    #[test]
+    fn float_bits_accessor() {
+        let mut store = test_utils::store_with(crate::Config::new().preserve_float_bits(true), ());
+
+        let nan = f32::from_bits(0x7F80_0001);
+        let global = Global::new(&mut store, backend::Value::F32(nan), true);
+        let clone = global.clone();
+
+        // the accessor is instantiated once and shared with clones
+        assert!(matches!(
+            clone.get(&mut store),
+            backend::Value::F32(value) if value.to_bits() == nan.to_bits()
+        ));
+        assert!(Arc::ptr_eq(&global.accessor, &clone.accessor));
+        let accessor = global.accessor.get().unwrap().as_ptr();
+
+        global.set(&mut store, backend::Value::F32(-nan)).unwrap();
+        assert!(matches!(
+            global.get(&mut store),
+            backend::Value::F32(value) if value.to_bits() == (-nan).to_bits()
+        ));
+        assert_eq!(global.accessor.get().unwrap().as_ptr(), accessor);
+    }
 }
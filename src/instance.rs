@@ -1,15 +1,20 @@
 use std::{collections::BTreeMap, sync::Arc};
 
-use fxhash::FxHashMap;
-use pyo3::{intern, prelude::*, sync::GILOnceCell};
+use pyo3::{intern, prelude::*};
 use wasm_runtime_layer::{
-    backend::{AsContext, AsContextMut, Export, Extern, Imports, WasmInstance, WasmModule},
+    backend::{
+        AsContext, AsContextMut, Export, Extern, Imports, WasmInstance, WasmModule,
+        WasmStoreContext,
+    },
     ExportType, ExternType,
 };
 
 use crate::{
-    conversion::{create_js_object, ToPy},
-    Engine, Func, Global, Memory, Module, Table,
+    conversion::{create_js_object, instanceof, ToPy},
+    instrument::{EPOCH_DEADLINE_GLOBAL, EPOCH_GLOBAL, FUEL_GLOBAL, INSTRUMENTATION_MODULE},
+    js::{web_assembly_instance, web_assembly_instance_new},
+    store::StoreContextMut,
+    Engine, Func, Global, Memory, Module, Snapshot, Table,
 };
 
 /// An instantiated instance of a WASM [`Module`].
@@ -18,11 +23,15 @@ use crate::{
 ///
 /// [`WebAssembly.Instance`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Instance
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 pub struct Instance {
     /// The inner instance
     instance: Py<PyAny>,
-    /// The exports of the instance
-    exports: Arc<FxHashMap<String, Extern<Engine>>>,
+    /// The exports of the instance, in the declaration order of the module's
+    /// exports
+    exports: Arc<[Extern<Engine>]>,
+    /// The module that the instance was created from
+    module: Module,
 }
 
 impl Clone for Instance {
@@ -30,13 +39,14 @@ impl Clone for Instance {
         Python::with_gil(|py| Self {
             instance: self.instance.clone_ref(py),
             exports: self.exports.clone(),
+            module: self.module.clone(),
         })
     }
 }
 
 impl WasmInstance<Engine> for Instance {
     fn new(
-        _store: impl AsContextMut<Engine>,
+        mut store: impl AsContextMut<Engine>,
         module: &Module,
         imports: &Imports<Engine>,
     ) -> anyhow::Result<Self> {
@@ -44,26 +54,35 @@ impl WasmInstance<Engine> for Instance {
             #[cfg(feature = "tracing")]
             let _span = tracing::debug_span!("Instance::new").entered();
 
+            let mut store: StoreContextMut<_> = store.as_context_mut();
+            module.check_imports(store.as_context(), imports)?;
+            limit_instance_resources(&mut store, module)?;
+
             let imports_object = create_imports_object(py, imports)?;
+            add_instrumentation_imports(&imports_object, &mut store, module)?;
 
             let instance =
                 web_assembly_instance_new(py)?.call1((module.module(py), imports_object))?;
+            count_instance_resources(&mut store, module);
 
             let exports = instance.getattr(intern!(py, "exports"))?;
             let exports = process_exports(&exports, module)?;
 
             Ok(Self {
                 instance: instance.unbind(),
-                exports: Arc::new(exports),
+                exports: exports.into(),
+                module: module.clone(),
             })
         })
     }
 
     fn exports(&self, _store: impl AsContext<Engine>) -> Box<dyn Iterator<Item = Export<Engine>>> {
         Box::new(
-            self.exports
-                .iter()
-                .map(|(name, value)| Export {
+            self.module
+                .parsed()
+                .exports()
+                .zip(self.exports.iter())
+                .map(|((name, _), value)| Export {
                     name: name.into(),
                     value: value.clone(),
                 })
@@ -73,8 +92,133 @@ impl WasmInstance<Engine> for Instance {
     }
 
     fn get_export(&self, _store: impl AsContext<Engine>, name: &str) -> Option<Extern<Engine>> {
-        self.exports.get(name).cloned()
+        let index = self.module.parsed().export_index(name)?;
+        Some(self.exports[index].clone())
+    }
+}
+
+impl Instance {
+    /// Captures a [`Snapshot`] of the state of this instance, i.e. of its
+    /// exported memories, mutable globals and `funcref` tables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading any of the exports fails, if an exported
+    /// table contains a function that is not a WebAssembly function, or if
+    /// the instance's module was created without its bytes.
+    pub fn snapshot(&self, mut ctx: impl AsContextMut<Engine>) -> anyhow::Result<Snapshot> {
+        Snapshot::capture(self, ctx.as_context_mut())
+    }
+
+    /// Restores a [`Snapshot`] into this instance, which must have been
+    /// freshly instantiated from the same [`Module`] as the instance that the
+    /// snapshot was taken from.
+    ///
+    /// Memories and tables are grown to the size they had in the snapshot.
+    /// Table elements are resolved to the functions that this instance exports
+    /// directly or through its exported tables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the snapshot was taken from an instance of a
+    /// different module, if the instance's module was created without its
+    /// bytes, or if writing any of the exports fails.
+    pub fn restore(
+        &self,
+        mut ctx: impl AsContextMut<Engine>,
+        snapshot: &Snapshot,
+    ) -> anyhow::Result<()> {
+        snapshot.restore(self, ctx.as_context_mut())
+    }
+
+    /// Wraps an existing JavaScript [`WebAssembly.Instance`] of the `module`.
+    ///
+    /// The `module` may be created with [`Module::from_js_module`] to wrap the
+    /// instance's `WebAssembly.Module`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `instance` is not a [`WebAssembly.Instance`] or if
+    /// its exports do not match those of the `module`.
+    ///
+    /// [`WebAssembly.Instance`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Instance
+    pub fn from_js(instance: &Bound<PyAny>, module: &Module) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Instance::from_js", %instance).entered();
+
+        let py = instance.py();
+
+        if !instanceof(instance, web_assembly_instance(py)?)? {
+            anyhow::bail!("expected WebAssembly.Instance but found {instance}");
+        }
+
+        let exports = instance.getattr(intern!(py, "exports"))?;
+        let exports = process_exports(&exports, module)?;
+
+        Ok(Self {
+            instance: instance.clone().unbind(),
+            exports: exports.into(),
+            module: module.clone(),
+        })
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript [`WebAssembly.Instance`].
+    ///
+    /// [`WebAssembly.Instance`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Instance
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.instance.bind(py)
     }
+
+    /// Returns the module that the instance was created from
+    pub(crate) const fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Returns the export with the given `name`
+    pub(crate) fn export(&self, name: &str) -> anyhow::Result<Extern<Engine>> {
+        match self.module.parsed().export_index(name) {
+            Some(index) => Ok(self.exports[index].clone()),
+            None => anyhow::bail!("the instance has no export named {name:?}"),
+        }
+    }
+}
+
+/// Checks the instance and the memories and tables it defines against the
+/// store's resource limiter
+fn limit_instance_resources<T>(
+    store: &mut StoreContextMut<T>,
+    module: &Module,
+) -> anyhow::Result<()> {
+    let parsed = module.parsed();
+
+    store.limit_new_resources(
+        1,
+        parsed.defined_memories().len(),
+        parsed.defined_tables().len(),
+    )?;
+
+    for memory in parsed.defined_memories() {
+        store.limit_memory_growth(0, memory.initial_pages(), memory.maximum_pages())?;
+    }
+
+    for table in parsed.defined_tables() {
+        store.limit_table_growth(0, table.minimum(), table.maximum())?;
+    }
+
+    Ok(())
+}
+
+/// Accounts for the successfully created instance and the memories and tables
+/// it defines in the store
+fn count_instance_resources<T>(store: &mut StoreContextMut<T>, module: &Module) {
+    let parsed = module.parsed();
+
+    store.count_new_resources(
+        1,
+        parsed.defined_memories().len(),
+        parsed.defined_tables().len(),
+    );
 }
 
 /// Creates the js import map
@@ -122,11 +266,38 @@ fn create_imports_object<'py>(
     Ok(imports)
 }
 
-/// Processes a wasm module's exports into a hashmap
-fn process_exports(
-    exports: &Bound<PyAny>,
+/// Adds the imports that are required by the module's instrumentation to the
+/// js import map
+fn add_instrumentation_imports<T>(
+    imports_object: &Bound<PyAny>,
+    store: &mut StoreContextMut<T>,
     module: &Module,
-) -> anyhow::Result<FxHashMap<String, Extern<Engine>>> {
+) -> anyhow::Result<()> {
+    let py = imports_object.py();
+    let instrumentation = module.instrumentation();
+
+    if !instrumentation.is_enabled() {
+        return Ok(());
+    }
+
+    let obj = create_js_object(py)?;
+    if instrumentation.fuel {
+        obj.setattr(FUEL_GLOBAL, store.fuel_global(py)?.to_py(py))?;
+    }
+    if instrumentation.epoch {
+        obj.setattr(EPOCH_GLOBAL, store.engine().epoch_global(py)?.to_py(py))?;
+        obj.setattr(
+            EPOCH_DEADLINE_GLOBAL,
+            store.epoch_deadline_global(py)?.to_py(py),
+        )?;
+    }
+    imports_object.setattr(INSTRUMENTATION_MODULE, obj)?;
+
+    Ok(())
+}
+
+/// Processes a wasm module's exports, in declaration order
+fn process_exports(exports: &Bound<PyAny>, module: &Module) -> anyhow::Result<Vec<Extern<Engine>>> {
     #[cfg(feature = "tracing")]
     let _span = tracing::debug_span!("process_exports").entered();
 
@@ -137,6 +308,8 @@ fn process_exports(
                 ExternType::Func(signature) => Extern::Func(Func::from_exported_function(
                     exports.getattr(name)?,
                     signature,
+                    Some(name),
+                    Some(module.shared_parsed()),
                 )?),
                 ExternType::Global(signature) => Extern::Global(Global::from_exported_global(
                     exports.getattr(name)?,
@@ -150,12 +323,106 @@ fn process_exports(
                 },
             };
 
-            Ok((String::from(name), export))
+            Ok(export)
         })
         .collect()
 }
 
-fn web_assembly_instance_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_INSTANCE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_INSTANCE.import(py, "js.WebAssembly.Instance", "new")
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::{
+        self as frontend,
+        backend::{Extern, Imports, Value, WasmMemory, WasmTable},
+        MemoryType, TableType, ValueType,
+    };
+
+    use crate::{test_utils, LinkError, Memory, Table};
+
+    #[test]
+    fn instantiation() {
+        let mut store = test_utils::store();
+
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (import "env" "base" (global $base i32))
+                (func (export "next") (result i32) (global.get $next))
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 0))
+                (data (i32.const 4) "data")
+                (func $start
+                    (i32.store8 (global.get $base) (i32.const 42))
+                    (global.set $next (global.get $base)))
+                (start $start)
+            )"#,
+        )
+        .unwrap();
+        let module = frontend::Module::new(store.engine(), bytes.as_slice()).unwrap();
+
+        let err =
+            frontend::Instance::new(&mut store, &module, &frontend::Imports::new()).unwrap_err();
+        let err = err.downcast_ref::<LinkError>().unwrap();
+        assert_eq!(err.missing().len(), 1);
+        assert_eq!(err.missing()[0].name(), "base");
+
+        let mut imports = frontend::Imports::new();
+        imports.define(
+            "env",
+            "base",
+            frontend::Extern::Global(frontend::Global::new(
+                &mut store,
+                frontend::Value::I32(8),
+                false,
+            )),
+        );
+        let instance = frontend::Instance::new(&mut store, &module, &imports).unwrap();
+
+        // the exports are listed in the order in which they are declared
+        let exports = instance
+            .exports(&store)
+            .map(|export| export.name)
+            .collect::<Vec<_>>();
+        assert_eq!(exports, ["next", "memory"]);
+
+        let memory = instance
+            .get_export(&store, "memory")
+            .and_then(frontend::Extern::into_memory)
+            .unwrap();
+        let mut buffer = [0; 5];
+        memory.read(&store, 4, &mut buffer).unwrap();
+        assert_eq!(&buffer, b"data\x2a");
+    }
+
+    #[test]
+    fn grown_imports() {
+        let mut store = test_utils::store();
+
+        let memory = Memory::new(&mut store, MemoryType::new(1, Some(4))).unwrap();
+        let table = Table::new(
+            &mut store,
+            TableType::new(ValueType::FuncRef, 1, None),
+            Value::FuncRef(None),
+        )
+        .unwrap();
+
+        let mut imports = Imports::default();
+        imports.define("env", "memory", Extern::Memory(memory.clone()));
+        imports.define("env", "table", Extern::Table(table.clone()));
+
+        let wat = r#"
+            (module
+                (import "env" "memory" (memory 2 4))
+                (import "env" "table" (table 3 funcref))
+            )"#;
+
+        let err = test_utils::instantiate(&mut store, wat, &imports).unwrap_err();
+        let err = err.downcast_ref::<LinkError>().unwrap();
+        assert_eq!(err.mismatched().len(), 2);
+
+        // the imports are checked against their current and not their initial size
+        memory.grow(&mut store, 1).unwrap();
+        table.grow(&mut store, 2, Value::FuncRef(None)).unwrap();
+
+        test_utils::instantiate(&mut store, wat, &imports).unwrap();
+    }
 }
@@ -0,0 +1,525 @@
+use std::convert::Infallible;
+
+use wasm_encoder::{
+    reencode::{Error, Reencode},
+    BlockType, CodeSection, EntityType, Function, ImportSection, Instruction, Module, SectionId,
+    ValType,
+};
+
+use crate::Config;
+
+/// The module name of all imports that are injected by the instrumentation
+pub const INSTRUMENTATION_MODULE: &str = "__pyodide_webassembly_runtime_layer";
+
+/// The import name of the mutable `i64` fuel counter global
+pub const FUEL_GLOBAL: &str = "fuel";
+
+/// The import name of the mutable `i64` engine epoch global
+pub const EPOCH_GLOBAL: &str = "epoch";
+
+/// The import name of the mutable `i64` store epoch deadline global
+pub const EPOCH_DEADLINE_GLOBAL: &str = "epoch_deadline";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The instrumentation that is applied to a WASM module
+pub struct Instrumentation {
+    /// Whether the module consumes fuel
+    pub fuel: bool,
+    /// Whether the module can be interrupted by epochs
+    pub epoch: bool,
+}
+
+impl Instrumentation {
+    #[must_use]
+    pub const fn from_config(config: &Config) -> Self {
+        Self {
+            fuel: config.consumes_fuel(),
+            epoch: config.epoch_interruption_enabled(),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        self.fuel || self.epoch
+    }
+
+    /// Instruments the WASM module `bytes`
+    ///
+    /// All injected imports are imported from the [`INSTRUMENTATION_MODULE`].
+    ///
+    /// With fuel, the instrumented module imports one mutable `i64` global
+    /// named [`FUEL_GLOBAL`], which is decremented by the number of executed
+    /// instructions at the start of every function and every basic block.
+    /// Once the fuel becomes negative, the module traps.
+    ///
+    /// With epochs, the instrumented module imports two mutable `i64` globals
+    /// named [`EPOCH_GLOBAL`] and [`EPOCH_DEADLINE_GLOBAL`]. At the start of
+    /// every function and every loop iteration, the module traps if the
+    /// epoch has reached the deadline.
+    ///
+    /// Returns the instrumented module together with an [`OffsetMap`] from
+    /// its instruction offsets back to those in the original module.
+    pub fn apply(self, bytes: &[u8]) -> anyhow::Result<(Vec<u8>, OffsetMap)> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Instrumentation::apply", ?self).entered();
+
+        let mut instrumenter = Instrumenter {
+            instrumentation: self,
+            imports_emitted: false,
+            imported_globals: 0,
+            function_offsets: Vec::new(),
+        };
+
+        let mut module = Module::new();
+        instrumenter.parse_core_module(&mut module, wasmparser::Parser::new(0), bytes)?;
+        let bytes = module.finish();
+
+        // the instrumented function bodies are only placed once the module is
+        // finished, so their recorded relative offsets are made absolute here
+        let mut function_offsets = instrumenter.function_offsets.into_iter();
+        let mut offsets = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload? {
+                let start = body.range().start;
+                offsets.extend(
+                    function_offsets
+                        .next()
+                        .into_iter()
+                        .flatten()
+                        .map(|(offset, original)| (start + offset, original)),
+                );
+            }
+        }
+
+        Ok((bytes, OffsetMap { offsets }))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Maps the byte offsets of instructions in an instrumented module back to
+/// their offsets in the original module
+pub struct OffsetMap {
+    /// Pairs of instrumented and original offsets, sorted by both
+    offsets: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    /// Returns the original offset of the instruction at the instrumented
+    /// `offset`
+    ///
+    /// Injected instructions are attributed to the preceding original
+    /// instruction.
+    #[must_use]
+    pub fn original(&self, offset: usize) -> Option<usize> {
+        let index = self
+            .offsets
+            .partition_point(|(instrumented, _)| *instrumented <= offset);
+
+        index.checked_sub(1).map(|index| self.offsets[index].1)
+    }
+}
+
+/// [`Reencode`]r that injects the imports and instructions of an
+/// [`Instrumentation`]
+struct Instrumenter {
+    /// The instrumentation to apply
+    instrumentation: Instrumentation,
+    /// Whether the injected imports have already been emitted
+    imports_emitted: bool,
+    /// The number of globals that the original module imports
+    imported_globals: u32,
+    /// For every function, pairs of relative instrumented and absolute
+    /// original instruction offsets
+    function_offsets: Vec<Vec<(usize, usize)>>,
+}
+
+impl Instrumenter {
+    /// The number of globals that are imported by the instrumentation
+    fn injected_globals(&self) -> u32 {
+        u32::from(self.instrumentation.fuel) + 2 * u32::from(self.instrumentation.epoch)
+    }
+
+    /// The global index of the injected fuel global
+    const fn fuel_global(&self) -> u32 {
+        self.imported_globals
+    }
+
+    /// The global index of the injected epoch global
+    fn epoch_global(&self) -> u32 {
+        self.imported_globals + u32::from(self.instrumentation.fuel)
+    }
+
+    /// The global index of the injected epoch deadline global
+    fn epoch_deadline_global(&self) -> u32 {
+        self.epoch_global() + 1
+    }
+
+    fn emit_imports(&mut self, imports: &mut ImportSection) {
+        let mutable_i64 = EntityType::Global(wasm_encoder::GlobalType {
+            val_type: ValType::I64,
+            mutable: true,
+            shared: false,
+        });
+
+        if self.instrumentation.fuel {
+            imports.import(INSTRUMENTATION_MODULE, FUEL_GLOBAL, mutable_i64);
+        }
+
+        if self.instrumentation.epoch {
+            imports.import(INSTRUMENTATION_MODULE, EPOCH_GLOBAL, mutable_i64);
+            imports.import(INSTRUMENTATION_MODULE, EPOCH_DEADLINE_GLOBAL, mutable_i64);
+        }
+
+        self.imports_emitted = true;
+    }
+
+    /// Emits the instrumentation that precedes a basic block with `cost`
+    /// instructions, which may be a function entry or loop `header`
+    fn emit_block_prologue(&self, function: &mut Function, cost: usize, header: bool) {
+        if self.instrumentation.epoch && header {
+            function
+                .instruction(&Instruction::GlobalGet(self.epoch_global()))
+                .instruction(&Instruction::GlobalGet(self.epoch_deadline_global()))
+                .instruction(&Instruction::I64GeU)
+                .instruction(&Instruction::If(BlockType::Empty))
+                .instruction(&Instruction::Unreachable)
+                .instruction(&Instruction::End);
+        }
+
+        if self.instrumentation.fuel && cost > 0 {
+            let fuel = self.fuel_global();
+            let cost = i64::try_from(cost).unwrap_or(i64::MAX);
+
+            function
+                .instruction(&Instruction::GlobalGet(fuel))
+                .instruction(&Instruction::I64Const(cost))
+                .instruction(&Instruction::I64Sub)
+                .instruction(&Instruction::GlobalSet(fuel))
+                .instruction(&Instruction::GlobalGet(fuel))
+                .instruction(&Instruction::I64Const(0))
+                .instruction(&Instruction::I64LtS)
+                .instruction(&Instruction::If(BlockType::Empty))
+                .instruction(&Instruction::Unreachable)
+                .instruction(&Instruction::End);
+        }
+    }
+
+    /// Flushes the basic `block` of instructions and their original offsets
+    /// into the `function`, preceded by its instrumentation, which may be a
+    /// function entry or loop `header`
+    fn flush_block(
+        &mut self,
+        function: &mut Function,
+        block: &mut Vec<(Instruction, usize)>,
+        header: bool,
+    ) {
+        if block.is_empty() && !header {
+            return;
+        }
+
+        self.emit_block_prologue(function, block.len(), header);
+
+        let offsets = self
+            .function_offsets
+            .last_mut()
+            .expect("function offsets are pushed before the function is instrumented");
+
+        for (instruction, original) in block.drain(..) {
+            offsets.push((function.byte_len(), original));
+            function.instruction(&instruction);
+        }
+    }
+}
+
+impl Reencode for Instrumenter {
+    type Error = Infallible;
+
+    fn global_index(&mut self, global: u32) -> u32 {
+        if global >= self.imported_globals {
+            global + self.injected_globals()
+        } else {
+            global
+        }
+    }
+
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<(), Error<Self::Error>> {
+        for import in section {
+            let import = import?;
+
+            if matches!(import.ty, wasmparser::TypeRef::Global(_)) {
+                self.imported_globals += 1;
+            }
+
+            self.parse_import(imports, import)?;
+        }
+
+        self.emit_imports(imports);
+
+        Ok(())
+    }
+
+    fn intersperse_section_hook(
+        &mut self,
+        module: &mut Module,
+        _after: Option<SectionId>,
+        before: Option<SectionId>,
+    ) -> Result<(), Error<Self::Error>> {
+        // inject an import section if the module does not have one
+        if !self.imports_emitted && !matches!(before, Some(SectionId::Type | SectionId::Import)) {
+            let mut imports = ImportSection::new();
+            self.emit_imports(&mut imports);
+            module.section(&imports);
+        }
+
+        Ok(())
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        func: wasmparser::FunctionBody<'_>,
+    ) -> Result<(), Error<Self::Error>> {
+        let mut function = self.new_function_with_parsed_locals(&func)?;
+        let mut reader = func.get_operators_reader()?;
+
+        self.function_offsets.push(Vec::new());
+
+        let mut block = Vec::new();
+        let mut header = true;
+
+        while !reader.eof() {
+            let original = reader.original_position();
+            let operator = reader.read()?;
+
+            // control flow can only enter in the middle of a function after
+            // these operators, so they end the current basic block
+            let ends_block = matches!(
+                operator,
+                wasmparser::Operator::Loop { .. }
+                    | wasmparser::Operator::If { .. }
+                    | wasmparser::Operator::Else
+                    | wasmparser::Operator::End
+                    | wasmparser::Operator::Try { .. }
+                    | wasmparser::Operator::TryTable { .. }
+                    | wasmparser::Operator::Catch { .. }
+                    | wasmparser::Operator::CatchAll
+                    | wasmparser::Operator::Delegate { .. }
+            );
+
+            let starts_loop = matches!(operator, wasmparser::Operator::Loop { .. });
+
+            block.push((self.instruction(operator)?, original));
+
+            if ends_block {
+                self.flush_block(&mut function, &mut block, header);
+                header = starts_loop;
+            }
+        }
+
+        self.flush_block(&mut function, &mut block, header);
+
+        code.function(&function);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str = r#"
+        (module
+            (import "env" "offset" (global $offset i64))
+            (global $counter (mut i64) (global.get $offset))
+            (func (export "count") (param $n i64) (result i64)
+                (block $exit
+                    (loop $continue
+                        (br_if $exit (i64.eqz (local.get $n)))
+                        (global.set $counter (i64.add (global.get $counter) (i64.const 1)))
+                        (local.set $n (i64.sub (local.get $n) (i64.const 1)))
+                        (br $continue)
+                    )
+                )
+                (global.get $counter)
+            )
+        )
+    "#;
+
+    #[test]
+    fn fuel_instrumentation() {
+        let bytes = wat::parse_str(MODULE).unwrap();
+
+        let (instrumented, _) = Instrumentation {
+            fuel: true,
+            epoch: false,
+        }
+        .apply(&bytes)
+        .unwrap();
+
+        wasmparser::Validator::new()
+            .validate_all(&instrumented)
+            .unwrap();
+
+        let mut imports = Vec::new();
+        let mut global_gets = Vec::new();
+
+        for payload in wasmparser::Parser::new(0).parse_all(&instrumented) {
+            match payload.unwrap() {
+                wasmparser::Payload::ImportSection(section) => {
+                    for import in section {
+                        let import = import.unwrap();
+                        imports.push((import.module, import.name));
+                    }
+                },
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    for operator in body.get_operators_reader().unwrap() {
+                        if let wasmparser::Operator::GlobalGet { global_index } = operator.unwrap()
+                        {
+                            global_gets.push(global_index);
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // the fuel global is imported after all existing imports
+        assert_eq!(
+            imports,
+            [("env", "offset"), (INSTRUMENTATION_MODULE, FUEL_GLOBAL)]
+        );
+
+        // the function entry, the loop header, the loop body after br_if,
+        // and the code after the block are all metered
+        assert_eq!(global_gets.iter().filter(|g| **g == 1).count(), 2 * 4);
+        // the counter global has been shifted behind the fuel global
+        assert!(global_gets.contains(&2));
+    }
+
+    #[test]
+    fn fuel_instrumentation_without_imports() {
+        let bytes = wat::parse_str("(module (func (export \"f\") (loop)))").unwrap();
+
+        let (instrumented, _) = Instrumentation {
+            fuel: true,
+            epoch: false,
+        }
+        .apply(&bytes)
+        .unwrap();
+
+        wasmparser::Validator::new()
+            .validate_all(&instrumented)
+            .unwrap();
+    }
+
+    #[test]
+    fn instrumented_offsets() {
+        let bytes = wat::parse_str(MODULE).unwrap();
+
+        let (instrumented, offsets) = Instrumentation {
+            fuel: true,
+            epoch: true,
+        }
+        .apply(&bytes)
+        .unwrap();
+
+        let operator_offsets = |bytes: &[u8]| {
+            let mut operators = Vec::new();
+            for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+                if let wasmparser::Payload::CodeSectionEntry(body) = payload.unwrap() {
+                    let mut reader = body.get_operators_reader().unwrap();
+                    while !reader.eof() {
+                        let offset = reader.original_position();
+                        // global indices are shifted, so only the kinds are compared
+                        let operator = format!("{:?}", reader.read().unwrap());
+                        let kind = operator.split([' ', '{']).next().unwrap().to_owned();
+                        operators.push((offset, kind));
+                    }
+                }
+            }
+            operators
+        };
+
+        let original = operator_offsets(&bytes);
+
+        // every original instruction maps back to its original offset, while
+        // the injected instructions map to a preceding original instruction
+        let mut mapped = operator_offsets(&instrumented)
+            .into_iter()
+            .filter_map(|(offset, operator)| {
+                let offset = offsets.original(offset)?;
+                original.contains(&(offset, operator)).then_some(offset)
+            })
+            .collect::<Vec<_>>();
+        mapped.dedup();
+
+        assert_eq!(
+            mapped,
+            original
+                .iter()
+                .map(|(offset, _)| *offset)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn epoch_instrumentation() {
+        let bytes = wat::parse_str(MODULE).unwrap();
+
+        let (instrumented, _) = Instrumentation {
+            fuel: true,
+            epoch: true,
+        }
+        .apply(&bytes)
+        .unwrap();
+
+        wasmparser::Validator::new()
+            .validate_all(&instrumented)
+            .unwrap();
+
+        let mut imports = Vec::new();
+        let mut loops = 0;
+        let mut epoch_checks = 0;
+
+        for payload in wasmparser::Parser::new(0).parse_all(&instrumented) {
+            match payload.unwrap() {
+                wasmparser::Payload::ImportSection(section) => {
+                    for import in section {
+                        let import = import.unwrap();
+                        imports.push((import.module, import.name));
+                    }
+                },
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    for operator in body.get_operators_reader().unwrap() {
+                        match operator.unwrap() {
+                            wasmparser::Operator::Loop { .. } => loops += 1,
+                            wasmparser::Operator::I64GeU => epoch_checks += 1,
+                            _ => (),
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // the epoch globals are imported after the fuel global
+        assert_eq!(
+            imports,
+            [
+                ("env", "offset"),
+                (INSTRUMENTATION_MODULE, FUEL_GLOBAL),
+                (INSTRUMENTATION_MODULE, EPOCH_GLOBAL),
+                (INSTRUMENTATION_MODULE, EPOCH_DEADLINE_GLOBAL),
+            ]
+        );
+
+        // the epoch is checked at the function entry and the loop header
+        assert_eq!(loops, 1);
+        assert_eq!(epoch_checks, 2);
+    }
+}
@@ -0,0 +1,236 @@
+//! The JavaScript APIs that are used by this crate
+//!
+//! All JavaScript objects and functions are looked up through Pyodide's `js`
+//! and `pyodide` Python modules here, and then cached. The implementation
+//! can therefore be swapped out by installing different Python modules under
+//! these names before the first lookup, which the native [`mock`] does for
+//! `cargo test`.
+//!
+//! The helper functions that are evaluated with [`run_js`], e.g.
+//! [`big_int_params`] or [`call_batch`], are replaced by Python
+//! reimplementations in the mock, which cannot evaluate JavaScript. Their
+//! JavaScript source is only tested by the Pyodide integration tests in
+//! `tests/pyodide`.
+
+use pyo3::{intern, prelude::*, sync::GILOnceCell};
+
+#[cfg(test)]
+pub mod mock;
+
+/// The `WebAssembly.validate` function
+pub fn web_assembly_validate(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_VALIDATE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_VALIDATE.import(py, "js.WebAssembly", "validate")
+}
+
+/// The `WebAssembly.Module` class
+pub fn web_assembly_module(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MODULE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MODULE.import(py, "js.WebAssembly", "Module")
+}
+
+/// The `new WebAssembly.Module` constructor
+pub fn web_assembly_module_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MODULE_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MODULE_NEW.import(py, "js.WebAssembly.Module", "new")
+}
+
+/// The `WebAssembly.Module.imports` function
+pub fn web_assembly_module_imports(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MODULE_IMPORTS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MODULE_IMPORTS.import(py, "js.WebAssembly.Module", "imports")
+}
+
+/// The `WebAssembly.Module.exports` function
+pub fn web_assembly_module_exports(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MODULE_EXPORTS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MODULE_EXPORTS.import(py, "js.WebAssembly.Module", "exports")
+}
+
+/// The `WebAssembly.Instance` class
+pub fn web_assembly_instance(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_INSTANCE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_INSTANCE.import(py, "js.WebAssembly", "Instance")
+}
+
+/// The `new WebAssembly.Instance` constructor
+pub fn web_assembly_instance_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_INSTANCE_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_INSTANCE_NEW.import(py, "js.WebAssembly.Instance", "new")
+}
+
+/// The `WebAssembly.Memory` class
+pub fn web_assembly_memory(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MEMORY: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MEMORY.import(py, "js.WebAssembly", "Memory")
+}
+
+/// The `new WebAssembly.Memory` constructor
+pub fn web_assembly_memory_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_MEMORY_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_MEMORY_NEW.import(py, "js.WebAssembly.Memory", "new")
+}
+
+/// The `WebAssembly.Table` class
+pub fn web_assembly_table(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_TABLE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_TABLE.import(py, "js.WebAssembly", "Table")
+}
+
+/// The `new WebAssembly.Table` constructor
+pub fn web_assembly_table_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_TABLE_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_TABLE_NEW.import(py, "js.WebAssembly.Table", "new")
+}
+
+/// The `WebAssembly.Global` class
+pub fn web_assembly_global(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_GLOBAL: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_GLOBAL.import(py, "js.WebAssembly", "Global")
+}
+
+/// The `new WebAssembly.Global` constructor
+pub fn web_assembly_global_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static WEB_ASSEMBLY_GLOBAL_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    WEB_ASSEMBLY_GLOBAL_NEW.import(py, "js.WebAssembly.Global", "new")
+}
+
+/// The `BigInt` function
+pub fn big_int(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static BIG_INT: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    BIG_INT.import(py, "js", "BigInt")
+}
+
+/// A function that wraps a number in a `BigInt` object, i.e.
+/// `Object(BigInt(v))`
+pub fn object_wrapped_big_int(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static OBJECT_WRAPPED_BIG_INT: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+    OBJECT_WRAPPED_BIG_INT
+        .get_or_try_init(py, || {
+            run_js(
+                py,
+                "function objectWrappedBigInt(v){ return Object(BigInt(v)); } objectWrappedBigInt",
+            )
+        })
+        .map(|x| x.bind(py))
+}
+
+/// A function that wraps a function such that the arguments at the given
+/// indices are converted into `BigInt`s before it is called, i.e.
+/// `bigIntParams(func, ...indices)`
+pub fn big_int_params(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static BIG_INT_PARAMS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+    BIG_INT_PARAMS
+        .get_or_try_init(py, || {
+            run_js(
+                py,
+                "function bigIntParams(func, ...indices){ return function(...args){ for (const i \
+                 of indices){ args[i] = BigInt(args[i]); } return func(...args); }; } bigIntParams",
+            )
+        })
+        .map(|x| x.bind(py))
+}
+
+/// A function that wraps a function such that its single result, or its
+/// results at the given indices, are converted into `BigInt`s after it has
+/// been called, i.e. `bigIntResults(func, single, ...indices)`
+pub fn big_int_results(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static BIG_INT_RESULTS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+    BIG_INT_RESULTS
+        .get_or_try_init(py, || {
+            run_js(
+                py,
+                "function bigIntResults(func, single, ...indices){ return function(...args){ \
+                 const results = func(...args); if (single){ return BigInt(results); } const \
+                 array = Array.from(results); results.destroy?.(); for (const i of indices){ \
+                 array[i] = BigInt(array[i]); } return array; }; } bigIntResults",
+            )
+        })
+        .map(|x| x.bind(py))
+}
+
+/// A function that calls a function `calls` times in a row, taking `arity`
+/// arguments for each call from the flat `args` array, and returns the
+/// `results` results of all calls in one flat array, i.e.
+/// `callBatch(func, calls, arity, results, args)`
+pub fn call_batch(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static CALL_BATCH: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+    CALL_BATCH
+        .get_or_try_init(py, || {
+            run_js(
+                py,
+                "function callBatch(func, calls, arity, results, args){ const batch = []; for \
+                 (let i = 0; i < calls; i++){ const result = func(...args.slice(i * arity, (i + \
+                 1) * arity)); if (results === 1){ batch.push(result); } else if (results > 1){ \
+                 const array = Array.from(result); result.destroy?.(); batch.push(...array); } } \
+                 return batch; } callBatch",
+            )
+        })
+        .map(|x| x.bind(py))
+}
+
+/// The `new Uint8Array` constructor
+pub fn uint8_array_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static UINT8_ARRAY_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    UINT8_ARRAY_NEW.import(py, "js.Uint8Array", "new")
+}
+
+/// The `ArrayBuffer` class
+pub fn array_buffer(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static ARRAY_BUFFER: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    ARRAY_BUFFER.import(py, "js", "ArrayBuffer")
+}
+
+/// The `ArrayBuffer.isView` function
+pub fn array_buffer_is_view(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static ARRAY_BUFFER_IS_VIEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    ARRAY_BUFFER_IS_VIEW.import(py, "js.ArrayBuffer", "isView")
+}
+
+/// The `Function` class
+pub fn function(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static FUNCTION: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    FUNCTION.import(py, "js", "Function")
+}
+
+/// The `new Object` constructor
+pub fn object_new(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static OBJECT_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    OBJECT_NEW.import(py, "js.Object", "new")
+}
+
+/// A function that checks if an object is an instance of the class with a
+/// constructor, i.e. `object instanceof constructor`
+pub fn is_instance_of(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static IS_INSTANCE_OF: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+    IS_INSTANCE_OF
+        .get_or_try_init(py, || {
+            run_js(
+                py,
+                "function isInstanceOf(object, constructor){ return (object instanceof \
+                 constructor); } isInstanceOf",
+            )
+        })
+        .map(|x| x.bind(py))
+}
+
+/// Pyodide's `pyodide.ffi.to_js` function
+pub fn to_js(py: Python<'_>) -> Result<&Bound<'_, PyAny>, PyErr> {
+    static TO_JS: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+    TO_JS.import(py, "pyodide.ffi", "to_js")
+}
+
+/// Evaluates the JavaScript `code` with Pyodide's `pyodide.code.run_js`
+pub fn run_js(py: Python<'_>, code: &str) -> Result<Py<PyAny>, PyErr> {
+    Ok(py
+        .import(intern!(py, "pyodide"))?
+        .getattr(intern!(py, "code"))?
+        .getattr(intern!(py, "run_js"))?
+        .call1((code,))?
+        .unbind())
+}
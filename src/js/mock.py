@@ -0,0 +1,373 @@
+"""The JavaScript and Pyodide glue of the native mock of the JavaScript APIs
+that are used by pyodide-webassembly-runtime-layer, which allows its tests to
+run under CPython.
+
+The mock provides the `js`, `pyodide.code`, and `pyodide.ffi` modules. Their
+`WebAssembly` namespace is implemented in Rust on top of the wasmi
+interpreter, see `mock.rs`, and passed to `install`. This module implements
+the plain JavaScript objects, errors, BigInts, and buffers around it, and
+converts values between Python and WebAssembly with `to_wasm` and
+`from_wasm`.
+
+Values cross the JavaScript boundary like in Pyodide: JavaScript numbers are
+represented as Python floats and converted to Python ints if they are safe
+integers, while BigInts are represented as Python ints.
+
+The mock cannot evaluate JavaScript. `run_js` instead returns a Python
+reimplementation of the helper function that the code defines, so the
+JavaScript source of the helpers is only tested by the Pyodide integration
+tests in `tests/pyodide`, which run against a real JavaScript engine.
+"""
+
+import math
+import struct
+import sys
+import types
+
+MAX_SAFE_INTEGER = (1 << 53) - 1
+
+
+# === JavaScript objects and errors ===
+
+
+class JsException(Exception):
+    """A JavaScript error, which Pyodide raises as a `JsException`"""
+
+    def __init__(self, name, message, stack=None):
+        super().__init__(f"{name}: {message}")
+        self.name = name
+        self.message = message
+        self.stack = f"{name}: {message}" if stack is None else stack
+
+
+def throw(name, message):
+    raise JsException(name, message)
+
+
+class Object:
+    """A plain JavaScript object"""
+
+    def __init__(self, **properties):
+        self.__dict__.update(properties)
+
+    @classmethod
+    def new(cls):
+        return cls()
+
+    def __repr__(self):
+        return "[object Object]"
+
+
+class BigIntObject:
+    """A BigInt that is wrapped in an object, i.e. `Object(BigInt(v))`"""
+
+    def __init__(self, value):
+        self.value = int(value)
+
+    def valueOf(self):
+        return self.value
+
+    def __repr__(self):
+        return f"{self.value}n"
+
+
+def BigInt(value):
+    if isinstance(value, BigIntObject):
+        return value.value
+    if isinstance(value, float):
+        if not value.is_integer():
+            throw("RangeError", f"The number {value} cannot be converted to a BigInt")
+        return int(value)
+    if isinstance(value, int):
+        return int(value)
+    throw("TypeError", f"Cannot convert {value!r} to a BigInt")
+
+
+def to_js(value):
+    """Converts a Python value passed to JavaScript"""
+    if isinstance(value, bool):
+        return value
+    if isinstance(value, int):
+        return float(value) if abs(value) <= MAX_SAFE_INTEGER else value
+    return value
+
+
+def to_py(value):
+    """Converts a JavaScript value passed to Python"""
+    if isinstance(value, float) and value.is_integer() and abs(value) <= MAX_SAFE_INTEGER:
+        return int(value)
+    return value
+
+
+def to_number(value):
+    if value is None:
+        return 0.0
+    if isinstance(value, bool):
+        return float(value)
+    if isinstance(value, float):
+        return value
+    if isinstance(value, (int, BigIntObject)):
+        throw("TypeError", "Cannot convert a BigInt value to a number")
+    return math.nan
+
+
+# === Buffers ===
+
+
+class ArrayBuffer:
+    """An ArrayBuffer, whose `data` is either a bytearray or the data of a
+    WebAssembly.Memory, which is detached once the memory has grown"""
+
+    def __init__(self, data):
+        self._data = data
+
+    @classmethod
+    def new(cls, length):
+        return cls(bytearray(int(length)))
+
+    @property
+    def detached(self):
+        return getattr(self._data, "detached", False)
+
+    @property
+    def byteLength(self):
+        return len(self._data)
+
+    @staticmethod
+    def isView(value):
+        return isinstance(value, Uint8Array)
+
+    def __repr__(self):
+        return "[object ArrayBuffer]"
+
+
+class Uint8Array:
+    def __init__(self, buffer, offset, length):
+        self.buffer = buffer
+        self.byteOffset = offset
+        self._length = length
+
+    @classmethod
+    def new(cls, source, offset=0, length=None):
+        if isinstance(source, ArrayBuffer):
+            offset = int(offset)
+            if offset > source.byteLength:
+                throw("RangeError", f"Start offset {offset} is outside the bounds of the buffer")
+            if length is None:
+                length = source.byteLength - offset
+            length = int(length)
+            if offset + length > source.byteLength:
+                throw("RangeError", f"Invalid typed array length: {length}")
+            return cls(source, offset, length)
+        if isinstance(source, int):
+            return cls(ArrayBuffer(bytearray(source)), 0, source)
+        data = bytearray(source)
+        return cls(ArrayBuffer(data), 0, len(data))
+
+    @property
+    def length(self):
+        return 0 if self.buffer.detached else self._length
+
+    @property
+    def byteLength(self):
+        return self.length
+
+    def _range(self):
+        return slice(self.byteOffset, self.byteOffset + self.length)
+
+    def assign(self, source):
+        source = memoryview(source).cast("B")
+        if len(source) != self.length:
+            raise ValueError("cannot assign from a buffer of a different length")
+        self.buffer._data[self._range()] = bytes(source)
+
+    def assign_to(self, target):
+        target = memoryview(target).cast("B")
+        if len(target) != self.length:
+            raise ValueError("cannot assign to a buffer of a different length")
+        target[:] = self.buffer._data[self._range()]
+
+    def to_bytes(self):
+        return bytes(self.buffer._data[self._range()])
+
+    def __repr__(self):
+        return "[object Uint8Array]"
+
+
+def buffer_bytes(source):
+    if isinstance(source, Uint8Array):
+        return source.to_bytes()
+    if isinstance(source, ArrayBuffer):
+        return bytes(source._data[:])
+    throw("TypeError", "WebAssembly.Module(): Argument 0 must be a buffer source")
+
+
+# === Values ===
+
+
+def signed(x, bits):
+    x &= (1 << bits) - 1
+    return x - (1 << bits) if x >> (bits - 1) & 1 else x
+
+
+def fround(x):
+    try:
+        return struct.unpack("<f", struct.pack("<f", x))[0]
+    except OverflowError:
+        return math.copysign(math.inf, x)
+
+
+def to_int32(x):
+    if math.isnan(x) or math.isinf(x):
+        return 0
+    return signed(math.trunc(x), 32)
+
+
+def is_number(value):
+    return isinstance(to_js(value), (float, int, BigIntObject))
+
+
+def to_wasm(value, ty):
+    """Converts a Python value that is passed to JavaScript into a WebAssembly
+    value of type `ty`, which is an int for integers, a float for floats, and
+    the value itself for references"""
+    value = to_js(value)
+    if ty == "i32":
+        return to_int32(to_number(value))
+    if ty == "i64":
+        if isinstance(value, bool):
+            return int(value)
+        if isinstance(value, BigIntObject):
+            return signed(value.value, 64)
+        if isinstance(value, int):
+            return signed(value, 64)
+        throw("TypeError", f"Cannot convert {value!r} to a BigInt")
+    if ty == "f32":
+        return fround(to_number(value))
+    if ty == "f64":
+        return to_number(value)
+    return value
+
+
+def from_wasm(value, ty):
+    """Converts a WebAssembly value of type `ty` into a Python value that is
+    passed from JavaScript"""
+    if ty in ("i32", "f32", "f64"):
+        return to_py(float(value))
+    return to_py(value)
+
+
+# === Helpers ===
+
+
+def big_int_params(func, *indices):
+    # the mock passes the BigInts on as wrapped BigInts, since its bare
+    # BigInts are Python ints that would be converted into numbers again
+    def call(*args):
+        args = [to_js(arg) for arg in args]
+        for i in indices:
+            args[i] = BigIntObject(BigInt(args[i]))
+        return func(*args)
+
+    return call
+
+
+def big_int_results(func, single, *indices):
+    def call(*args):
+        results = func(*args)
+        if single:
+            return BigIntObject(BigInt(to_js(results)))
+        results = [to_js(result) for result in results]
+        for i in indices:
+            results[i] = BigIntObject(BigInt(results[i]))
+        return results
+
+    return call
+
+
+class JsArray(list):
+    """A JavaScript array, which Pyodide passes to Python as a `JsProxy`"""
+
+    def to_py(self, depth=-1):
+        return list(self)
+
+
+def call_batch(func, calls, arity, results, args):
+    batch = JsArray()
+    for i in range(calls):
+        result = func(*args[i * arity : (i + 1) * arity])
+        if results == 1:
+            batch.append(result)
+        elif results > 1:
+            array = list(result)
+            if hasattr(result, "destroy"):
+                result.destroy()
+            batch.extend(array)
+    return batch
+
+
+def run_js(code):
+    """Returns a Python reimplementation of the helper function that the
+    JavaScript `code` defines and evaluates to, which is identified by its
+    name at the end of the `code`
+
+    The `code` itself is never evaluated, so the reimplementations must be
+    kept in sync with the JavaScript helpers by hand.
+    """
+
+    helpers = {
+        "objectWrappedBigInt": BigIntObject,
+        "isInstanceOf": lambda obj, constructor: isinstance(constructor, type) and isinstance(obj, constructor),
+        "bigIntParams": big_int_params,
+        "bigIntResults": big_int_results,
+        "callBatch": call_batch,
+    }
+
+    name = code.split()[-1]
+    if name not in helpers:
+        raise NotImplementedError(f"the mock cannot evaluate {code!r}")
+    return helpers[name]
+
+
+def ffi_to_js(value, create_pyproxies=False, **_kwargs):
+    return value
+
+
+def install(web_assembly):
+    """Installs the mock `js`, `pyodide.code`, and `pyodide.ffi` modules with
+    the `js.WebAssembly` module `web_assembly`"""
+
+    js = types.ModuleType("js")
+    js.WebAssembly = web_assembly
+    js.BigInt = BigInt
+    js.Uint8Array = Uint8Array
+    js.ArrayBuffer = ArrayBuffer
+    js.Object = Object
+    js.Function = web_assembly.Function
+
+    code = types.ModuleType("pyodide.code")
+    code.run_js = run_js
+
+    ffi = types.ModuleType("pyodide.ffi")
+    ffi.to_js = ffi_to_js
+    ffi.JsException = JsException
+
+    pyodide = types.ModuleType("pyodide")
+    pyodide.code = code
+    pyodide.ffi = ffi
+
+    sys.modules.update({
+        "js": js,
+        "js.WebAssembly": web_assembly,
+        "js.WebAssembly.Module": web_assembly.Module,
+        "js.WebAssembly.Instance": web_assembly.Instance,
+        "js.WebAssembly.Memory": web_assembly.Memory,
+        "js.WebAssembly.Table": web_assembly.Table,
+        "js.WebAssembly.Global": web_assembly.Global,
+        "js.Uint8Array": Uint8Array,
+        "js.ArrayBuffer": ArrayBuffer,
+        "js.Object": Object,
+        "pyodide": pyodide,
+        "pyodide.code": code,
+        "pyodide.ffi": ffi,
+    })  # fmt: skip
@@ -0,0 +1,1146 @@
+//! A native mock of the JavaScript APIs for `cargo test`
+//!
+//! The mock provides the `js`, `pyodide.code`, and `pyodide.ffi` Python
+//! modules. Its `WebAssembly` namespace is implemented here on top of the
+//! [`wasmi`] interpreter, while the JavaScript objects, buffers, and value
+//! conversions around it are implemented in `mock.py`.
+//!
+//! Every thread has its own [`wasmi::Store`], so the WebAssembly objects must
+//! not be shared between threads. Only a [`Module`] is recompiled when it is
+//! instantiated on a different thread than the one that compiled it.
+//!
+//! Imported Python callables are called while the WebAssembly function that
+//! calls them is suspended, so that they can use the store themselves, e.g.
+//! to call back into WebAssembly. Therefore, a start function cannot call an
+//! imported Python callable.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    fmt,
+    sync::{Arc, Mutex, Once, PoisonError, Weak},
+    thread::{self, ThreadId},
+};
+
+use pyo3::{
+    exceptions::PyTypeError,
+    ffi::c_str,
+    prelude::*,
+    sync::GILOnceCell,
+    types::{PyBytes, PyDict, PyList, PySlice, PyTuple},
+};
+use wasmi::{
+    core::{TrapCode, ValueType, F32, F64},
+    Extern, ExternRef, ExternType, FuncRef, FuncType, Mutability, ResumableCall, Value,
+};
+
+/// Initializes a native Python interpreter, if necessary, and installs the
+/// mock JavaScript APIs into it
+///
+/// # Panics
+///
+/// Panics if the mock could not be installed.
+pub fn install() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| -> Result<(), PyErr> {
+            let code = CString::new(include_str!("mock.py"))
+                .expect("mock.py should not contain a nul byte");
+
+            let glue = PyModule::from_code(
+                py,
+                &code,
+                c_str!("mock.py"),
+                c_str!("pyodide_webassembly_runtime_layer_mock"),
+            )?;
+            GLUE.get_or_init(py, || glue.clone().unbind());
+
+            let web_assembly = PyModule::new(py, "js.WebAssembly")?;
+            web_assembly.add_class::<Module>()?;
+            web_assembly.add_class::<Instance>()?;
+            web_assembly.add_class::<Memory>()?;
+            web_assembly.add_class::<Table>()?;
+            web_assembly.add_class::<Global>()?;
+            web_assembly.add_class::<Function>()?;
+            web_assembly.add_function(wrap_pyfunction!(validate, &web_assembly)?)?;
+
+            glue.call_method1("install", (web_assembly,))?;
+
+            Ok(())
+        })
+        .expect("installing the mock JavaScript APIs should not fail");
+    });
+}
+
+/// The `mock.py` module
+static GLUE: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+
+thread_local! {
+    /// The store that owns all WebAssembly objects of the current thread
+    static STORE: RefCell<wasmi::Store<()>> =
+        RefCell::new(wasmi::Store::new(&wasmi::Engine::default(), ()));
+
+    /// The names of the exported WebAssembly functions of the current thread,
+    /// keyed by the debug representation of their handle
+    static FUNCTION_NAMES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+fn glue(py: Python<'_>) -> &Bound<'_, PyModule> {
+    GLUE.get(py)
+        .expect("the mock JavaScript APIs should be installed")
+        .bind(py)
+}
+
+/// Runs `f` with the store of the current thread, which must not call into
+/// Python
+fn with_store<R>(f: impl FnOnce(&mut wasmi::Store<()>) -> R) -> R {
+    STORE.with(|store| f(&mut store.borrow_mut()))
+}
+
+/// Creates a JavaScript error with the given `name` and `message`
+fn js_error(py: Python<'_>, name: &str, message: impl Into<String>) -> PyErr {
+    match glue(py).call_method1("JsException", (name, message.into())) {
+        Ok(err) => PyErr::from_value(err),
+        Err(err) => err,
+    }
+}
+
+/// Converts a WebAssembly trap into the error that a JavaScript engine throws
+fn trap_error(py: Python<'_>, trap: &wasmi::core::Trap) -> PyErr {
+    if trap.downcast_ref::<HostCall>().is_some() {
+        return js_error(
+            py,
+            "RuntimeError",
+            "the mock cannot call an imported function from a start function",
+        );
+    }
+
+    let message = match trap.trap_code() {
+        Some(TrapCode::StackOverflow) => {
+            return js_error(py, "RangeError", "Maximum call stack size exceeded")
+        },
+        Some(TrapCode::UnreachableCodeReached) => "unreachable",
+        Some(TrapCode::MemoryOutOfBounds) => "memory access out of bounds",
+        Some(TrapCode::TableOutOfBounds) => "table index is out of bounds",
+        Some(TrapCode::IndirectCallToNull | TrapCode::BadSignature) => {
+            "null function or function signature mismatch"
+        },
+        Some(TrapCode::IntegerDivisionByZero) => "divide by zero",
+        Some(TrapCode::IntegerOverflow) => "divide result unrepresentable",
+        Some(TrapCode::BadConversionToInteger) => "float unrepresentable in integer range",
+        _ => return js_error(py, "RuntimeError", trap.to_string()),
+    };
+
+    js_error(py, "RuntimeError", message)
+}
+
+/// The values that were imported by an instance
+///
+/// The store never frees its host functions, which therefore only hold weak
+/// references to the imported Python callables. The imports are instead kept
+/// alive by the instance and by the objects that it exports, similar to how a
+/// JavaScript engine would collect them once they are no longer reachable.
+type Imports = Arc<[Arc<Py<PyAny>>]>;
+
+/// A call to an imported Python callable, which suspends the WebAssembly
+/// function that made it
+#[derive(Debug)]
+struct HostCall {
+    callable: Arc<Py<PyAny>>,
+    params: Vec<Value>,
+    results: Vec<ValueType>,
+}
+
+impl fmt::Display for HostCall {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("a call to an imported Python callable")
+    }
+}
+
+impl wasmi::core::HostError for HostCall {}
+
+impl HostCall {
+    /// Creates a host function of type `ty` that calls the `callable`
+    fn func(ty: FuncType, callable: Weak<Py<PyAny>>) -> wasmi::Func {
+        let results = ty.results().to_vec();
+
+        with_store(|store| {
+            wasmi::Func::new(store, ty, move |_caller, params, _results| {
+                let Some(callable) = callable.upgrade() else {
+                    return Err(wasmi::core::Trap::new(
+                        "the imported Python callable has already been freed",
+                    ));
+                };
+
+                Err(wasmi::core::Trap::from(Self {
+                    callable,
+                    params: params.to_vec(),
+                    results: results.clone(),
+                }))
+            })
+        })
+    }
+
+    fn call(&self, py: Python<'_>) -> PyResult<Vec<Value>> {
+        let args = self
+            .params
+            .iter()
+            .map(|value| from_wasm(py, value))
+            .collect::<PyResult<Vec<_>>>()?;
+        let result = self.callable.bind(py).call1(PyTuple::new(py, args)?)?;
+
+        match self.results.as_slice() {
+            [] => Ok(Vec::new()),
+            [ty] => Ok(vec![to_wasm(&result, *ty)?]),
+            results => {
+                let values = result.try_iter()?.collect::<PyResult<Vec<_>>>()?;
+                if values.len() != results.len() {
+                    return Err(js_error(py, "TypeError", "multi-return length mismatch"));
+                }
+                values
+                    .iter()
+                    .zip(results)
+                    .map(|(value, ty)| to_wasm(value, *ty))
+                    .collect()
+            },
+        }
+    }
+}
+
+/// Calls the `func` with the `params`, and calls the imported Python
+/// callables that it calls in turn
+fn call(py: Python<'_>, func: wasmi::Func, params: &[Value]) -> PyResult<Vec<Value>> {
+    let mut results = with_store(|store| {
+        func.ty(&*store)
+            .results()
+            .iter()
+            .copied()
+            .map(Value::default)
+            .collect::<Vec<_>>()
+    });
+
+    let mut call = with_store(|store| func.call_resumable(store, params, &mut results));
+
+    loop {
+        match call {
+            Ok(ResumableCall::Finished) => return Ok(results),
+            Ok(ResumableCall::Resumable(invocation)) => {
+                let host_results = match invocation.host_error().downcast_ref::<HostCall>() {
+                    Some(host_call) => host_call.call(py)?,
+                    None => return Err(trap_error(py, invocation.host_error())),
+                };
+                call = with_store(|store| invocation.resume(store, &host_results, &mut results));
+            },
+            // an imported Python callable that is called directly
+            Err(wasmi::Error::Trap(trap)) => {
+                return trap.downcast_ref::<HostCall>().map_or_else(
+                    || Err(trap_error(py, &trap)),
+                    |host_call| host_call.call(py),
+                );
+            },
+            Err(err) => return Err(js_error(py, "TypeError", err.to_string())),
+        }
+    }
+}
+
+const fn value_type_name(ty: ValueType) -> &'static str {
+    match ty {
+        ValueType::I32 => "i32",
+        ValueType::I64 => "i64",
+        ValueType::F32 => "f32",
+        ValueType::F64 => "f64",
+        ValueType::FuncRef => "funcref",
+        ValueType::ExternRef => "externref",
+    }
+}
+
+fn value_type(name: &str) -> Option<ValueType> {
+    match name {
+        "i32" => Some(ValueType::I32),
+        "i64" => Some(ValueType::I64),
+        "f32" => Some(ValueType::F32),
+        "f64" => Some(ValueType::F64),
+        "funcref" | "anyfunc" => Some(ValueType::FuncRef),
+        "externref" => Some(ValueType::ExternRef),
+        _ => None,
+    }
+}
+
+/// Converts a Python value that is passed to JavaScript into a WebAssembly
+/// value of type `ty`
+#[allow(clippy::cast_possible_truncation)]
+fn to_wasm(value: &Bound<PyAny>, ty: ValueType) -> PyResult<Value> {
+    let py = value.py();
+
+    let value = glue(py).call_method1("to_wasm", (value, value_type_name(ty)))?;
+
+    Ok(match ty {
+        ValueType::I32 => Value::I32(value.extract()?),
+        ValueType::I64 => Value::I64(value.extract()?),
+        ValueType::F32 => Value::F32(F32::from_bits((value.extract::<f64>()? as f32).to_bits())),
+        ValueType::F64 => Value::F64(F64::from_bits(value.extract::<f64>()?.to_bits())),
+        ValueType::FuncRef => match value.extract::<Option<PyRef<Function>>>() {
+            Ok(func) => Value::FuncRef(FuncRef::new(func.map(|func| func.func))),
+            Err(_) => {
+                return Err(js_error(
+                    py,
+                    "TypeError",
+                    "type incompatibility when transforming from/to JS",
+                ))
+            },
+        },
+        ValueType::ExternRef => {
+            let object = (!value.is_none()).then(|| value.unbind());
+            Value::ExternRef(with_store(|store| {
+                ExternRef::new::<Py<PyAny>>(store, object)
+            }))
+        },
+    })
+}
+
+/// Converts a WebAssembly value into a Python value that is passed from
+/// JavaScript
+fn from_wasm<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    let raw = match value {
+        Value::I32(value) => value.into_pyobject(py)?.into_any(),
+        Value::I64(value) => value.into_pyobject(py)?.into_any(),
+        Value::F32(value) => f64::from(f32::from_bits(value.to_bits()))
+            .into_pyobject(py)?
+            .into_any(),
+        Value::F64(value) => f64::from_bits(value.to_bits())
+            .into_pyobject(py)?
+            .into_any(),
+        Value::FuncRef(func) => match func.func() {
+            Some(func) => function(py, *func)?,
+            None => py.None().into_bound(py),
+        },
+        Value::ExternRef(object) => with_store(|store| {
+            object
+                .data(&*store)
+                .and_then(|object| object.downcast_ref::<Py<PyAny>>())
+                .map(|object| object.clone_ref(py))
+        })
+        .unwrap_or_else(|| py.None())
+        .into_bound(py),
+    };
+
+    glue(py).call_method1("from_wasm", (raw, value_type_name(value.ty())))
+}
+
+/// Returns the value of the `property` of a JavaScript `object`, unless it is
+/// missing or `undefined`
+fn property<'py>(object: &Bound<'py, PyAny>, property: &str) -> Option<Bound<'py, PyAny>> {
+    object
+        .getattr(property)
+        .ok()
+        .filter(|value| !value.is_none())
+}
+
+/// Creates a plain JavaScript object with the given `properties`
+fn object<'py>(properties: &Bound<'py, PyDict>) -> PyResult<Bound<'py, PyAny>> {
+    glue(properties.py())
+        .getattr("Object")?
+        .call((), Some(properties))
+}
+
+/// Returns the JS Type Reflection descriptor of the type `ty`
+fn descriptor<'py>(py: Python<'py>, ty: &ExternType) -> PyResult<Bound<'py, PyAny>> {
+    match ty {
+        ExternType::Func(ty) => func_descriptor(py, ty),
+        ExternType::Table(ty) => table_descriptor(py, ty.element(), ty.minimum(), ty.maximum()),
+        ExternType::Memory(ty) => memory_descriptor(
+            py,
+            u32::from(ty.initial_pages()),
+            ty.maximum_pages().map(u32::from),
+        ),
+        ExternType::Global(ty) => {
+            let properties = PyDict::new(py);
+            properties.set_item("value", value_type_name(ty.content()))?;
+            properties.set_item("mutable", ty.mutability() == Mutability::Var)?;
+            object(&properties)
+        },
+    }
+}
+
+fn func_descriptor<'py>(py: Python<'py>, ty: &FuncType) -> PyResult<Bound<'py, PyAny>> {
+    let names = |types: &[ValueType]| {
+        types
+            .iter()
+            .map(|ty| value_type_name(*ty))
+            .collect::<Vec<_>>()
+    };
+
+    let properties = PyDict::new(py);
+    properties.set_item("parameters", names(ty.params()))?;
+    properties.set_item("results", names(ty.results()))?;
+    object(&properties)
+}
+
+fn table_descriptor(
+    py: Python<'_>,
+    element: ValueType,
+    minimum: u32,
+    maximum: Option<u32>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let properties = PyDict::new(py);
+    properties.set_item("element", value_type_name(element))?;
+    properties.set_item("minimum", minimum)?;
+    if let Some(maximum) = maximum {
+        properties.set_item("maximum", maximum)?;
+    }
+    object(&properties)
+}
+
+fn memory_descriptor(
+    py: Python<'_>,
+    minimum: u32,
+    maximum: Option<u32>,
+) -> PyResult<Bound<'_, PyAny>> {
+    let properties = PyDict::new(py);
+    properties.set_item("minimum", minimum)?;
+    if let Some(maximum) = maximum {
+        properties.set_item("maximum", maximum)?;
+    }
+    properties.set_item("shared", false)?;
+    object(&properties)
+}
+
+const fn extern_kind(ty: &ExternType) -> &'static str {
+    match ty {
+        ExternType::Func(_) => "function",
+        ExternType::Table(_) => "table",
+        ExternType::Memory(_) => "memory",
+        ExternType::Global(_) => "global",
+    }
+}
+
+/// Returns the bytes of a JavaScript buffer `source`
+fn buffer_bytes(source: &Bound<PyAny>) -> PyResult<Vec<u8>> {
+    glue(source.py())
+        .call_method1("buffer_bytes", (source,))?
+        .extract()
+}
+
+fn compile(py: Python<'_>, bytes: &[u8]) -> PyResult<wasmi::Module> {
+    with_store(|store| wasmi::Module::new(store.engine(), bytes))
+        .map_err(|err| js_error(py, "CompileError", format!("WebAssembly.Module(): {err}")))
+}
+
+/// The `WebAssembly.validate` function
+#[pyfunction]
+fn validate(source: &Bound<PyAny>) -> PyResult<bool> {
+    let bytes = buffer_bytes(source)?;
+    Ok(compile(source.py(), &bytes).is_ok())
+}
+
+#[pyclass(frozen, name = "Module")]
+struct Module {
+    bytes: Vec<u8>,
+    compiled: Mutex<(ThreadId, Arc<wasmi::Module>)>,
+    /// The function indices of the exported functions, by their export name
+    functions: HashMap<String, u32>,
+    /// The names of the exports in declaration order
+    exports: Vec<String>,
+}
+
+impl Module {
+    /// Returns the module compiled for the store of the current thread
+    fn compiled(&self, py: Python<'_>) -> PyResult<Arc<wasmi::Module>> {
+        let mut compiled = self.compiled.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if compiled.0 != thread::current().id() {
+            *compiled = (thread::current().id(), Arc::new(compile(py, &self.bytes)?));
+        }
+
+        Ok(Arc::clone(&compiled.1))
+    }
+}
+
+#[pymethods]
+impl Module {
+    #[staticmethod]
+    #[pyo3(name = "new")]
+    fn js_new(source: &Bound<PyAny>) -> PyResult<Self> {
+        let py = source.py();
+
+        let bytes = buffer_bytes(source)?;
+        let compiled = compile(py, &bytes)?;
+
+        let mut functions = HashMap::new();
+        let mut exports = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+            if let Ok(wasmparser::Payload::ExportSection(section)) = payload {
+                for export in section.into_iter().flatten() {
+                    if export.kind == wasmparser::ExternalKind::Func {
+                        functions.insert(export.name.to_owned(), export.index);
+                    }
+                    exports.push(export.name.to_owned());
+                }
+            }
+        }
+
+        Ok(Self {
+            bytes,
+            compiled: Mutex::new((thread::current().id(), Arc::new(compiled))),
+            functions,
+            exports,
+        })
+    }
+
+    #[staticmethod]
+    fn imports<'py>(module: &Bound<'py, Self>) -> PyResult<Bound<'py, PyList>> {
+        let py = module.py();
+
+        let compiled = module.get().compiled(py)?;
+
+        let imports = compiled
+            .imports()
+            .map(|import| {
+                let properties = PyDict::new(py);
+                properties.set_item("module", import.module())?;
+                properties.set_item("name", import.name())?;
+                properties.set_item("kind", extern_kind(import.ty()))?;
+                properties.set_item("type", descriptor(py, import.ty())?)?;
+                object(&properties)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, imports)
+    }
+
+    #[staticmethod]
+    fn exports<'py>(module: &Bound<'py, Self>) -> PyResult<Bound<'py, PyList>> {
+        let py = module.py();
+
+        let compiled = module.get().compiled(py)?;
+
+        let exports = module
+            .get()
+            .exports
+            .iter()
+            .filter_map(|name| Some((name, compiled.get_export(name)?)))
+            .map(|(name, ty)| {
+                let properties = PyDict::new(py);
+                properties.set_item("name", name)?;
+                properties.set_item("kind", extern_kind(&ty))?;
+                properties.set_item("type", descriptor(py, &ty)?)?;
+                object(&properties)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, exports)
+    }
+
+    #[allow(clippy::unused_self)]
+    fn __repr__(&self) -> &'static str {
+        "[object WebAssembly.Module]"
+    }
+}
+
+#[pyclass(frozen, name = "Instance")]
+struct Instance {
+    #[pyo3(get)]
+    exports: Py<PyAny>,
+    _imports: Imports,
+}
+
+#[pymethods]
+impl Instance {
+    #[staticmethod]
+    #[pyo3(name = "new", signature = (module, imports = None))]
+    #[allow(clippy::too_many_lines)]
+    fn js_new(module: &Bound<Module>, imports: Option<&Bound<PyAny>>) -> PyResult<Self> {
+        let py = module.py();
+
+        let compiled = module.get().compiled(py)?;
+
+        let mut externs = Vec::new();
+        let mut values = Vec::new();
+        for (index, import) in compiled.imports().enumerate() {
+            let (module_name, name) = (import.module(), import.name());
+
+            let Some(namespace) = imports.and_then(|imports| property(imports, module_name)) else {
+                return Err(js_error(
+                    py,
+                    "TypeError",
+                    format!(
+                        "WebAssembly.Instance(): Import #{index} \"{module_name}\": module is not \
+                         an object or function"
+                    ),
+                ));
+            };
+            let value = property(&namespace, name);
+            let kept = Arc::new(
+                value
+                    .as_ref()
+                    .map_or_else(|| py.None(), |value| value.clone().unbind()),
+            );
+            values.push(Arc::clone(&kept));
+
+            let link_error = |message: &str| {
+                js_error(
+                    py,
+                    "LinkError",
+                    format!(
+                        "WebAssembly.Instance(): Import \"{module_name}\" \"{name}\": {message}"
+                    ),
+                )
+            };
+
+            let value = match (import.ty(), value) {
+                (ExternType::Func(_), Some(value)) if value.is_instance_of::<Function>() => {
+                    Extern::Func(value.downcast::<Function>()?.get().func)
+                },
+                (ExternType::Func(ty), Some(value)) if value.is_callable() => {
+                    Extern::Func(HostCall::func(ty.clone(), Arc::downgrade(&kept)))
+                },
+                (ExternType::Func(_), _) => {
+                    return Err(link_error("function import requires a callable"))
+                },
+                (ExternType::Table(_), Some(value)) if value.is_instance_of::<Table>() => {
+                    Extern::Table(value.downcast::<Table>()?.get().table)
+                },
+                (ExternType::Table(_), _) => {
+                    return Err(link_error("table import requires a WebAssembly.Table"))
+                },
+                (ExternType::Memory(_), Some(value)) if value.is_instance_of::<Memory>() => {
+                    Extern::Memory(value.downcast::<Memory>()?.get().memory)
+                },
+                (ExternType::Memory(_), _) => {
+                    return Err(link_error(
+                        "memory import must be a WebAssembly.Memory object",
+                    ))
+                },
+                (ExternType::Global(_), Some(value)) if value.is_instance_of::<Global>() => {
+                    Extern::Global(value.downcast::<Global>()?.get().global)
+                },
+                (ExternType::Global(ty), Some(value))
+                    if ty.mutability() == Mutability::Const
+                        && !matches!(ty.content(), ValueType::FuncRef | ValueType::ExternRef)
+                        && glue(py).call_method1("is_number", (&value,))?.is_truthy()? =>
+                {
+                    let value = to_wasm(&value, ty.content())?;
+                    Extern::Global(with_store(|store| {
+                        wasmi::Global::new(store, value, Mutability::Const)
+                    }))
+                },
+                (ExternType::Global(_), _) => {
+                    return Err(link_error(
+                        "global import must be a number, valid Wasm reference, or \
+                         WebAssembly.Global object",
+                    ))
+                },
+            };
+
+            externs.push((module_name, name, value));
+        }
+
+        let instance = with_store(|store| {
+            let mut linker = wasmi::Linker::new(store.engine());
+            let mut defined = HashSet::new();
+            for (module_name, name, value) in externs {
+                if defined.insert((module_name, name)) {
+                    linker.define(module_name, name, value)?;
+                }
+            }
+            linker
+                .instantiate(&mut *store, &compiled)?
+                .start(&mut *store)
+        })
+        .map_err(|err| match err {
+            wasmi::Error::Trap(trap) => trap_error(py, &trap),
+            err => js_error(py, "LinkError", format!("WebAssembly.Instance(): {err}")),
+        })?;
+
+        let exports = with_store(|store| {
+            instance
+                .exports(&*store)
+                .map(|export| (export.name().to_owned(), export.into_extern()))
+                .collect::<Vec<_>>()
+        });
+
+        let imports: Imports = values.into();
+
+        let object = glue(py).call_method0("Object")?;
+        for (name, value) in exports {
+            let imports = Some(Arc::clone(&imports));
+            let value = match value {
+                Extern::Func(func) => {
+                    if let Some(index) = module.get().functions.get(&name) {
+                        FUNCTION_NAMES.with(|names| {
+                            names
+                                .borrow_mut()
+                                .entry(format!("{func:?}"))
+                                .or_insert_with(|| index.to_string());
+                        });
+                    }
+                    Bound::new(
+                        py,
+                        Function {
+                            func,
+                            _imports: imports,
+                        },
+                    )?
+                    .into_any()
+                },
+                Extern::Table(table) => Bound::new(
+                    py,
+                    Table {
+                        table,
+                        _imports: imports,
+                    },
+                )?
+                .into_any(),
+                Extern::Memory(memory) => Bound::new(
+                    py,
+                    Memory {
+                        memory,
+                        _imports: imports,
+                    },
+                )?
+                .into_any(),
+                Extern::Global(global) => Bound::new(
+                    py,
+                    Global {
+                        global,
+                        _imports: imports,
+                    },
+                )?
+                .into_any(),
+            };
+            object.setattr(name.as_str(), value)?;
+        }
+
+        Ok(Self {
+            exports: object.unbind(),
+            _imports: imports,
+        })
+    }
+
+    #[allow(clippy::unused_self)]
+    fn __repr__(&self) -> &'static str {
+        "[object WebAssembly.Instance]"
+    }
+}
+
+#[pyclass(frozen, name = "Memory")]
+struct Memory {
+    memory: wasmi::Memory,
+    /// The imports of the instance that exported this object, if any
+    _imports: Option<Imports>,
+}
+
+#[pymethods]
+impl Memory {
+    #[staticmethod]
+    #[pyo3(name = "new")]
+    fn js_new(descriptor: &Bound<PyAny>) -> PyResult<Self> {
+        let py = descriptor.py();
+
+        let initial = property(descriptor, "initial")
+            .map(|initial| initial.extract())
+            .transpose()?
+            .unwrap_or(0);
+        let maximum = property(descriptor, "maximum")
+            .map(|maximum| maximum.extract())
+            .transpose()?;
+
+        let memory = wasmi::MemoryType::new(initial, maximum)
+            .and_then(|ty| with_store(|store| wasmi::Memory::new(store, ty)))
+            .map_err(|_| {
+                js_error(
+                    py,
+                    "RangeError",
+                    "WebAssembly.Memory(): Property 'initial' is out of bounds",
+                )
+            })?;
+
+        Ok(Self {
+            memory,
+            _imports: None,
+        })
+    }
+
+    #[getter]
+    fn buffer<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let data = MemoryData {
+            memory: self.memory,
+            pages: with_store(|store| u32::from(self.memory.current_pages(&*store))),
+        };
+
+        glue(py).call_method1("ArrayBuffer", (data,))
+    }
+
+    fn grow(&self, py: Python<'_>, delta: u32) -> PyResult<u32> {
+        wasmi::core::Pages::new(delta)
+            .and_then(|delta| with_store(|store| self.memory.grow(store, delta).ok()))
+            .map(u32::from)
+            .ok_or_else(|| {
+                js_error(
+                    py,
+                    "RangeError",
+                    "WebAssembly.Memory.grow(): Maximum memory size exceeded",
+                )
+            })
+    }
+
+    #[pyo3(name = "type")]
+    fn ty<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (pages, ty) =
+            with_store(|store| (self.memory.current_pages(&*store), self.memory.ty(&*store)));
+
+        memory_descriptor(py, u32::from(pages), ty.maximum_pages().map(u32::from))
+    }
+
+    #[allow(clippy::unused_self)]
+    fn __repr__(&self) -> &'static str {
+        "[object WebAssembly.Memory]"
+    }
+}
+
+/// The data of a memory, which backs a JavaScript `ArrayBuffer` and is
+/// detached once the memory has grown
+#[pyclass(frozen)]
+struct MemoryData {
+    memory: wasmi::Memory,
+    pages: u32,
+}
+
+impl MemoryData {
+    /// Returns the byte range of the data that the `slice` selects
+    fn range(&self, slice: &Bound<PySlice>) -> PyResult<std::ops::Range<usize>> {
+        let len = isize::try_from(self.__len__()).unwrap_or(isize::MAX);
+        let indices = slice.indices(len)?;
+
+        let start = usize::try_from(indices.start).unwrap_or(0);
+        let stop = usize::try_from(indices.stop).unwrap_or(0).max(start);
+
+        Ok(start..stop)
+    }
+}
+
+#[pymethods]
+impl MemoryData {
+    #[getter]
+    fn detached(&self) -> bool {
+        with_store(|store| u32::from(self.memory.current_pages(&*store)) != self.pages)
+    }
+
+    fn __len__(&self) -> usize {
+        if self.detached() {
+            return 0;
+        }
+
+        with_store(|store| self.memory.data(&*store).len())
+    }
+
+    fn __getitem__<'py>(&self, slice: &Bound<'py, PySlice>) -> PyResult<Bound<'py, PyBytes>> {
+        let range = self.range(slice)?;
+
+        with_store(|store| Ok(PyBytes::new(slice.py(), &self.memory.data(&*store)[range])))
+    }
+
+    fn __setitem__(&self, slice: &Bound<PySlice>, data: &[u8]) -> PyResult<()> {
+        let range = self.range(slice)?;
+
+        if range.len() != data.len() {
+            return Err(PyTypeError::new_err(
+                "cannot assign from a buffer of a different length",
+            ));
+        }
+
+        with_store(|store| self.memory.data_mut(&mut *store)[range].copy_from_slice(data));
+
+        Ok(())
+    }
+}
+
+#[pyclass(frozen, name = "Table")]
+struct Table {
+    table: wasmi::Table,
+    /// The imports of the instance that exported this object, if any
+    _imports: Option<Imports>,
+}
+
+#[pymethods]
+impl Table {
+    #[staticmethod]
+    #[pyo3(name = "new", signature = (descriptor, value = None))]
+    fn js_new(descriptor: &Bound<PyAny>, value: Option<&Bound<PyAny>>) -> PyResult<Self> {
+        let py = descriptor.py();
+
+        let element = property(descriptor, "element")
+            .and_then(|element| value_type(&element.extract::<String>().ok()?))
+            .filter(|element| matches!(element, ValueType::FuncRef | ValueType::ExternRef))
+            .ok_or_else(|| {
+                js_error(
+                    py,
+                    "TypeError",
+                    "WebAssembly.Table(): Descriptor property 'element' must be a WebAssembly \
+                     reference type",
+                )
+            })?;
+        let initial = property(descriptor, "initial")
+            .map(|initial| initial.extract())
+            .transpose()?
+            .unwrap_or(0);
+        let maximum = property(descriptor, "maximum")
+            .map(|maximum| maximum.extract())
+            .transpose()?;
+
+        let value = to_wasm(
+            &value.map_or_else(|| py.None().into_bound(py), Clone::clone),
+            element,
+        )?;
+
+        let table = with_store(|store| {
+            wasmi::Table::new(
+                store,
+                wasmi::TableType::new(element, initial, maximum),
+                value,
+            )
+        })
+        .map_err(|_| {
+            js_error(
+                py,
+                "RangeError",
+                "WebAssembly.Table(): Property 'initial' is out of bounds",
+            )
+        })?;
+
+        Ok(Self {
+            table,
+            _imports: None,
+        })
+    }
+
+    #[getter]
+    fn length(&self) -> u32 {
+        with_store(|store| self.table.size(&*store))
+    }
+
+    fn get<'py>(&self, py: Python<'py>, index: u32) -> PyResult<Bound<'py, PyAny>> {
+        let value = with_store(|store| self.table.get(&*store, index)).ok_or_else(|| {
+            js_error(py, "RangeError", "WebAssembly.Table.get(): invalid address")
+        })?;
+
+        from_wasm(py, &value)
+    }
+
+    #[pyo3(signature = (index, value = None))]
+    fn set(&self, py: Python<'_>, index: u32, value: Option<&Bound<PyAny>>) -> PyResult<()> {
+        let value = self.element(py, value)?;
+
+        with_store(|store| self.table.set(store, index, value))
+            .map_err(|_| js_error(py, "RangeError", "WebAssembly.Table.set(): invalid address"))
+    }
+
+    #[pyo3(signature = (delta, value = None))]
+    fn grow(&self, py: Python<'_>, delta: u32, value: Option<&Bound<PyAny>>) -> PyResult<u32> {
+        let value = self.element(py, value)?;
+
+        with_store(|store| self.table.grow(store, delta, value)).map_err(|_| {
+            js_error(
+                py,
+                "RangeError",
+                "WebAssembly.Table.grow(): failed to grow table",
+            )
+        })
+    }
+
+    #[pyo3(name = "type")]
+    fn ty<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let (size, ty) = with_store(|store| (self.table.size(&*store), self.table.ty(&*store)));
+
+        table_descriptor(py, ty.element(), size, ty.maximum())
+    }
+
+    #[allow(clippy::unused_self)]
+    fn __repr__(&self) -> &'static str {
+        "[object WebAssembly.Table]"
+    }
+}
+
+impl Table {
+    /// Converts the `value` into an element of the table
+    fn element(&self, py: Python<'_>, value: Option<&Bound<PyAny>>) -> PyResult<Value> {
+        let element = with_store(|store| self.table.ty(&*store).element());
+
+        to_wasm(
+            &value.map_or_else(|| py.None().into_bound(py), Clone::clone),
+            element,
+        )
+    }
+}
+
+#[pyclass(frozen, name = "Global")]
+struct Global {
+    global: wasmi::Global,
+    /// The imports of the instance that exported this object, if any
+    _imports: Option<Imports>,
+}
+
+#[pymethods]
+impl Global {
+    #[staticmethod]
+    #[pyo3(name = "new", signature = (descriptor, value = None))]
+    fn js_new(descriptor: &Bound<PyAny>, value: Option<&Bound<PyAny>>) -> PyResult<Self> {
+        let py = descriptor.py();
+
+        let content = property(descriptor, "value")
+            .and_then(|content| value_type(&content.extract::<String>().ok()?))
+            .ok_or_else(|| {
+                js_error(
+                    py,
+                    "TypeError",
+                    "WebAssembly.Global(): Descriptor property 'value' must be a WebAssembly type",
+                )
+            })?;
+        let mutability = match property(descriptor, "mutable") {
+            Some(mutable) if mutable.is_truthy()? => Mutability::Var,
+            _ => Mutability::Const,
+        };
+
+        let value = match value.filter(|value| !value.is_none()) {
+            Some(value) => to_wasm(value, content)?,
+            None if matches!(content, ValueType::FuncRef | ValueType::ExternRef) => {
+                to_wasm(&py.None().into_bound(py), content)?
+            },
+            None => Value::default(content),
+        };
+
+        Ok(Self {
+            global: with_store(|store| wasmi::Global::new(store, value, mutability)),
+            _imports: None,
+        })
+    }
+
+    #[getter]
+    fn value<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        from_wasm(py, &with_store(|store| self.global.get(&*store)))
+    }
+
+    #[setter]
+    fn set_value(&self, value: &Bound<PyAny>) -> PyResult<()> {
+        let py = value.py();
+
+        let ty = with_store(|store| self.global.ty(&*store));
+        if ty.mutability() == Mutability::Const {
+            return Err(js_error(
+                py,
+                "TypeError",
+                "Can't set the value of an immutable global.",
+            ));
+        }
+
+        let value = to_wasm(value, ty.content())?;
+
+        with_store(|store| self.global.set(store, value))
+            .map_err(|err| js_error(py, "TypeError", err.to_string()))
+    }
+
+    #[pyo3(name = "valueOf")]
+    fn value_of<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.value(py)
+    }
+
+    #[pyo3(name = "type")]
+    fn ty<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        descriptor(
+            py,
+            &ExternType::Global(with_store(|store| self.global.ty(&*store))),
+        )
+    }
+
+    #[allow(clippy::unused_self)]
+    fn __repr__(&self) -> &'static str {
+        "[object WebAssembly.Global]"
+    }
+}
+
+/// A WebAssembly function, or an imported Python callable that was wrapped
+/// into one
+///
+/// Exported WebAssembly functions are named by their function index, while
+/// all other functions have an empty name.
+#[pyclass(frozen, name = "Function")]
+struct Function {
+    func: wasmi::Func,
+    /// The imports of the instance that exported this object, if any
+    _imports: Option<Imports>,
+}
+
+fn function(py: Python<'_>, func: wasmi::Func) -> PyResult<Bound<'_, PyAny>> {
+    Ok(Bound::new(
+        py,
+        Function {
+            func,
+            _imports: None,
+        },
+    )?
+    .into_any())
+}
+
+#[pymethods]
+impl Function {
+    #[getter]
+    fn length(&self) -> usize {
+        with_store(|store| self.func.ty(&*store).params().len())
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        FUNCTION_NAMES.with(|names| {
+            names
+                .borrow()
+                .get(&format!("{:?}", self.func))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    #[pyo3(name = "type")]
+    fn ty<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        func_descriptor(py, &with_store(|store| self.func.ty(&*store)))
+    }
+
+    #[pyo3(signature = (*args))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        args: &Bound<'py, PyTuple>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let ty = with_store(|store| self.func.ty(&*store));
+
+        let params = ty
+            .params()
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                let arg = args
+                    .get_item(i)
+                    .unwrap_or_else(|_| py.None().into_bound(py));
+                to_wasm(&arg, *ty)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let results = call(py, self.func, &params)?
+            .iter()
+            .map(|value| from_wasm(py, value))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        match <[_; 1]>::try_from(results) {
+            Ok([result]) => Ok(result),
+            Err(results) if results.is_empty() => Ok(py.None().into_bound(py)),
+            Err(results) => Ok(PyList::new(py, results)?.into_any()),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("function {}() {{ [native code] }}", self.name())
+    }
+}
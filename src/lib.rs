@@ -74,34 +74,142 @@
 //! [`Func`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html
 //! [`Store`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Store.html
 
-use wasm_runtime_layer::backend::WasmEngine;
+use std::{fmt, sync::Arc};
 
+use pyo3::{prelude::*, sync::GILOnceCell};
+use wasm_runtime_layer::backend::{Value, WasmEngine};
+
+mod backtrace;
+mod config;
 mod conversion;
+mod debug;
 mod externref;
 mod features;
+mod float_bits;
 mod func;
 mod global;
 mod instance;
+mod instrument;
+mod js;
+mod limits;
+mod link;
 mod memory;
 mod module;
+mod reflection;
+mod snapshot;
 mod store;
 mod table;
+#[cfg(test)]
+mod test_utils;
+mod trap;
+mod typed_func;
 
+pub use backtrace::{FrameInfo, WasmBacktrace};
+pub use config::Config;
+pub use debug::SourceLocation;
 pub use externref::ExternRef;
 pub use func::Func;
 pub use global::Global;
 pub use instance::Instance;
+pub use limits::{
+    ResourceLimitError, ResourceLimiter, StoreLimits, DEFAULT_INSTANCE_LIMIT, DEFAULT_MEMORY_LIMIT,
+    DEFAULT_TABLE_LIMIT,
+};
+pub use link::{LinkError, MismatchedImport, MissingImport};
 pub use memory::Memory;
 pub use module::Module;
+pub use snapshot::{Snapshot, SnapshotFormatError};
 pub use store::{Store, StoreContext, StoreContextMut};
 pub use table::Table;
+pub use trap::{CallFrame, StackOverflowError, Trap};
+pub use typed_func::{TypedFunc, WasmParams, WasmResults, WasmTy};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Clone)]
 /// Runtime for [`WebAssembly`] web runtime.
 ///
 /// [`WebAssembly`]: https://developer.mozilla.org/en-US/docs/WebAssembly
 pub struct Engine {
-    _private: (),
+    /// The engine's configuration
+    config: Config,
+    /// The epoch counter global, which is lazily created and shared between
+    /// clones of the engine
+    epoch: Arc<GILOnceCell<Global>>,
+}
+
+impl Engine {
+    #[must_use]
+    /// Creates a new engine with the given `config`uration.
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            epoch: Arc::new(GILOnceCell::new()),
+        }
+    }
+
+    #[must_use]
+    /// Returns the configuration of this engine.
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Increments the epoch of this engine and all of its clones.
+    ///
+    /// Guest code that is executing in a [`Store`] of this engine is
+    /// interrupted with [`Trap::Interrupt`] once the epoch reaches the
+    /// store's deadline, see [`Config::epoch_interruption`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if epoch interruption is not enabled with
+    /// [`Config::epoch_interruption`].
+    pub fn increment_epoch(&self) -> anyhow::Result<()> {
+        Python::with_gil(|py| {
+            let epoch = self.epoch_global(py)?;
+
+            let Value::I64(current) = epoch.get_value(py)? else {
+                anyhow::bail!("epoch global has an invalid value");
+            };
+            epoch.set_value(py, &Value::I64(current.wrapping_add(1)))?;
+
+            Ok(())
+        })
+    }
+
+    /// Returns the current epoch of this engine.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if epoch interruption is not enabled with
+    /// [`Config::epoch_interruption`].
+    pub fn current_epoch(&self) -> anyhow::Result<u64> {
+        Python::with_gil(|py| match self.epoch_global(py)?.get_value(py)? {
+            #[allow(clippy::cast_sign_loss)]
+            Value::I64(epoch) => Ok(epoch as u64),
+            _ => anyhow::bail!("epoch global has an invalid value"),
+        })
+    }
+
+    /// Returns the epoch counter global that is imported by instrumented
+    /// modules, which is created on first use
+    pub(crate) fn epoch_global(&self, py: Python) -> anyhow::Result<&Global> {
+        if !self.config.epoch_interruption_enabled() {
+            anyhow::bail!("epoch interruption is not enabled in the engine's config");
+        }
+
+        let epoch = self
+            .epoch
+            .get_or_try_init(py, || Global::new_with_value(py, &Value::I64(0), true))?;
+
+        Ok(epoch)
+    }
+}
+
+impl fmt::Debug for Engine {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Engine")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
 }
 
 impl WasmEngine for Engine {
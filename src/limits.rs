@@ -0,0 +1,323 @@
+use std::{error::Error, fmt};
+
+/// The default maximum number of instances that can be created in a [`Store`]
+///
+/// [`Store`]: crate::Store
+pub const DEFAULT_INSTANCE_LIMIT: usize = 10_000;
+
+/// The default maximum number of memories that can be created in a [`Store`]
+///
+/// [`Store`]: crate::Store
+pub const DEFAULT_MEMORY_LIMIT: usize = 10_000;
+
+/// The default maximum number of tables that can be created in a [`Store`]
+///
+/// [`Store`]: crate::Store
+pub const DEFAULT_TABLE_LIMIT: usize = 10_000;
+
+/// Used by hosts to limit the resources that are allocated in a [`Store`].
+///
+/// A resource limiter is installed with [`Store::set_limiter`] or
+/// [`StoreContextMut::set_limiter`]. It is consulted before memories and
+/// tables are created or grown by the host, and before a [`Module`] is
+/// instantiated, in which case the memories and tables that are defined by
+/// the module are checked as well.
+///
+/// The [`WebAssembly`] JavaScript API does not allow intercepting
+/// guest-initiated `memory.grow` and `table.grow` instructions. Instead, the
+/// limiter can lower the maximum size of memories and tables that are created
+/// by the host using [`ResourceLimiter::memory_maximum`] and
+/// [`ResourceLimiter::table_maximum`], which the web browser then enforces for
+/// guest-initiated growth as well. Memories and tables that are defined inside
+/// a [`Module`] are only checked for their initial size.
+///
+/// [`Store`]: crate::Store
+/// [`Store::set_limiter`]: crate::Store::set_limiter
+/// [`StoreContextMut::set_limiter`]: crate::StoreContextMut::set_limiter
+/// [`Module`]: crate::Module
+/// [`WebAssembly`]: https://developer.mozilla.org/en-US/docs/WebAssembly
+pub trait ResourceLimiter: Send + Sync {
+    /// Notifies the limiter that a memory is about to grow from `current` to
+    /// `desired` pages, where `maximum` is the maximum number of pages in the
+    /// memory's type, if any.
+    ///
+    /// A newly created memory is reported as growing from zero pages.
+    ///
+    /// Returning `Ok(false)` rejects the growth, which is then reported as a
+    /// [`ResourceLimitError`]. Returning an error aborts the operation with
+    /// that error instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operation should be aborted with a custom
+    /// error.
+    fn memory_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<bool>;
+
+    /// Notifies the limiter that a table is about to grow from `current` to
+    /// `desired` elements, where `maximum` is the maximum number of elements
+    /// in the table's type, if any.
+    ///
+    /// A newly created table is reported as growing from zero elements.
+    ///
+    /// Returning `Ok(false)` rejects the growth, which is then reported as a
+    /// [`ResourceLimitError`]. Returning an error aborts the operation with
+    /// that error instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the operation should be aborted with a custom
+    /// error.
+    fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<bool>;
+
+    /// Returns the maximum number of pages that a memory, which is created by
+    /// the host and whose type has the given `maximum`, may be grown to.
+    ///
+    /// This limit is enforced by the web browser for both host- and
+    /// guest-initiated growth. By default, the `maximum` is not changed.
+    fn memory_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        maximum
+    }
+
+    /// Returns the maximum number of elements that a table, which is created
+    /// by the host and whose type has the given `maximum`, may be grown to.
+    ///
+    /// This limit is enforced by the web browser for both host- and
+    /// guest-initiated growth. By default, the `maximum` is not changed.
+    fn table_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        maximum
+    }
+
+    /// The maximum number of instances that can be created in the store.
+    fn instances(&self) -> usize {
+        DEFAULT_INSTANCE_LIMIT
+    }
+
+    /// The maximum number of memories that can be created in the store,
+    /// including those defined by instantiated modules.
+    fn memories(&self) -> usize {
+        DEFAULT_MEMORY_LIMIT
+    }
+
+    /// The maximum number of tables that can be created in the store,
+    /// including those defined by instantiated modules.
+    fn tables(&self) -> usize {
+        DEFAULT_TABLE_LIMIT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A [`ResourceLimiter`] with simple static limits.
+pub struct StoreLimits {
+    /// The maximum number of pages that any memory may have
+    pub memory_pages: Option<u32>,
+    /// The maximum number of elements that any table may have
+    pub table_elements: Option<u32>,
+    /// The maximum number of instances that can be created
+    pub instances: usize,
+    /// The maximum number of memories that can be created
+    pub memories: usize,
+    /// The maximum number of tables that can be created
+    pub tables: usize,
+}
+
+impl Default for StoreLimits {
+    fn default() -> Self {
+        Self {
+            memory_pages: None,
+            table_elements: None,
+            instances: DEFAULT_INSTANCE_LIMIT,
+            memories: DEFAULT_MEMORY_LIMIT,
+            tables: DEFAULT_TABLE_LIMIT,
+        }
+    }
+}
+
+impl ResourceLimiter for StoreLimits {
+    fn memory_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(self.memory_pages.map_or(true, |limit| desired <= limit))
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        Ok(self.table_elements.map_or(true, |limit| desired <= limit))
+    }
+
+    fn memory_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        min_limit(maximum, self.memory_pages)
+    }
+
+    fn table_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        min_limit(maximum, self.table_elements)
+    }
+
+    fn instances(&self) -> usize {
+        self.instances
+    }
+
+    fn memories(&self) -> usize {
+        self.memories
+    }
+
+    fn tables(&self) -> usize {
+        self.tables
+    }
+}
+
+fn min_limit(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error that is returned when a [`ResourceLimiter`] rejects an allocation.
+pub enum ResourceLimitError {
+    /// Growing a memory from `current` to `desired` pages was rejected
+    MemoryGrowth {
+        /// The current number of pages
+        current: u32,
+        /// The desired number of pages
+        desired: u32,
+    },
+    /// Growing a table from `current` to `desired` elements was rejected
+    TableGrowth {
+        /// The current number of elements
+        current: u32,
+        /// The desired number of elements
+        desired: u32,
+    },
+    /// Creating another instance would exceed the `limit`
+    Instances {
+        /// The maximum number of instances
+        limit: usize,
+    },
+    /// Creating another memory would exceed the `limit`
+    Memories {
+        /// The maximum number of memories
+        limit: usize,
+    },
+    /// Creating another table would exceed the `limit`
+    Tables {
+        /// The maximum number of tables
+        limit: usize,
+    },
+}
+
+impl fmt::Display for ResourceLimitError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MemoryGrowth { current, desired } => write!(
+                fmt,
+                "the resource limiter rejected growing a memory from {current} to {desired} pages"
+            ),
+            Self::TableGrowth { current, desired } => write!(
+                fmt,
+                "the resource limiter rejected growing a table from {current} to {desired} \
+                 elements"
+            ),
+            Self::Instances { limit } => {
+                write!(fmt, "the store's limit of {limit} instances was exceeded")
+            },
+            Self::Memories { limit } => {
+                write!(fmt, "the store's limit of {limit} memories was exceeded")
+            },
+            Self::Tables { limit } => {
+                write!(fmt, "the store's limit of {limit} tables was exceeded")
+            },
+        }
+    }
+}
+
+impl Error for ResourceLimitError {}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::{AsContextMut, Imports, Instance, Memory, MemoryType, Module};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn store_limits() {
+        let mut store = test_utils::store();
+        store.as_context_mut().inner.set_limiter(StoreLimits {
+            memory_pages: Some(2),
+            instances: 1,
+            memories: 2,
+            ..StoreLimits::default()
+        });
+
+        let trapping =
+            wat::parse_str("(module (memory 1) (func $start unreachable) (start $start))").unwrap();
+        let trapping = Module::new(store.engine(), trapping.as_slice()).unwrap();
+        let large = Module::new(
+            store.engine(),
+            wat::parse_str("(module (memory 3))").unwrap().as_slice(),
+        )
+        .unwrap();
+        let small = Module::new(
+            store.engine(),
+            wat::parse_str("(module (memory 1))").unwrap().as_slice(),
+        )
+        .unwrap();
+
+        // failed instantiations do not count towards the limits
+        for _ in 0..3 {
+            let err = Instance::new(&mut store, &trapping, &Imports::new()).unwrap_err();
+            assert!(
+                err.downcast_ref::<ResourceLimitError>().is_none(),
+                "{err:?}"
+            );
+        }
+
+        let err = Instance::new(&mut store, &large, &Imports::new()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ResourceLimitError>(),
+            Some(&ResourceLimitError::MemoryGrowth {
+                current: 0,
+                desired: 3
+            })
+        );
+
+        Instance::new(&mut store, &small, &Imports::new()).unwrap();
+
+        let err = Instance::new(&mut store, &small, &Imports::new()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ResourceLimitError>(),
+            Some(&ResourceLimitError::Instances { limit: 1 })
+        );
+
+        // host-created memories have their maximum lowered to the limit
+        let memory = Memory::new(&mut store, MemoryType::new(1, None)).unwrap();
+        assert_eq!(memory.ty(&store), MemoryType::new(1, Some(2)));
+        assert_eq!(memory.grow(&mut store, 1).unwrap(), 1);
+        assert!(memory.grow(&mut store, 1).is_err());
+
+        let err = Memory::new(&mut store, MemoryType::new(1, None)).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ResourceLimitError>(),
+            Some(&ResourceLimitError::Memories { limit: 2 })
+        );
+    }
+}
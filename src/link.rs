@@ -0,0 +1,361 @@
+use std::{error::Error, fmt};
+
+use fxhash::FxHashMap;
+use wasm_runtime_layer::ExternType;
+
+#[derive(Debug, Clone)]
+/// Error that lists every import of a [`Module`] which cannot be satisfied by
+/// the provided imports, see [`Module::check_imports`].
+///
+/// [`Module`]: crate::Module
+/// [`Module::check_imports`]: crate::Module::check_imports
+pub struct LinkError {
+    /// The imports that the module requires but that were not provided
+    missing: Vec<MissingImport>,
+    /// The provided imports whose types do not match the module's imports
+    mismatched: Vec<MismatchedImport>,
+    /// The provided imports that the module does not require
+    extraneous: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+/// An import that a module requires but that was not provided.
+pub struct MissingImport {
+    /// The module name of the import
+    module: String,
+    /// The name of the import
+    name: String,
+    /// The type of the import that the module requires
+    ty: ExternType,
+}
+
+#[derive(Debug, Clone)]
+/// A provided import whose type does not match the module's import.
+pub struct MismatchedImport {
+    /// The module name of the import
+    module: String,
+    /// The name of the import
+    name: String,
+    /// The type of the import that the module requires
+    expected: ExternType,
+    /// The type of the provided import
+    actual: ExternType,
+}
+
+impl LinkError {
+    /// Checks the `provided` imports against the `required` imports of a
+    /// module
+    ///
+    /// A provided import satisfies a required import if it is of the same
+    /// kind and its type matches, where memories and tables may be larger
+    /// than required and have a stricter maximum size. Extraneous imports
+    /// alone do not cause an error, since they are ignored during
+    /// instantiation.
+    pub(crate) fn check<'a>(
+        required: impl IntoIterator<Item = (&'a str, &'a str, &'a ExternType)>,
+        provided: impl IntoIterator<Item = (&'a str, &'a str, ExternType)>,
+    ) -> Result<(), Self> {
+        let mut provided = provided
+            .into_iter()
+            .map(|(module, name, ty)| ((module, name), (ty, false)))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut missing = Vec::new();
+        let mut mismatched = Vec::new();
+
+        for (module, name, expected) in required {
+            match provided.get_mut(&(module, name)) {
+                None => missing.push(MissingImport {
+                    module: String::from(module),
+                    name: String::from(name),
+                    ty: expected.clone(),
+                }),
+                Some((actual, used)) => {
+                    *used = true;
+
+                    if !is_compatible(actual, expected) {
+                        mismatched.push(MismatchedImport {
+                            module: String::from(module),
+                            name: String::from(name),
+                            expected: expected.clone(),
+                            actual: actual.clone(),
+                        });
+                    }
+                },
+            }
+        }
+
+        if missing.is_empty() && mismatched.is_empty() {
+            return Ok(());
+        }
+
+        let mut extraneous = provided
+            .into_iter()
+            .filter(|(_, (_, used))| !used)
+            .map(|((module, name), _)| (String::from(module), String::from(name)))
+            .collect::<Vec<_>>();
+        extraneous.sort_unstable();
+
+        Err(Self {
+            missing,
+            mismatched,
+            extraneous,
+        })
+    }
+
+    #[must_use]
+    /// Returns the imports that the module requires but that were not
+    /// provided, in the module's declaration order.
+    pub fn missing(&self) -> &[MissingImport] {
+        &self.missing
+    }
+
+    #[must_use]
+    /// Returns the provided imports whose types do not match the module's
+    /// imports, in the module's declaration order.
+    pub fn mismatched(&self) -> &[MismatchedImport] {
+        &self.mismatched
+    }
+
+    #[must_use]
+    /// Returns the module and import names of the provided imports that the
+    /// module does not require, sorted by name.
+    ///
+    /// Extraneous imports are only reported alongside missing or mismatched
+    /// imports, where they often point to a misspelt import name.
+    pub fn extraneous(&self) -> &[(String, String)] {
+        &self.extraneous
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("the provided imports do not satisfy the module's imports:")?;
+
+        for import in &self.missing {
+            write!(fmt, "\n  {import}")?;
+        }
+
+        for import in &self.mismatched {
+            write!(fmt, "\n  {import}")?;
+        }
+
+        for (module, name) in &self.extraneous {
+            write!(fmt, "\n  extraneous import {module:?}.{name:?}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for LinkError {}
+
+impl MissingImport {
+    #[must_use]
+    /// Returns the module name of the import.
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    #[must_use]
+    /// Returns the name of the import.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    /// Returns the type of the import that the module requires.
+    pub const fn ty(&self) -> &ExternType {
+        &self.ty
+    }
+}
+
+impl fmt::Display for MissingImport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "missing import {:?}.{:?}: {}",
+            self.module,
+            self.name,
+            DisplayExternType(&self.ty)
+        )
+    }
+}
+
+impl MismatchedImport {
+    #[must_use]
+    /// Returns the module name of the import.
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    #[must_use]
+    /// Returns the name of the import.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    /// Returns the type of the import that the module requires.
+    pub const fn expected(&self) -> &ExternType {
+        &self.expected
+    }
+
+    #[must_use]
+    /// Returns the type of the provided import.
+    pub const fn actual(&self) -> &ExternType {
+        &self.actual
+    }
+}
+
+impl fmt::Display for MismatchedImport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "mismatched import {:?}.{:?}: expected {}, found {}",
+            self.module,
+            self.name,
+            DisplayExternType(&self.expected),
+            DisplayExternType(&self.actual),
+        )
+    }
+}
+
+/// Checks whether an `actual` import can be used for an `expected` import
+pub fn is_compatible(actual: &ExternType, expected: &ExternType) -> bool {
+    match (actual, expected) {
+        (ExternType::Func(actual), ExternType::Func(expected)) => actual == expected,
+        (ExternType::Global(actual), ExternType::Global(expected)) => actual == expected,
+        (ExternType::Memory(actual), ExternType::Memory(expected)) => {
+            actual.initial_pages() >= expected.initial_pages()
+                && is_within_maximum(actual.maximum_pages(), expected.maximum_pages())
+        },
+        (ExternType::Table(actual), ExternType::Table(expected)) => {
+            actual.element() == expected.element()
+                && actual.minimum() >= expected.minimum()
+                && is_within_maximum(actual.maximum(), expected.maximum())
+        },
+        _ => false,
+    }
+}
+
+/// Checks whether an `actual` maximum size is at most the `expected` one
+const fn is_within_maximum(actual: Option<u32>, expected: Option<u32>) -> bool {
+    match (actual, expected) {
+        (_, None) => true,
+        (Some(actual), Some(expected)) => actual <= expected,
+        (None, Some(_)) => false,
+    }
+}
+
+/// Formats an [`ExternType`] in the text format style
+pub struct DisplayExternType<'a>(pub &'a ExternType);
+
+impl fmt::Display for DisplayExternType<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        /// Formats the `min` and optional `max` limits
+        fn limits(fmt: &mut fmt::Formatter, min: u32, max: Option<u32>) -> fmt::Result {
+            match max {
+                Some(max) => write!(fmt, "{min} {max}"),
+                None => write!(fmt, "{min}"),
+            }
+        }
+
+        match self.0 {
+            ExternType::Func(ty) => write!(fmt, "{ty}"),
+            ExternType::Global(ty) if ty.mutable() => write!(fmt, "global (mut {})", ty.content()),
+            ExternType::Global(ty) => write!(fmt, "global {}", ty.content()),
+            ExternType::Memory(ty) => {
+                fmt.write_str("memory ")?;
+                limits(fmt, ty.initial_pages(), ty.maximum_pages())
+            },
+            ExternType::Table(ty) => {
+                fmt.write_str("table ")?;
+                limits(fmt, ty.minimum(), ty.maximum())?;
+                write!(fmt, " {}", ty.element())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::{FuncType, GlobalType, MemoryType, TableType, ValueType};
+
+    use super::*;
+
+    #[test]
+    fn import_diagnostics() {
+        let required = [
+            (
+                "env",
+                "log",
+                ExternType::Func(FuncType::new([ValueType::I32], [])),
+            ),
+            (
+                "env",
+                "memory",
+                ExternType::Memory(MemoryType::new(1, Some(16))),
+            ),
+            (
+                "env",
+                "table",
+                ExternType::Table(TableType::new(ValueType::FuncRef, 2, None)),
+            ),
+            (
+                "env",
+                "counter",
+                ExternType::Global(GlobalType::new(ValueType::I64, true)),
+            ),
+        ];
+
+        let provided = [
+            (
+                "env",
+                "lgo",
+                ExternType::Func(FuncType::new([ValueType::I32], [])),
+            ),
+            // a memory with a smaller maximum is compatible
+            (
+                "env",
+                "memory",
+                ExternType::Memory(MemoryType::new(2, Some(8))),
+            ),
+            (
+                "env",
+                "table",
+                ExternType::Table(TableType::new(ValueType::FuncRef, 1, None)),
+            ),
+            (
+                "env",
+                "counter",
+                ExternType::Global(GlobalType::new(ValueType::I64, false)),
+            ),
+        ];
+
+        let err = LinkError::check(
+            required
+                .iter()
+                .map(|(module, name, ty)| (*module, *name, ty)),
+            provided.iter().cloned(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "the provided imports do not satisfy the module's imports:
+  missing import \"env\".\"log\": func(i32)
+  mismatched import \"env\".\"table\": expected table 2 funcref, found table 1 funcref
+  mismatched import \"env\".\"counter\": expected global (mut i64), found global i64
+  extraneous import \"env\".\"lgo\""
+        );
+
+        assert!(LinkError::check(
+            required
+                .iter()
+                .map(|(module, name, ty)| (*module, *name, ty)),
+            required.iter().cloned(),
+        )
+        .is_ok());
+    }
+}
@@ -1,14 +1,20 @@
-use pyo3::{intern, prelude::*, sync::GILOnceCell, types::PyBytes};
+use pyo3::{intern, prelude::*, types::PyBytes};
 use wasm_runtime_layer::{
     backend::{AsContext, AsContextMut, WasmMemory},
     MemoryType,
 };
 
 use crate::{
-    conversion::{create_js_object, instanceof, js_uint8_array_new, ToPy},
+    conversion::{create_js_object, instanceof, ToPy},
+    js::{uint8_array_new, web_assembly_memory, web_assembly_memory_new},
+    reflection::{memory_type_from_js, reflect_type},
+    store::StoreContextMut,
     Engine,
 };
 
+/// The size of a WASM memory page in bytes
+const PAGE_SIZE: u64 = 1 << 16;
+
 #[derive(Debug)]
 /// A WASM memory.
 ///
@@ -32,11 +38,20 @@ impl Clone for Memory {
 }
 
 impl WasmMemory<Engine> for Memory {
-    fn new(_ctx: impl AsContextMut<Engine>, ty: MemoryType) -> anyhow::Result<Self> {
+    fn new(mut ctx: impl AsContextMut<Engine>, ty: MemoryType) -> anyhow::Result<Self> {
         Python::with_gil(|py| {
             #[cfg(feature = "tracing")]
             tracing::debug!(?ty, "Memory::new");
 
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+
+            store.limit_new_resources(0, 1, 0)?;
+            store.limit_memory_growth(0, ty.initial_pages(), ty.maximum_pages())?;
+            let ty = MemoryType::new(
+                ty.initial_pages(),
+                store.limit_memory_maximum(ty.maximum_pages()),
+            );
+
             let desc = create_js_object(py)?;
             desc.setattr(intern!(py, "initial"), ty.initial_pages())?;
             if let Some(maximum) = ty.maximum_pages() {
@@ -44,6 +59,7 @@ impl WasmMemory<Engine> for Memory {
             }
 
             let memory = web_assembly_memory_new(py)?.call1((desc,))?;
+            store.count_new_resources(0, 1, 0);
 
             Ok(Self {
                 memory: memory.unbind(),
@@ -56,13 +72,22 @@ impl WasmMemory<Engine> for Memory {
         self.ty
     }
 
-    fn grow(&self, _ctx: impl AsContextMut<Engine>, additional: u32) -> anyhow::Result<u32> {
+    fn grow(&self, mut ctx: impl AsContextMut<Engine>, additional: u32) -> anyhow::Result<u32> {
+        let current = self.current_pages(ctx.as_context());
+
         Python::with_gil(|py| {
             let memory = self.memory.bind(py);
 
             #[cfg(feature = "tracing")]
             tracing::debug!(memory = %memory, ?self.ty, additional, "Memory::grow");
 
+            let Some(desired) = current.checked_add(additional) else {
+                anyhow::bail!("cannot grow a memory of {current} pages by {additional} pages");
+            };
+
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+            store.limit_memory_growth(current, desired, self.ty.maximum_pages())?;
+
             let old_pages = memory
                 .call_method1(intern!(py, "grow"), (additional,))?
                 .extract()?;
@@ -72,8 +97,6 @@ impl WasmMemory<Engine> for Memory {
     }
 
     fn current_pages(&self, _ctx: impl AsContext<Engine>) -> u32 {
-        const PAGE_SIZE: u64 = 1 << 16;
-
         Python::with_gil(|py| -> Result<u32, PyErr> {
             let memory = self.memory.bind(py);
 
@@ -104,7 +127,7 @@ impl WasmMemory<Engine> for Memory {
             tracing::debug!(memory = %memory, ?self.ty, offset, len = buffer.len(), "Memory::read");
 
             let memory = memory.getattr(intern!(py, "buffer"))?;
-            let memory = js_uint8_array_new(py)?.call1((memory, offset, buffer.len()))?;
+            let memory = uint8_array_new(py)?.call1((memory, offset, buffer.len()))?;
 
             let bytes: Bound<PyBytes> = memory.call_method0(intern!(py, "to_bytes"))?.extract()?;
             buffer.copy_from_slice(bytes.as_bytes());
@@ -126,7 +149,7 @@ impl WasmMemory<Engine> for Memory {
             tracing::debug!(memory = %memory, ?self.ty, offset, len = buffer.len(), "Memory::write");
 
             let memory = memory.getattr(intern!(py, "buffer"))?;
-            let memory = js_uint8_array_new(py)?.call1((memory, offset, buffer.len()))?;
+            let memory = uint8_array_new(py)?.call1((memory, offset, buffer.len()))?;
 
             memory.call_method1(intern!(py, "assign"), (buffer,))?;
 
@@ -145,7 +168,53 @@ impl ToPy for Memory {
 }
 
 impl Memory {
-    /// Construct a memory from an exported memory object
+    /// Wraps an existing JavaScript [`WebAssembly.Memory`] with the given
+    /// type `ty`, or with the type that is inferred using JS Type Reflection
+    /// if `ty` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `memory` is not a [`WebAssembly.Memory`], if it is
+    /// smaller than the initial size of `ty`, if its maximum size exceeds that
+    /// of `ty` where JS Type Reflection is supported, or if `ty` is `None` and
+    /// the type cannot be inferred since the web browser does not support JS
+    /// Type Reflection.
+    ///
+    /// [`WebAssembly.Memory`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Memory
+    pub fn from_js(memory: &Bound<PyAny>, ty: Option<MemoryType>) -> anyhow::Result<Self> {
+        let py = memory.py();
+
+        if !instanceof(memory, web_assembly_memory(py)?)? {
+            anyhow::bail!("expected WebAssembly.Memory but found {memory}");
+        }
+
+        let ty = reflect_type(memory, ty, memory_type_from_js)?;
+
+        let byte_length: u64 = memory
+            .getattr(intern!(py, "buffer"))?
+            .getattr(intern!(py, "byteLength"))?
+            .extract()?;
+        let pages = byte_length / PAGE_SIZE;
+        if pages < u64::from(ty.initial_pages()) {
+            anyhow::bail!(
+                "{memory} has {pages} pages but at least {} are required",
+                ty.initial_pages()
+            );
+        }
+
+        Self::from_exported_memory(memory.clone(), ty)
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript [`WebAssembly.Memory`].
+    ///
+    /// [`WebAssembly.Memory`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Memory
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.memory.bind(py)
+    }
+
+    /// Construct a memory of the trusted type `ty` from an exported memory
+    /// object
     pub(crate) fn from_exported_memory(
         memory: Bound<PyAny>,
         ty: MemoryType,
@@ -164,12 +233,29 @@ impl Memory {
     }
 }
 
-fn web_assembly_memory(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_MEMORY: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_MEMORY.import(py, "js.WebAssembly", "Memory")
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
 
-fn web_assembly_memory_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_MEMORY_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_MEMORY_NEW.import(py, "js.WebAssembly.Memory", "new")
+    #[test]
+    fn read_write_grow() {
+        let mut store = test_utils::store();
+
+        let memory =
+            wasm_runtime_layer::Memory::new(&mut store, MemoryType::new(1, Some(2))).unwrap();
+        assert_eq!(memory.current_pages(&store), 1);
+
+        memory.write(&mut store, 0xFFFC, b"wasm").unwrap();
+        assert!(memory.write(&mut store, 0xFFFD, b"wasm").is_err());
+
+        assert_eq!(memory.grow(&mut store, 1).unwrap(), 1);
+        assert!(memory.grow(&mut store, 1).is_err());
+        assert_eq!(memory.current_pages(&store), 2);
+
+        let mut buffer = [0; 6];
+        memory.read(&store, 0xFFFB, &mut buffer).unwrap();
+        assert_eq!(&buffer, b"\0wasm\0");
+        assert!(memory.read(&store, 0x1FFFF, &mut buffer).is_err());
+    }
 }
@@ -2,17 +2,31 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use fxhash::FxHashMap;
-use pyo3::{prelude::*, sync::GILOnceCell};
+use pyo3::{intern, prelude::*};
 use wasm_runtime_layer::{
-    backend::WasmModule, ExportType, ExternType, FuncType, GlobalType, ImportType, MemoryType,
-    TableType, ValueType,
+    backend::{AsContext, Extern, Imports, WasmMemory, WasmModule, WasmTable},
+    ExportType, ExternType, FuncType, GlobalType, ImportType, MemoryType, TableType, ValueType,
 };
 
 use crate::{
-    conversion::js_uint8_array_new, features::UnsupportedWasmFeatureExtensionError, Engine,
+    backtrace::WasmBacktrace,
+    conversion::{
+        instanceof, js_uint8_array_from_slice, js_uint8_array_to_vec, js_uint8_array_view,
+    },
+    debug::{DebugInfo, SourceLocation},
+    features::UnsupportedWasmFeatureExtensionError,
+    instrument::{Instrumentation, OffsetMap},
+    js::{
+        web_assembly_module, web_assembly_module_exports, web_assembly_module_imports,
+        web_assembly_module_new,
+    },
+    link::LinkError,
+    reflection::{extern_kind, extern_type_from_js},
+    Engine,
 };
 
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 /// A WASM module.
 ///
 /// This type wraps a [`WebAssembly.Module`] from the JavaScript API.
@@ -23,6 +37,8 @@ pub struct Module {
     module: Py<PyAny>,
     /// The parsed module, containing import and export signatures
     parsed: Arc<ParsedModule>,
+    /// The instrumentation that has been applied to the module
+    instrumentation: Instrumentation,
 }
 
 impl Clone for Module {
@@ -30,45 +46,22 @@ impl Clone for Module {
         Python::with_gil(|py| Self {
             module: self.module.clone_ref(py),
             parsed: self.parsed.clone(),
+            instrumentation: self.instrumentation,
         })
     }
 }
 
 impl WasmModule<Engine> for Module {
-    fn new(_engine: &Engine, mut stream: impl std::io::Read) -> anyhow::Result<Self> {
-        Python::with_gil(|py| {
-            #[cfg(feature = "tracing")]
-            let _span = tracing::debug_span!("Module::new").entered();
-
-            let mut bytes = Vec::new();
-            stream
-                .read_to_end(&mut bytes)
-                .context("Failed to read module bytes")?;
-
-            let parsed = ParsedModule::parse(&bytes)?;
-
-            let buffer = js_uint8_array_new(py)?.call1((bytes.as_slice(),))?;
-
-            let module = match web_assembly_module_new(py)?.call1((buffer,)) {
-                Ok(module) => module,
-                // check if the error comes from missing feature support
-                // - if so, report the more informative unsupported feature error instead
-                // - if not, bubble up the error that made module instantiation fail
-                Err(err) => match Python::with_gil(|py| {
-                    UnsupportedWasmFeatureExtensionError::check_support(py, &bytes)
-                })? {
-                    Ok(()) => anyhow::bail!(err),
-                    Err(unsupported) => anyhow::bail!(unsupported),
-                },
-            };
+    fn new(engine: &Engine, mut stream: impl std::io::Read) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Module::new").entered();
 
-            let parsed = Arc::new(parsed);
+        let mut bytes = Vec::new();
+        stream
+            .read_to_end(&mut bytes)
+            .context("Failed to read module bytes")?;
 
-            Ok(Self {
-                module: module.unbind(),
-                parsed,
-            })
-        })
+        Python::with_gil(|py| Self::compile(py, engine, &bytes, None))
     }
 
     fn exports(&self) -> Box<dyn '_ + Iterator<Item = ExportType<'_>>> {
@@ -79,7 +72,9 @@ impl WasmModule<Engine> for Module {
     }
 
     fn get_export(&self, name: &str) -> Option<ExternType> {
-        self.parsed.exports.get(name).cloned()
+        self.parsed
+            .export_index(name)
+            .map(|index| self.parsed.exports[index].1.clone())
     }
 
     fn imports(&self) -> Box<dyn '_ + Iterator<Item = ImportType<'_>>> {
@@ -97,18 +92,491 @@ impl WasmModule<Engine> for Module {
 }
 
 impl Module {
+    /// Creates a new module from the borrowed WASM `bytes`.
+    ///
+    /// Unlike [`Module::new`], which first reads its stream into a buffer,
+    /// the module is parsed directly from `bytes`, which are then copied
+    /// only once, into the JavaScript `Uint8Array` that is compiled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module is invalid, uses unsupported features,
+    /// or fails to compile.
+    ///
+    /// [`Module::new`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Module.html#method.new
+    pub fn from_bytes(engine: &Engine, bytes: &[u8]) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Module::from_bytes").entered();
+
+        Python::with_gil(|py| Self::compile(py, engine, bytes, None))
+    }
+
+    /// Creates a new module from the WASM bytes in the JavaScript `buffer`,
+    /// which must be an `ArrayBuffer` or an `ArrayBuffer` view, e.g. a
+    /// `Uint8Array`.
+    ///
+    /// The bytes are copied once, directly into Rust, to parse the module.
+    /// Unless the module needs to be instrumented, the JavaScript `buffer`
+    /// itself is then compiled without any further copies.
+    ///
+    /// A `Response` from `fetch` can be used by first awaiting its
+    /// `arrayBuffer()` in JavaScript, since modules are compiled
+    /// synchronously.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `buffer` is not an `ArrayBuffer` or an
+    /// `ArrayBuffer` view, if the module is invalid, uses unsupported
+    /// features, or fails to compile.
+    pub fn from_js_buffer(engine: &Engine, buffer: &Bound<PyAny>) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Module::from_js_buffer", %buffer).entered();
+
+        let py = buffer.py();
+
+        let array = js_uint8_array_view(buffer)?;
+        let bytes = js_uint8_array_to_vec(&array)?;
+
+        Self::compile(py, engine, &bytes, Some(array))
+    }
+
+    /// Wraps an existing JavaScript [`WebAssembly.Module`], e.g. one that was
+    /// loaded by a bundler or received from a worker.
+    ///
+    /// If the module's original `bytes` are given, they are parsed to recover
+    /// the full type information of the module. Otherwise, the types of the
+    /// module's imports and exports are recovered from the type descriptors of
+    /// the [JS Type Reflection] proposal, which not all web browsers support.
+    /// Without the bytes, the module's index spaces only contain its imports,
+    /// and it has no start function, segments, custom sections, or names.
+    /// Its instances also cannot be snapshotted, since the module cannot be
+    /// told apart from other modules with the same imports and exports.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if
+    /// - `module` is not a [`WebAssembly.Module`]
+    /// - the engine requires modules to be instrumented, see
+    ///   [`Config::consume_fuel`] and [`Config::epoch_interruption`], which is
+    ///   impossible for an already compiled module
+    /// - the `bytes` are invalid or their imports and exports do not match
+    ///   those of the `module`
+    /// - the types cannot be recovered without the `bytes`
+    ///
+    /// [`WebAssembly.Module`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Module
+    /// [JS Type Reflection]: https://github.com/WebAssembly/js-types
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    /// [`Config::epoch_interruption`]: crate::Config::epoch_interruption
+    pub fn from_js_module(
+        engine: &Engine,
+        module: &Bound<PyAny>,
+        bytes: Option<&[u8]>,
+    ) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Module::from_js_module", %module).entered();
+
+        let py = module.py();
+
+        if !instanceof(module, web_assembly_module(py)?)? {
+            anyhow::bail!("expected a WebAssembly.Module");
+        }
+
+        let instrumentation = Instrumentation::from_config(engine.config());
+        if instrumentation.is_enabled() {
+            anyhow::bail!(
+                "a compiled WebAssembly.Module cannot be instrumented for fuel consumption or \
+                 epoch interruption"
+            );
+        }
+
+        let imports = web_assembly_module_imports(py)?
+            .call1((module,))?
+            .try_iter()?
+            .map(|import| {
+                let import = import?;
+                Ok((
+                    (
+                        import.getattr(intern!(py, "module"))?.extract()?,
+                        import.getattr(intern!(py, "name"))?.extract()?,
+                    ),
+                    descriptor_type(&import)?,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let exports = web_assembly_module_exports(py)?
+            .call1((module,))?
+            .try_iter()?
+            .map(|export| {
+                let export = export?;
+                Ok((
+                    export.getattr(intern!(py, "name"))?.extract()?,
+                    descriptor_type(&export)?,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let parsed = match bytes {
+            Some(bytes) => {
+                wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+                    .validate_all(bytes)
+                    .context("the bytes are not a valid WebAssembly module")?;
+
+                let mut parsed = ParsedModule::parse(bytes)?;
+                parsed.check_descriptors(&imports, &exports)?;
+
+                if engine.config().debug_info_enabled() {
+                    parsed.load_dwarf();
+                }
+
+                parsed
+            },
+            None => ParsedModule::from_descriptors(imports, exports)?,
+        };
+
+        Ok(Self {
+            module: module.clone().unbind(),
+            parsed: Arc::new(parsed),
+            instrumentation,
+        })
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript [`WebAssembly.Module`].
+    ///
+    /// [`WebAssembly.Module`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Module
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.module.bind(py)
+    }
+
+    /// Parses, optionally instruments, and compiles the module `bytes`,
+    /// which are already contained in the JavaScript `Uint8Array` `array`,
+    /// if given
+    fn compile(
+        py: Python,
+        engine: &Engine,
+        bytes: &[u8],
+        array: Option<Bound<PyAny>>,
+    ) -> anyhow::Result<Self> {
+        let mut parsed = ParsedModule::parse(bytes)?;
+
+        if engine.config().debug_info_enabled() {
+            parsed.load_dwarf();
+        }
+
+        let instrumentation = Instrumentation::from_config(engine.config());
+        let buffer = if instrumentation.is_enabled() {
+            let (instrumented, offsets) = instrumentation.apply(bytes)?;
+            parsed.offsets = Some(offsets);
+            js_uint8_array_from_slice(py, &instrumented)?
+        } else if let Some(array) = array {
+            array
+        } else {
+            js_uint8_array_from_slice(py, bytes)?
+        };
+
+        let module = match web_assembly_module_new(py)?.call1((buffer,)) {
+            Ok(module) => module,
+            // check if the error comes from missing feature support
+            // - if so, report the more informative unsupported feature error instead
+            // - if not, bubble up the error that made module instantiation fail
+            Err(err) => match UnsupportedWasmFeatureExtensionError::check_support(py, bytes)? {
+                Ok(()) => anyhow::bail!(err),
+                Err(unsupported) => anyhow::bail!(unsupported),
+            },
+        };
+
+        Ok(Self {
+            module: module.unbind(),
+            parsed: Arc::new(parsed),
+            instrumentation,
+        })
+    }
+
     pub(crate) fn module(&self, py: Python) -> Py<PyAny> {
         self.module.clone_ref(py)
     }
+
+    pub(crate) fn parsed(&self) -> &ParsedModule {
+        &self.parsed
+    }
+
+    pub(crate) fn shared_parsed(&self) -> Arc<ParsedModule> {
+        self.parsed.clone()
+    }
+
+    /// Returns a copy of this module that symbolizes its stack traces using
+    /// the `source_map`, instead of any DWARF debug information.
+    ///
+    /// The source map must be in the [source map format] for WebAssembly, in
+    /// which the generated columns are byte offsets in the module. The URL of
+    /// a module's source map can be found with [`Module::source_mapping_url`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `source_map` cannot be parsed.
+    ///
+    /// [source map format]: https://tc39.es/source-map/
+    pub fn with_source_map(&self, source_map: &str) -> anyhow::Result<Self> {
+        let mut parsed = ParsedModule::clone(&self.parsed);
+        parsed.debug_info = Some(DebugInfo::from_source_map(source_map)?);
+
+        Ok(Python::with_gil(|py| Self {
+            module: self.module.clone_ref(py),
+            parsed: Arc::new(parsed),
+            instrumentation: self.instrumentation,
+        }))
+    }
+
+    #[must_use]
+    /// Returns the URL of the module's source map from its
+    /// `sourceMappingURL` custom section, if any.
+    pub fn source_mapping_url(&self) -> Option<&str> {
+        let section = self.custom_sections("sourceMappingURL").next()?;
+        wasmparser::BinaryReader::new(section, 0).read_string().ok()
+    }
+
+    /// Checks that the `imports` satisfy all of the module's imports.
+    ///
+    /// Every import of the module must be provided with a matching type:
+    /// functions must have the same signature, globals the same value type
+    /// and mutability, and memories and tables must currently be at least as
+    /// large as required and have a maximum size that is at most the required
+    /// one.
+    ///
+    /// [`Instance::new`] performs this check before instantiating the module,
+    /// since the web browser's `LinkError`s only describe the first invalid
+    /// import, often vaguely.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LinkError`] that lists every missing and mismatched import,
+    /// together with the extraneous imports that the module does not require.
+    ///
+    /// [`Instance::new`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Instance.html#method.new
+    pub fn check_imports(
+        &self,
+        ctx: impl AsContext<Engine>,
+        imports: &Imports<Engine>,
+    ) -> Result<(), LinkError> {
+        LinkError::check(
+            self.parsed
+                .imports
+                .iter()
+                .map(|((module, name), ty)| (module.as_str(), name.as_str(), ty)),
+            imports.iter().map(move |(module, name, import)| {
+                (module, name, current_extern_type(ctx.as_context(), import))
+            }),
+        )
+    }
+
+    #[must_use]
+    /// Returns the function types in the module's type section.
+    pub fn types(&self) -> &[FuncType] {
+        &self.parsed.types
+    }
+
+    #[must_use]
+    /// Returns the signatures of all functions in the module's function index
+    /// space, starting with the imported functions.
+    pub fn functions(&self) -> &[FuncType] {
+        &self.parsed.functions
+    }
+
+    #[must_use]
+    /// Returns the signature of the function with the given `index` in the
+    /// module's function index space.
+    pub fn function(&self, index: u32) -> Option<&FuncType> {
+        self.parsed.functions.get(index as usize)
+    }
+
+    #[must_use]
+    /// Returns the signatures of the functions that are imported by the
+    /// module.
+    pub fn imported_functions(&self) -> &[FuncType] {
+        &self.parsed.functions[..self.parsed.imported_functions]
+    }
+
+    #[must_use]
+    /// Returns the signatures of the functions that are defined by the
+    /// module.
+    pub fn defined_functions(&self) -> &[FuncType] {
+        &self.parsed.functions[self.parsed.imported_functions..]
+    }
+
+    #[must_use]
+    /// Returns the types of all globals in the module's global index space,
+    /// starting with the imported globals.
+    pub fn globals(&self) -> &[GlobalType] {
+        &self.parsed.globals
+    }
+
+    #[must_use]
+    /// Returns the types of the globals that are imported by the module.
+    pub fn imported_globals(&self) -> &[GlobalType] {
+        &self.parsed.globals[..self.parsed.imported_globals]
+    }
+
+    #[must_use]
+    /// Returns the types of the globals that are defined by the module.
+    pub fn defined_globals(&self) -> &[GlobalType] {
+        &self.parsed.globals[self.parsed.imported_globals..]
+    }
+
+    #[must_use]
+    /// Returns the types of all memories in the module's memory index space,
+    /// starting with the imported memories.
+    pub fn memories(&self) -> &[MemoryType] {
+        &self.parsed.memories
+    }
+
+    #[must_use]
+    /// Returns the types of the memories that are imported by the module.
+    pub fn imported_memories(&self) -> &[MemoryType] {
+        &self.parsed.memories[..self.parsed.imported_memories]
+    }
+
+    #[must_use]
+    /// Returns the types of the memories that are defined by the module.
+    pub fn defined_memories(&self) -> &[MemoryType] {
+        self.parsed.defined_memories()
+    }
+
+    #[must_use]
+    /// Returns the types of all tables in the module's table index space,
+    /// starting with the imported tables.
+    pub fn tables(&self) -> &[TableType] {
+        &self.parsed.tables
+    }
+
+    #[must_use]
+    /// Returns the types of the tables that are imported by the module.
+    pub fn imported_tables(&self) -> &[TableType] {
+        &self.parsed.tables[..self.parsed.imported_tables]
+    }
+
+    #[must_use]
+    /// Returns the types of the tables that are defined by the module.
+    pub fn defined_tables(&self) -> &[TableType] {
+        self.parsed.defined_tables()
+    }
+
+    #[must_use]
+    /// Returns the name of the module from its `name` custom section, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.parsed.module_name.as_deref()
+    }
+
+    #[must_use]
+    /// Returns the name of the function with the given `index` in the
+    /// module's function index space from its `name` custom section, if any.
+    pub fn function_name(&self, index: u32) -> Option<&str> {
+        self.parsed.function_name(index)
+    }
+
+    #[must_use]
+    /// Maps the WebAssembly frames in a JavaScript stack trace, e.g. from
+    /// the `stack` property of a `WebAssembly.RuntimeError`, back to the
+    /// function names of this module.
+    pub fn symbolize(&self, stack: &str) -> WasmBacktrace {
+        WasmBacktrace::from_stack(&self.parsed, stack)
+    }
+
+    #[must_use]
+    /// Returns the index of the module's start function, if any.
+    pub fn start(&self) -> Option<u32> {
+        self.parsed.start
+    }
+
+    #[must_use]
+    /// Returns the number of data segments in the module.
+    pub fn data_segments(&self) -> u32 {
+        self.parsed.data_segments
+    }
+
+    #[must_use]
+    /// Returns the number of element segments in the module.
+    pub fn element_segments(&self) -> u32 {
+        self.parsed.element_segments
+    }
+
+    /// Returns the names of all custom sections in the module, in the order
+    /// in which they appear.
+    pub fn custom_section_names(&self) -> impl Iterator<Item = &str> {
+        self.parsed
+            .custom_sections
+            .iter()
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the contents of all custom sections with the given `name`, in
+    /// the order in which they appear.
+    ///
+    /// This method mirrors [`WebAssembly.Module.customSections`]. Custom
+    /// sections are read from the module's original bytes, i.e. before any
+    /// instrumentation is applied.
+    ///
+    /// [`WebAssembly.Module.customSections`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Module/customSections_static
+    pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a [u8]> {
+        self.parsed
+            .custom_sections
+            .iter()
+            .filter(move |(section, _)| section == name)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    pub(crate) const fn instrumentation(&self) -> Instrumentation {
+        self.instrumentation
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// A parsed core module with imports and exports
-struct ParsedModule {
-    /// Import signatures
-    imports: FxHashMap<(String, String), ExternType>,
-    /// Export signatures
-    exports: FxHashMap<String, ExternType>,
+pub struct ParsedModule {
+    /// Import signatures, in declaration order and including duplicates
+    imports: Vec<((String, String), ExternType)>,
+    /// Export signatures, in declaration order
+    exports: Vec<(String, ExternType)>,
+    /// The indices of the exports by name
+    export_indices: FxHashMap<String, usize>,
+    /// The type section
+    types: Vec<FuncType>,
+    /// The function index space, starting with imported functions
+    functions: Vec<FuncType>,
+    /// The number of imported functions
+    imported_functions: usize,
+    /// The global index space, starting with imported globals
+    globals: Vec<GlobalType>,
+    /// The number of imported globals
+    imported_globals: usize,
+    /// The memory index space, starting with imported memories
+    memories: Vec<MemoryType>,
+    /// The number of imported memories
+    imported_memories: usize,
+    /// The table index space, starting with imported tables
+    tables: Vec<TableType>,
+    /// The number of imported tables
+    imported_tables: usize,
+    /// The index of the start function, if any
+    start: Option<u32>,
+    /// The number of data segments
+    data_segments: u32,
+    /// The number of element segments
+    element_segments: u32,
+    /// The names and contents of all custom sections, in order
+    custom_sections: Vec<(String, Vec<u8>)>,
+    /// The module name from the `name` custom section
+    module_name: Option<String>,
+    /// The function names from the `name` custom section
+    function_names: FxHashMap<u32, String>,
+    /// The module offset at which the code section contents start, if any
+    code_section_start: Option<usize>,
+    /// The map from instrumented to original module offsets, if the module
+    /// has been instrumented
+    offsets: Option<OffsetMap>,
+    /// The debug information of the module, if loaded
+    debug_info: Option<DebugInfo>,
+    /// The fingerprint of the module's bytes, if they are known
+    fingerprint: Option<u64>,
 }
 
 impl ParsedModule {
@@ -117,8 +585,8 @@ impl ParsedModule {
     fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
         let parser = wasmparser::Parser::new(0);
 
-        let mut imports = FxHashMap::default();
-        let mut exports = FxHashMap::default();
+        let mut imports = Vec::new();
+        let mut exports = Vec::new();
 
         let mut types = Vec::new();
 
@@ -127,6 +595,22 @@ impl ParsedModule {
         let mut tables = Vec::new();
         let mut globals = Vec::new();
 
+        let mut imported_functions = 0;
+        let mut imported_globals = 0;
+        let mut imported_memories = 0;
+        let mut imported_tables = 0;
+
+        let mut start = None;
+        let mut data_segments = 0;
+        let mut element_segments = 0;
+        let mut custom_sections = Vec::new();
+
+        let mut code_section_start = None;
+        let mut module_name = None;
+        let mut function_names = FxHashMap::default();
+        let mut function_imports = Vec::new();
+        let mut function_exports = Vec::new();
+
         parser.parse_all(bytes).try_for_each(|payload| {
             match payload? {
                 wasmparser::Payload::TypeSection(section) => {
@@ -143,16 +627,18 @@ impl ParsedModule {
                                         .params()
                                         .iter()
                                         .copied()
-                                        .map(ValueType::from_value),
+                                        .map(ValueType::from_value)
+                                        .collect::<anyhow::Result<Vec<_>>>()?,
                                     func_type
                                         .results()
                                         .iter()
                                         .copied()
-                                        .map(ValueType::from_value),
+                                        .map(ValueType::from_value)
+                                        .collect::<anyhow::Result<Vec<_>>>()?,
                                 ),
-                                _ => unreachable!(),
+                                _ => anyhow::bail!("only function types are supported"),
                             },
-                            _ => unimplemented!(),
+                            _ => anyhow::bail!("recursive type groups are not supported"),
                         };
 
                         types.push(ty);
@@ -162,7 +648,9 @@ impl ParsedModule {
                     for type_index in section {
                         let type_index = type_index?;
 
-                        let ty = &types[type_index as usize];
+                        let ty = types
+                            .get(type_index as usize)
+                            .with_context(|| format!("unknown type {type_index}"))?;
 
                         functions.push(ty.clone());
                     }
@@ -182,7 +670,7 @@ impl ParsedModule {
                 wasmparser::Payload::GlobalSection(section) => {
                     for global in section {
                         let global = global?;
-                        globals.push(GlobalType::from_parsed(global.ty));
+                        globals.push(GlobalType::from_parsed(global.ty)?);
                     }
                 },
                 wasmparser::Payload::TagSection(section) => {
@@ -200,7 +688,13 @@ impl ParsedModule {
                         let import = import?;
                         let ty = match import.ty {
                             wasmparser::TypeRef::Func(index) => {
-                                let sig = types[index as usize].clone().with_name(import.name);
+                                function_imports
+                                    .push((imports.len(), u32::try_from(functions.len())?));
+                                let sig = types
+                                    .get(index as usize)
+                                    .with_context(|| format!("unknown type {index}"))?
+                                    .clone()
+                                    .with_name(import.name);
                                 functions.push(sig.clone());
                                 ExternType::Func(sig)
                             },
@@ -213,16 +707,21 @@ impl ParsedModule {
                                 ExternType::Memory(MemoryType::from_parsed(&ty)?)
                             },
                             wasmparser::TypeRef::Global(ty) => {
-                                globals.push(GlobalType::from_parsed(ty));
-                                ExternType::Global(GlobalType::from_parsed(ty))
+                                globals.push(GlobalType::from_parsed(ty)?);
+                                ExternType::Global(GlobalType::from_parsed(ty)?)
                             },
                             wasmparser::TypeRef::Tag(_) => {
-                                unimplemented!("WebAssembly.Tag is not yet supported")
+                                anyhow::bail!("WebAssembly.Tag is not yet supported")
                             },
                         };
 
-                        imports.insert((import.module.to_string(), import.name.to_string()), ty);
+                        imports.push(((import.module.to_string(), import.name.to_string()), ty));
                     }
+
+                    imported_functions = functions.len();
+                    imported_globals = globals.len();
+                    imported_memories = memories.len();
+                    imported_tables = tables.len();
                 },
                 wasmparser::Payload::ExportSection(section) => {
                     for export in section {
@@ -230,20 +729,58 @@ impl ParsedModule {
                         let index = export.index as usize;
                         let ty = match export.kind {
                             wasmparser::ExternalKind::Func => {
-                                ExternType::Func(functions[index].clone().with_name(export.name))
+                                function_exports.push((exports.len(), export.index));
+                                ExternType::Func(
+                                    functions
+                                        .get(index)
+                                        .with_context(|| format!("unknown function {index}"))?
+                                        .clone()
+                                        .with_name(export.name),
+                                )
                             },
-                            wasmparser::ExternalKind::Table => ExternType::Table(tables[index]),
-                            wasmparser::ExternalKind::Memory => ExternType::Memory(memories[index]),
-                            wasmparser::ExternalKind::Global => ExternType::Global(globals[index]),
+                            wasmparser::ExternalKind::Table => ExternType::Table(
+                                *tables
+                                    .get(index)
+                                    .with_context(|| format!("unknown table {index}"))?,
+                            ),
+                            wasmparser::ExternalKind::Memory => ExternType::Memory(
+                                *memories
+                                    .get(index)
+                                    .with_context(|| format!("unknown memory {index}"))?,
+                            ),
+                            wasmparser::ExternalKind::Global => ExternType::Global(
+                                *globals
+                                    .get(index)
+                                    .with_context(|| format!("unknown global {index}"))?,
+                            ),
                             wasmparser::ExternalKind::Tag => {
-                                unimplemented!("WebAssembly.Tag is not yet supported")
+                                anyhow::bail!("WebAssembly.Tag is not yet supported")
                             },
                         };
 
-                        exports.insert(export.name.to_string(), ty);
+                        exports.push((export.name.to_string(), ty));
                     }
                 },
+                wasmparser::Payload::CodeSectionStart { range, .. } => {
+                    code_section_start = Some(range.start);
+                },
+                wasmparser::Payload::StartSection { func, .. } => {
+                    start = Some(func);
+                },
+                wasmparser::Payload::DataSection(section) => {
+                    data_segments = section.count();
+                },
+                wasmparser::Payload::CustomSection(section) => {
+                    if let wasmparser::KnownCustom::Name(names) = section.as_known() {
+                        // malformed name sections are ignored, as in the JS API
+                        let _ = parse_names(names, &mut module_name, &mut function_names);
+                    }
+
+                    custom_sections.push((section.name().to_string(), section.data().to_vec()));
+                },
                 wasmparser::Payload::ElementSection(section) => {
+                    element_segments = section.count();
+
                     for element in section {
                         let element = element?;
 
@@ -263,34 +800,310 @@ impl ParsedModule {
             anyhow::Ok(())
         })?;
 
-        Ok(Self { imports, exports })
+        // name functions by their debug names, and fall back to the import and
+        // export names
+        for (index, name) in &function_names {
+            if let Some(function) = functions.get_mut(*index as usize) {
+                *function = function.clone().with_name(name.as_str());
+            }
+        }
+        for (position, index) in function_imports {
+            if function_names.contains_key(&index) {
+                imports[position].1 = ExternType::Func(functions[index as usize].clone());
+            }
+        }
+        for (position, index) in function_exports {
+            if function_names.contains_key(&index) {
+                exports[position].1 = ExternType::Func(functions[index as usize].clone());
+            }
+        }
+
+        let export_indices = exports
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.clone(), index))
+            .collect();
+
+        Ok(Self {
+            imports,
+            exports,
+            export_indices,
+            types,
+            functions,
+            imported_functions,
+            globals,
+            imported_globals,
+            memories,
+            imported_memories,
+            tables,
+            imported_tables,
+            start,
+            data_segments,
+            element_segments,
+            custom_sections,
+            module_name,
+            function_names,
+            code_section_start,
+            offsets: None,
+            debug_info: None,
+            fingerprint: Some(fxhash::hash64(bytes)),
+        })
+    }
+
+    /// Creates a module from the `imports` and `exports` of the descriptors of
+    /// a JavaScript `WebAssembly.Module`, which must all include their types
+    fn from_descriptors(
+        imports: Vec<((String, String), DescriptorType)>,
+        exports: Vec<(String, DescriptorType)>,
+    ) -> anyhow::Result<Self> {
+        let mut functions = Vec::new();
+        let mut globals = Vec::new();
+        let mut memories = Vec::new();
+        let mut tables = Vec::new();
+
+        let imports = imports
+            .into_iter()
+            .map(|((module, name), (_, ty))| {
+                let Some(ty) = ty else {
+                    anyhow::bail!(
+                        "the type of the import {module:?}.{name:?} cannot be recovered without \
+                         JS Type Reflection support, provide the module's bytes instead"
+                    );
+                };
+
+                let ty = match ty {
+                    ExternType::Func(ty) => {
+                        let ty = ty.with_name(name.as_str());
+                        functions.push(ty.clone());
+                        ExternType::Func(ty)
+                    },
+                    ExternType::Global(ty) => {
+                        globals.push(ty);
+                        ExternType::Global(ty)
+                    },
+                    ExternType::Memory(ty) => {
+                        memories.push(ty);
+                        ExternType::Memory(ty)
+                    },
+                    ExternType::Table(ty) => {
+                        tables.push(ty);
+                        ExternType::Table(ty)
+                    },
+                };
+
+                Ok(((module, name), ty))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let exports = exports
+            .into_iter()
+            .map(|(name, (_, ty))| match ty {
+                Some(ExternType::Func(ty)) => {
+                    Ok((name.clone(), ExternType::Func(ty.with_name(name))))
+                },
+                Some(ty) => Ok((name, ty)),
+                None => anyhow::bail!(
+                    "the type of the export {name:?} cannot be recovered without JS Type \
+                     Reflection support, provide the module's bytes instead"
+                ),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let export_indices = exports
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.clone(), index))
+            .collect();
+
+        Ok(Self {
+            imports,
+            exports,
+            export_indices,
+            types: Vec::new(),
+            imported_functions: functions.len(),
+            functions,
+            imported_globals: globals.len(),
+            globals,
+            imported_memories: memories.len(),
+            memories,
+            imported_tables: tables.len(),
+            tables,
+            start: None,
+            data_segments: 0,
+            element_segments: 0,
+            custom_sections: Vec::new(),
+            module_name: None,
+            function_names: FxHashMap::default(),
+            code_section_start: None,
+            offsets: None,
+            debug_info: None,
+            // modules with the same imports and exports cannot be told apart
+            // without their bytes
+            fingerprint: None,
+        })
+    }
+
+    /// Checks that the module's imports and exports match the `imports` and
+    /// `exports` of the descriptors of a JavaScript `WebAssembly.Module`
+    fn check_descriptors(
+        &self,
+        imports: &[((String, String), DescriptorType)],
+        exports: &[(String, DescriptorType)],
+    ) -> anyhow::Result<()> {
+        /// Checks that a parsed type matches a descriptor's kind and type
+        fn matches(ty: &ExternType, (kind, descriptor): &DescriptorType) -> bool {
+            let same_type = match (ty, descriptor) {
+                (_, None) => true,
+                (ExternType::Func(ty), Some(ExternType::Func(descriptor))) => ty == descriptor,
+                (ExternType::Global(ty), Some(ExternType::Global(descriptor))) => ty == descriptor,
+                (ExternType::Memory(ty), Some(ExternType::Memory(descriptor))) => ty == descriptor,
+                (ExternType::Table(ty), Some(ExternType::Table(descriptor))) => ty == descriptor,
+                _ => false,
+            };
+
+            same_type && extern_kind(ty) == kind
+        }
+
+        let same_imports = self.imports.len() == imports.len()
+            && self.imports.iter().zip(imports).all(
+                |((name, ty), (descriptor_name, descriptor))| {
+                    name == descriptor_name && matches(ty, descriptor)
+                },
+            );
+        let same_exports = self.exports.len() == exports.len()
+            && self.exports.iter().zip(exports).all(
+                |((name, ty), (descriptor_name, descriptor))| {
+                    name == descriptor_name && matches(ty, descriptor)
+                },
+            );
+
+        if !same_imports || !same_exports {
+            anyhow::bail!(
+                "the bytes do not match the imports and exports of the WebAssembly.Module"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Loads the DWARF debug information from the module's custom sections
+    ///
+    /// Malformed debug information is ignored, as it does not affect the
+    /// module's execution.
+    fn load_dwarf(&mut self) {
+        let Some(code_section_start) = self.code_section_start else {
+            return;
+        };
+
+        match DebugInfo::from_dwarf(&self.custom_sections, code_section_start) {
+            Ok(debug_info) => self.debug_info = debug_info,
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("failed to read DWARF debug information: {err:?}");
+                #[cfg(not(feature = "tracing"))]
+                let _ = err;
+            },
+        }
+    }
+
+    /// Maps an `offset` in the compiled, potentially instrumented, module
+    /// back to the offset in the original module
+    pub(crate) fn original_offset(&self, offset: usize) -> usize {
+        self.offsets
+            .as_ref()
+            .and_then(|offsets| offsets.original(offset))
+            .unwrap_or(offset)
+    }
+
+    /// Returns the source location of the instruction at the original module
+    /// `offset`, if debug information has been loaded
+    pub(crate) fn source_location(&self, offset: usize) -> Option<&SourceLocation> {
+        self.debug_info.as_ref()?.location(offset)
+    }
+
+    /// Returns the name of the function with the given `index` from the
+    /// `name` custom section, if any
+    pub(crate) fn function_name(&self, index: u32) -> Option<&str> {
+        self.function_names.get(&index).map(String::as_str)
+    }
+
+    /// Returns the index of the export with the given `name`, which is also
+    /// its position in [`Self::exports`]
+    pub(crate) fn export_index(&self, name: &str) -> Option<usize> {
+        self.export_indices.get(name).copied()
+    }
+
+    /// Returns the names and types of the module's exports, in declaration
+    /// order
+    pub(crate) fn exports(&self) -> impl Iterator<Item = (&str, ExternType)> {
+        self.exports
+            .iter()
+            .map(|(name, ty)| (name.as_str(), ty.clone()))
+    }
+
+    /// Returns the fingerprint of the module's bytes, which identifies the
+    /// module, or [`None`] if the module was created without its bytes
+    pub(crate) const fn fingerprint(&self) -> Option<u64> {
+        self.fingerprint
+    }
+
+    /// Returns the memories that are defined, not imported, by the module
+    pub(crate) fn defined_memories(&self) -> &[MemoryType] {
+        &self.memories[self.imported_memories..]
+    }
+
+    /// Returns the tables that are defined, not imported, by the module
+    pub(crate) fn defined_tables(&self) -> &[TableType] {
+        &self.tables[self.imported_tables..]
+    }
+}
+
+/// Parses the module and function names from a `name` custom section
+fn parse_names(
+    names: wasmparser::NameSectionReader,
+    module_name: &mut Option<String>,
+    function_names: &mut FxHashMap<u32, String>,
+) -> anyhow::Result<()> {
+    for name in names {
+        match name? {
+            wasmparser::Name::Module { name, .. } => *module_name = Some(String::from(name)),
+            wasmparser::Name::Function(names) => {
+                for naming in names {
+                    let naming = naming?;
+                    function_names.insert(naming.index, String::from(naming.name));
+                }
+            },
+            _ => (),
+        }
     }
+
+    Ok(())
 }
 
-trait ValueTypeFrom {
-    fn from_value(value: wasmparser::ValType) -> Self;
-    fn from_ref(ty: wasmparser::RefType) -> Self;
+trait ValueTypeFrom: Sized {
+    fn from_value(value: wasmparser::ValType) -> anyhow::Result<Self>;
+    fn from_ref(ty: wasmparser::RefType) -> anyhow::Result<Self>;
 }
 
 impl ValueTypeFrom for ValueType {
-    fn from_value(value: wasmparser::ValType) -> Self {
+    fn from_value(value: wasmparser::ValType) -> anyhow::Result<Self> {
         match value {
-            wasmparser::ValType::I32 => Self::I32,
-            wasmparser::ValType::I64 => Self::I64,
-            wasmparser::ValType::F32 => Self::F32,
-            wasmparser::ValType::F64 => Self::F64,
-            wasmparser::ValType::V128 => unimplemented!("v128 is not supported"),
+            wasmparser::ValType::I32 => Ok(Self::I32),
+            wasmparser::ValType::I64 => Ok(Self::I64),
+            wasmparser::ValType::F32 => Ok(Self::F32),
+            wasmparser::ValType::F64 => Ok(Self::F64),
+            wasmparser::ValType::V128 => anyhow::bail!("v128 is not supported"),
             wasmparser::ValType::Ref(ty) => Self::from_ref(ty),
         }
     }
 
-    fn from_ref(ty: wasmparser::RefType) -> Self {
+    fn from_ref(ty: wasmparser::RefType) -> anyhow::Result<Self> {
         if ty.is_func_ref() {
-            Self::FuncRef
+            Ok(Self::FuncRef)
         } else if ty.is_extern_ref() {
-            Self::ExternRef
+            Ok(Self::ExternRef)
         } else {
-            unimplemented!("unsupported reference type {ty:?}")
+            anyhow::bail!("unsupported reference type {ty:?}")
         }
     }
 }
@@ -302,7 +1115,7 @@ trait TableTypeFrom: Sized {
 impl TableTypeFrom for TableType {
     fn from_parsed(value: &wasmparser::TableType) -> anyhow::Result<Self> {
         Ok(Self::new(
-            ValueType::from_ref(value.element_type),
+            ValueType::from_ref(value.element_type)?,
             value.initial.try_into()?,
             match value.maximum {
                 None => None,
@@ -336,17 +1149,297 @@ impl MemoryTypeFrom for MemoryType {
     }
 }
 
-trait GlobalTypeFrom {
-    fn from_parsed(value: wasmparser::GlobalType) -> Self;
+trait GlobalTypeFrom: Sized {
+    fn from_parsed(value: wasmparser::GlobalType) -> anyhow::Result<Self>;
 }
 
 impl GlobalTypeFrom for GlobalType {
-    fn from_parsed(value: wasmparser::GlobalType) -> Self {
-        Self::new(ValueType::from_value(value.content_type), value.mutable)
+    fn from_parsed(value: wasmparser::GlobalType) -> anyhow::Result<Self> {
+        Ok(Self::new(
+            ValueType::from_value(value.content_type)?,
+            value.mutable,
+        ))
+    }
+}
+
+/// The kind of an import or export descriptor of a `WebAssembly.Module`,
+/// together with its type, if the JS Type Reflection proposal is supported
+type DescriptorType = (String, Option<ExternType>);
+
+/// Extracts the kind and, if available, the type of an import or export
+/// `descriptor` of a `WebAssembly.Module`
+fn descriptor_type(descriptor: &Bound<PyAny>) -> anyhow::Result<DescriptorType> {
+    let py = descriptor.py();
+
+    let kind: String = descriptor.getattr(intern!(py, "kind"))?.extract()?;
+
+    let ty = match descriptor.getattr(intern!(py, "type")) {
+        Ok(ty) if !ty.is_none() => Some(extern_type_from_js(&kind, &ty)?),
+        _ => None,
+    };
+
+    Ok((kind, ty))
+}
+
+/// Returns the type of an `import` with the current size of memories and
+/// tables, which may have grown since they were created
+fn current_extern_type(ctx: impl AsContext<Engine>, import: &Extern<Engine>) -> ExternType {
+    match import {
+        Extern::Memory(memory) => ExternType::Memory(MemoryType::new(
+            memory.current_pages(ctx.as_context()),
+            memory.ty(ctx.as_context()).maximum_pages(),
+        )),
+        Extern::Table(table) => {
+            let ty = table.ty(ctx.as_context());
+            ExternType::Table(TableType::new(
+                ty.element(),
+                table.size(ctx.as_context()),
+                ty.maximum(),
+            ))
+        },
+        import => import.ty(ctx),
     }
 }
 
-fn web_assembly_module_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_MODULE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_MODULE.import(py, "js.WebAssembly.Module", "new")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_spaces() {
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (type $unary (func (param i32) (result i32)))
+                (import "env" "f" (func $f (type $unary)))
+                (import "env" "g" (global $g i32))
+                (import "env" "memory" (memory 1))
+                (global $counter (mut i64) (i64.const 0))
+                (table 2 funcref)
+                (func $start)
+                (func $id (type $unary) (local.get 0))
+                (start $start)
+                (elem (i32.const 0) $f $id)
+                (data (i32.const 0) "hello")
+                (data "world")
+                (@custom "manifest" "{}")
+            )
+            "#,
+        )
+        .unwrap();
+
+        let parsed = ParsedModule::parse(&bytes).unwrap();
+
+        assert_eq!(parsed.types.len(), 2);
+        assert_eq!(parsed.functions.len(), 3);
+        assert_eq!(parsed.imported_functions, 1);
+        assert_eq!(
+            parsed.functions[2],
+            FuncType::new([ValueType::I32], [ValueType::I32])
+        );
+        assert_eq!(parsed.globals.len(), 2);
+        assert_eq!(parsed.imported_globals, 1);
+        assert_eq!(parsed.memories.len(), 1);
+        assert!(parsed.defined_memories().is_empty());
+        assert_eq!(parsed.defined_tables().len(), 1);
+        assert_eq!(parsed.start, Some(1));
+        assert_eq!(parsed.data_segments, 2);
+        assert_eq!(parsed.element_segments, 1);
+        assert_eq!(
+            parsed
+                .custom_sections
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["name", "manifest"]
+        );
+        assert_eq!(parsed.custom_sections[1].1, b"{}");
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        for wat in [
+            r#"(module (func (export "f") (param v128)))"#,
+            r#"(module (import "env" "t" (tag)))"#,
+            "(module (rec (type (func)) (type (func))))",
+        ] {
+            let bytes = wat::parse_str(wat).unwrap();
+            assert!(ParsedModule::parse(&bytes).is_err(), "{wat}");
+        }
+
+        // an export of an unknown function is an error rather than a panic
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00, // (export "f" (func 0))
+        ];
+        assert!(ParsedModule::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn declaration_order() {
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (import "env" "z" (func))
+                (import "env" "a" (global i32))
+                (import "env" "dup" (func))
+                (import "other" "m" (memory 1))
+                (import "env" "dup" (func (param i32)))
+                (func (export "zeta"))
+                (global (export "alpha") i32 (i32.const 0))
+                (func (export "mid"))
+                (export "beta" (memory 0))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let parsed = ParsedModule::parse(&bytes).unwrap();
+
+        let imports = parsed
+            .imports
+            .iter()
+            .map(|((module, name), _)| (module.as_str(), name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            imports,
+            [
+                ("env", "z"),
+                ("env", "a"),
+                ("env", "dup"),
+                ("other", "m"),
+                ("env", "dup"),
+            ]
+        );
+
+        // duplicate imports keep their own types
+        assert!(matches!(
+            &parsed.imports[2].1,
+            ExternType::Func(ty) if ty.params().is_empty()
+        ));
+        assert!(matches!(
+            &parsed.imports[4].1,
+            ExternType::Func(ty) if ty.params() == [ValueType::I32]
+        ));
+
+        let exports = parsed.exports().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(exports, ["zeta", "alpha", "mid", "beta"]);
+
+        assert_eq!(parsed.export_index("mid"), Some(2));
+        assert_eq!(parsed.export_index("missing"), None);
+    }
+
+    #[test]
+    fn name_section() {
+        let bytes = wat::parse_str(
+            r#"
+            (module $plugin
+                (import "env" "log" (func $host_log))
+                (import "env" "abort" (func))
+                (func $inner (unreachable))
+                (func $outer (export "run") (call $inner))
+                (func (export "anonymous"))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let parsed = ParsedModule::parse(&bytes).unwrap();
+
+        assert_eq!(parsed.module_name.as_deref(), Some("plugin"));
+        assert_eq!(parsed.function_name(0), Some("host_log"));
+        assert_eq!(parsed.function_name(1), None);
+        assert_eq!(parsed.function_name(3), Some("outer"));
+
+        let stack = "RuntimeError: unreachable\n    at inner \
+                     (wasm://wasm/cc1cabfe:wasm-function[2]:0x21)\n    at outer \
+                     (wasm://wasm/cc1cabfe:wasm-function[3]:0x25)\n    at <anonymous>:3:15";
+        let backtrace = WasmBacktrace::from_stack(&parsed, stack);
+
+        let frames = backtrace
+            .frames()
+            .iter()
+            .map(|frame| (frame.func_index(), frame.func_name(), frame.module_offset()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            [
+                (2, Some("inner"), Some(0x21)),
+                (3, Some("outer"), Some(0x25))
+            ]
+        );
+    }
+
+    #[test]
+    fn js_module_descriptors() {
+        let bytes = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func (param i32)))
+                (import "env" "memory" (memory 1 2))
+                (global (export "counter") (mut i64) (i64.const 0))
+                (func (export "run"))
+            )
+            "#,
+        )
+        .unwrap();
+
+        let parsed = ParsedModule::parse(&bytes).unwrap();
+
+        let descriptor = |kind: &str, ty: Option<ExternType>| (String::from(kind), ty);
+        let imports = vec![
+            (
+                (String::from("env"), String::from("f")),
+                descriptor(
+                    "function",
+                    Some(ExternType::Func(FuncType::new([ValueType::I32], []))),
+                ),
+            ),
+            (
+                (String::from("env"), String::from("memory")),
+                descriptor(
+                    "memory",
+                    Some(ExternType::Memory(MemoryType::new(1, Some(2)))),
+                ),
+            ),
+        ];
+        let exports = vec![
+            (
+                String::from("counter"),
+                descriptor(
+                    "global",
+                    Some(ExternType::Global(GlobalType::new(ValueType::I64, true))),
+                ),
+            ),
+            (
+                String::from("run"),
+                descriptor("function", Some(ExternType::Func(FuncType::new([], [])))),
+            ),
+        ];
+
+        parsed.check_descriptors(&imports, &exports).unwrap();
+
+        // descriptors without types only need to match in their kinds
+        let untyped_exports = vec![
+            (String::from("counter"), descriptor("global", None)),
+            (String::from("run"), descriptor("function", None)),
+        ];
+        parsed
+            .check_descriptors(&imports, &untyped_exports)
+            .unwrap();
+        assert!(ParsedModule::from_descriptors(imports.clone(), untyped_exports).is_err());
+
+        let mut swapped_exports = exports.clone();
+        swapped_exports.swap(0, 1);
+        assert!(parsed
+            .check_descriptors(&imports, &swapped_exports)
+            .is_err());
+
+        let reflected = ParsedModule::from_descriptors(imports, exports).unwrap();
+        assert_eq!(reflected.imports.len(), 2);
+        assert_eq!(reflected.functions.len(), 1);
+        assert_eq!(reflected.imported_functions, 1);
+        assert_eq!(reflected.memories, [MemoryType::new(1, Some(2))]);
+        assert_eq!(reflected.export_index("run"), Some(1));
+    }
 }
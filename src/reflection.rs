@@ -0,0 +1,261 @@
+use pyo3::{intern, prelude::*};
+use wasm_runtime_layer::{ExternType, FuncType, GlobalType, MemoryType, TableType, ValueType};
+
+use crate::link::{is_compatible, DisplayExternType};
+
+/// Returns the type of the JavaScript WebAssembly `object`, which is checked
+/// against the `expected` type if one is given and inferred otherwise
+///
+/// If the web browser supports the JS Type Reflection proposal, the object's
+/// type descriptor is reflected once and converted with `from_js`. Otherwise,
+/// the expected type is trusted and no type can be inferred.
+pub fn reflect_type<T: Clone + Into<ExternType>>(
+    object: &Bound<PyAny>,
+    expected: Option<T>,
+    from_js: fn(&Bound<PyAny>) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let reflected = match type_descriptor(object)? {
+        Some(descriptor) => Some(from_js(&descriptor)?),
+        None => None,
+    };
+
+    match (expected, reflected) {
+        (Some(expected), reflected) => {
+            check_reflected_type(reflected.map(Into::into).as_ref(), &expected.clone().into())?;
+            Ok(expected)
+        },
+        (None, Some(reflected)) => Ok(reflected),
+        (None, None) => anyhow::bail!(
+            "the type of {object} cannot be inferred without JS Type Reflection support"
+        ),
+    }
+}
+
+/// Checks that a `reflected` type, if known, is compatible with the
+/// `expected` type, i.e. that it could be used as an import of that type
+fn check_reflected_type(
+    reflected: Option<&ExternType>,
+    expected: &ExternType,
+) -> anyhow::Result<()> {
+    match reflected {
+        Some(reflected) if !is_compatible(reflected, expected) => anyhow::bail!(
+            "expected {} but found {}",
+            DisplayExternType(expected),
+            DisplayExternType(reflected)
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Converts an import or export descriptor `kind` with the type descriptor
+/// `ty` from the [JS Type Reflection] proposal into an [`ExternType`]
+///
+/// [JS Type Reflection]: https://github.com/WebAssembly/js-types
+pub fn extern_type_from_js(kind: &str, ty: &Bound<PyAny>) -> anyhow::Result<ExternType> {
+    match kind {
+        "function" => func_type_from_js(ty).map(ExternType::Func),
+        "global" => global_type_from_js(ty).map(ExternType::Global),
+        "memory" => memory_type_from_js(ty).map(ExternType::Memory),
+        "table" => table_type_from_js(ty).map(ExternType::Table),
+        "tag" => anyhow::bail!("WebAssembly.Tag is not yet supported"),
+        kind => anyhow::bail!("unknown WebAssembly extern kind {kind:?}"),
+    }
+}
+
+/// Returns the import or export descriptor kind of an [`ExternType`]
+pub const fn extern_kind(ty: &ExternType) -> &'static str {
+    match ty {
+        ExternType::Func(_) => "function",
+        ExternType::Global(_) => "global",
+        ExternType::Memory(_) => "memory",
+        ExternType::Table(_) => "table",
+    }
+}
+
+/// Converts a `{ parameters, results }` function type descriptor
+pub fn func_type_from_js(ty: &Bound<PyAny>) -> anyhow::Result<FuncType> {
+    let py = ty.py();
+
+    let params = value_types_from_js(&ty.getattr(intern!(py, "parameters"))?)?;
+    let results = value_types_from_js(&ty.getattr(intern!(py, "results"))?)?;
+
+    Ok(FuncType::new(params, results))
+}
+
+/// Converts a `{ value, mutable }` global type descriptor
+pub fn global_type_from_js(ty: &Bound<PyAny>) -> anyhow::Result<GlobalType> {
+    let py = ty.py();
+
+    let content = value_type_from_js(&ty.getattr(intern!(py, "value"))?.extract::<String>()?)?;
+    let mutable = ty.getattr(intern!(py, "mutable"))?.extract()?;
+
+    Ok(GlobalType::new(content, mutable))
+}
+
+/// Converts a `{ minimum, maximum?, shared? }` memory type descriptor
+pub fn memory_type_from_js(ty: &Bound<PyAny>) -> anyhow::Result<MemoryType> {
+    let py = ty.py();
+
+    if optional_property(ty, intern!(py, "shared"))?.unwrap_or(false) {
+        anyhow::bail!("shared memories are not yet supported");
+    }
+
+    let minimum = ty.getattr(intern!(py, "minimum"))?.extract()?;
+    let maximum = optional_property(ty, intern!(py, "maximum"))?;
+
+    Ok(MemoryType::new(minimum, maximum))
+}
+
+/// Converts an `{ element, minimum, maximum? }` table type descriptor
+pub fn table_type_from_js(ty: &Bound<PyAny>) -> anyhow::Result<TableType> {
+    let py = ty.py();
+
+    let element = value_type_from_js(&ty.getattr(intern!(py, "element"))?.extract::<String>()?)?;
+    let minimum = ty.getattr(intern!(py, "minimum"))?.extract()?;
+    let maximum = optional_property(ty, intern!(py, "maximum"))?;
+
+    Ok(TableType::new(element, minimum, maximum))
+}
+
+/// Converts a value type descriptor, e.g. `"i32"` or `"funcref"`
+pub fn value_type_from_js(ty: &str) -> anyhow::Result<ValueType> {
+    match ty {
+        "i32" => Ok(ValueType::I32),
+        "i64" => Ok(ValueType::I64),
+        "f32" => Ok(ValueType::F32),
+        "f64" => Ok(ValueType::F64),
+        // older engines still use the legacy name "anyfunc"
+        "funcref" | "anyfunc" => Ok(ValueType::FuncRef),
+        "externref" => Ok(ValueType::ExternRef),
+        ty => anyhow::bail!("unsupported WebAssembly value type {ty:?}"),
+    }
+}
+
+/// Converts an array of value type descriptors
+fn value_types_from_js(types: &Bound<PyAny>) -> anyhow::Result<Vec<ValueType>> {
+    types
+        .try_iter()?
+        .map(|ty| value_type_from_js(&ty?.extract::<String>()?))
+        .collect()
+}
+
+/// Extracts the optional property `name` of the descriptor `ty`, which may be
+/// missing or `undefined`
+fn optional_property<'py, T: FromPyObject<'py>>(
+    ty: &Bound<'py, PyAny>,
+    name: &Bound<'py, pyo3::types::PyString>,
+) -> anyhow::Result<Option<T>> {
+    match ty.getattr(name) {
+        Ok(value) if !value.is_none() => Ok(Some(value.extract()?)),
+        _ => Ok(None),
+    }
+}
+
+/// Returns the type descriptor of the JavaScript WebAssembly `object` from its
+/// `type()` method, if the web browser supports the JS Type Reflection
+/// proposal
+pub fn type_descriptor<'py>(
+    object: &Bound<'py, PyAny>,
+) -> Result<Option<Bound<'py, PyAny>>, PyErr> {
+    match object.getattr(intern!(object.py(), "type")) {
+        Ok(method) if method.is_callable() => method.call0().map(Some),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::backend::{Imports, Value, WasmGlobal, WasmMemory};
+
+    use super::*;
+    use crate::{test_utils, Func, Global, Memory};
+
+    #[test]
+    fn reflected_type_mismatches() {
+        let unary = ExternType::Func(FuncType::new([ValueType::I32], [ValueType::I32]));
+        let nullary = ExternType::Func(FuncType::new([], [ValueType::I32]));
+        let counter = ExternType::Global(GlobalType::new(ValueType::I64, true));
+        let constant = ExternType::Global(GlobalType::new(ValueType::I64, false));
+        let memory = ExternType::Memory(MemoryType::new(2, Some(4)));
+        let table = ExternType::Table(TableType::new(ValueType::FuncRef, 1, None));
+
+        // without JS Type Reflection, the expected type is trusted
+        check_reflected_type(None, &unary).unwrap();
+
+        check_reflected_type(Some(&unary), &unary).unwrap();
+        check_reflected_type(Some(&counter), &counter).unwrap();
+        check_reflected_type(Some(&memory), &ExternType::Memory(MemoryType::new(1, None))).unwrap();
+
+        assert_eq!(
+            check_reflected_type(Some(&nullary), &unary)
+                .unwrap_err()
+                .to_string(),
+            "expected func(i32) -> i32 but found func() -> i32"
+        );
+        assert_eq!(
+            check_reflected_type(Some(&constant), &counter)
+                .unwrap_err()
+                .to_string(),
+            "expected global (mut i64) but found global i64"
+        );
+        assert!(
+            check_reflected_type(Some(&memory), &ExternType::Memory(MemoryType::new(3, None)))
+                .is_err()
+        );
+        assert!(check_reflected_type(
+            Some(&table),
+            &ExternType::Table(TableType::new(ValueType::ExternRef, 1, None))
+        )
+        .is_err());
+        assert!(check_reflected_type(Some(&table), &memory).is_err());
+    }
+
+    #[test]
+    fn foreign_objects() {
+        let mut store = test_utils::store();
+
+        let memory = Memory::new(&mut store, MemoryType::new(1, Some(4))).unwrap();
+        memory.grow(&mut store, 1).unwrap();
+        let global = Global::new(&mut store, Value::I64(42), true);
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"(module (func (export "id") (param i32) (result i32) (local.get 0)))"#,
+            &Imports::default(),
+        )
+        .unwrap();
+        let id = test_utils::export_func(&store, &instance, "id");
+
+        Python::with_gil(|py| {
+            let memory = memory.as_js(py);
+            assert_eq!(
+                Memory::from_js(memory, None).unwrap().ty(&store),
+                MemoryType::new(2, Some(4))
+            );
+            Memory::from_js(memory, Some(MemoryType::new(1, None))).unwrap();
+            assert!(Memory::from_js(memory, Some(MemoryType::new(1, Some(2)))).is_err());
+
+            let global = global.as_js(py);
+            assert_eq!(
+                Global::from_js(global, None).unwrap().ty(&store),
+                GlobalType::new(ValueType::I64, true)
+            );
+            assert_eq!(
+                Global::from_js(global, Some(GlobalType::new(ValueType::I64, false)))
+                    .unwrap_err()
+                    .to_string(),
+                "expected global i64 but found global (mut i64)"
+            );
+
+            // the reflected type is checked even if the number of parameters
+            // matches
+            let id = id.as_js(py);
+            Func::from_js(id, Some(FuncType::new([ValueType::I32], [ValueType::I32]))).unwrap();
+            assert_eq!(
+                Func::from_js(id, Some(FuncType::new([ValueType::I64], [ValueType::I32])))
+                    .unwrap_err()
+                    .to_string(),
+                "expected func(i64) -> i32 but found func(i32) -> i32"
+            );
+        });
+    }
+}
@@ -0,0 +1,766 @@
+use std::{error::Error, fmt};
+
+use fxhash::FxHashMap;
+use pyo3::{intern, prelude::*};
+use wasm_runtime_layer::{
+    backend::{AsContext, AsContextMut, Extern, Value, WasmGlobal, WasmMemory, WasmTable},
+    ExternType, ValueType,
+};
+
+use crate::{conversion::ToPy, store::StoreContextMut, Func, Instance};
+
+/// The magic bytes at the start of every serialized [`Snapshot`]
+const MAGIC: &[u8; 8] = b"PWRLSNAP";
+
+/// The version of the serialized [`Snapshot`] format
+const VERSION: u32 = 1;
+
+/// The size of a WASM memory page in bytes
+const PAGE_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A snapshot of the state of an [`Instance`].
+///
+/// A snapshot contains the contents of all exported memories, the values of
+/// all exported mutable numeric globals, and the elements of all exported
+/// `funcref` tables. Imported entities are only included if they are
+/// re-exported. Tables with `externref` elements are not included, since
+/// their host references cannot be serialized.
+///
+/// A snapshot is taken with [`Instance::snapshot`] and can be restored into
+/// a fresh instance of the same [`Module`] with [`Instance::restore`]. It can
+/// be serialized into a self-describing and versioned binary format with
+/// [`Snapshot::to_bytes`].
+///
+/// [`Module`]: crate::Module
+pub struct Snapshot {
+    /// The fingerprint of the module that the instance was created from
+    module: u64,
+    /// The snapshots of the exported memories
+    memories: Vec<MemorySnapshot>,
+    /// The snapshots of the exported mutable globals
+    globals: Vec<GlobalSnapshot>,
+    /// The snapshots of the exported funcref tables
+    tables: Vec<TableSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A snapshot of an exported memory
+struct MemorySnapshot {
+    /// The export name of the memory
+    name: String,
+    /// The number of pages in the memory
+    pages: u32,
+    /// The contents of the memory
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A snapshot of an exported mutable global
+struct GlobalSnapshot {
+    /// The export name of the global
+    name: String,
+    /// The value of the global
+    value: GlobalValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The bit-exact value of a numeric global
+enum GlobalValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A snapshot of an exported funcref table
+struct TableSnapshot {
+    /// The export name of the table
+    name: String,
+    /// The function indices of the table elements, where `None` is a null
+    /// reference
+    elements: Vec<Option<u32>>,
+}
+
+impl Snapshot {
+    /// Captures a snapshot of the exports of the `instance`
+    pub(crate) fn capture<T>(
+        instance: &Instance,
+        mut store: StoreContextMut<T>,
+    ) -> anyhow::Result<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Snapshot::capture").entered();
+
+        let Some(module) = instance.module().parsed().fingerprint() else {
+            anyhow::bail!(
+                "cannot snapshot an instance of a module that was created without its bytes"
+            );
+        };
+
+        let mut memories = Vec::new();
+        let mut globals = Vec::new();
+        let mut tables = Vec::new();
+
+        for (name, ty) in snapshot_exports(instance) {
+            match (instance.export(name)?, ty) {
+                (Extern::Memory(memory), ExternType::Memory(_)) => {
+                    let pages = memory.current_pages(store.as_context());
+                    let Some(len) = memory_len(pages) else {
+                        anyhow::bail!("cannot snapshot memory {name:?} with {pages} pages");
+                    };
+                    let mut bytes = vec![0; len];
+                    memory.read(store.as_context(), 0, &mut bytes)?;
+
+                    memories.push(MemorySnapshot {
+                        name: String::from(name),
+                        pages,
+                        bytes,
+                    });
+                },
+                (Extern::Global(global), ExternType::Global(_)) => {
+                    let value = match global.get(store.as_context_mut()) {
+                        Value::I32(value) => GlobalValue::I32(value),
+                        Value::I64(value) => GlobalValue::I64(value),
+                        Value::F32(value) => GlobalValue::F32(value.to_bits()),
+                        Value::F64(value) => GlobalValue::F64(value.to_bits()),
+                        value => anyhow::bail!("cannot snapshot global {name:?} with {value:?}"),
+                    };
+
+                    globals.push(GlobalSnapshot {
+                        name: String::from(name),
+                        value,
+                    });
+                },
+                (Extern::Table(table), ExternType::Table(_)) => {
+                    let size = table.size(store.as_context());
+
+                    let elements = (0..size)
+                        .map(|index| match table.get(store.as_context_mut(), index) {
+                            Some(Value::FuncRef(None)) => Ok(None),
+                            Some(Value::FuncRef(Some(func))) => match function_index(&func) {
+                                Some(index) => Ok(Some(index)),
+                                None => anyhow::bail!(
+                                    "cannot snapshot element {index} of table {name:?}, which is \
+                                     not a WebAssembly function"
+                                ),
+                            },
+                            element => anyhow::bail!(
+                                "cannot snapshot element {index} of table {name:?} with \
+                                 {element:?}"
+                            ),
+                        })
+                        .collect::<anyhow::Result<_>>()?;
+
+                    tables.push(TableSnapshot {
+                        name: String::from(name),
+                        elements,
+                    });
+                },
+                (export, ty) => {
+                    anyhow::bail!("export {name:?} is {export:?} but was expected to be {ty:?}")
+                },
+            }
+        }
+
+        Ok(Self {
+            module,
+            memories,
+            globals,
+            tables,
+        })
+    }
+
+    /// Restores this snapshot into the exports of the `instance`
+    pub(crate) fn restore<T>(
+        &self,
+        instance: &Instance,
+        mut store: StoreContextMut<T>,
+    ) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("Snapshot::restore").entered();
+
+        let Some(module) = instance.module().parsed().fingerprint() else {
+            anyhow::bail!(
+                "cannot restore a snapshot into an instance of a module that was created without \
+                 its bytes"
+            );
+        };
+        if self.module != module {
+            anyhow::bail!("the snapshot was taken from an instance of a different module");
+        }
+
+        // resolve the table elements before any tables are overwritten
+        let functions = exported_functions(instance, &mut store)?;
+
+        for MemorySnapshot { name, pages, bytes } in &self.memories {
+            let Extern::Memory(memory) = instance.export(name)? else {
+                anyhow::bail!("export {name:?} is not a memory");
+            };
+
+            let current = memory.current_pages(store.as_context());
+            if current > *pages {
+                anyhow::bail!(
+                    "cannot restore memory {name:?} with {pages} pages into a memory with \
+                     {current} pages"
+                );
+            }
+            if current < *pages {
+                memory.grow(store.as_context_mut(), pages - current)?;
+            }
+
+            memory.write(store.as_context_mut(), 0, bytes)?;
+        }
+
+        for GlobalSnapshot { name, value } in &self.globals {
+            let Extern::Global(global) = instance.export(name)? else {
+                anyhow::bail!("export {name:?} is not a global");
+            };
+
+            let value = match *value {
+                GlobalValue::I32(value) => Value::I32(value),
+                GlobalValue::I64(value) => Value::I64(value),
+                GlobalValue::F32(value) => Value::F32(f32::from_bits(value)),
+                GlobalValue::F64(value) => Value::F64(f64::from_bits(value)),
+            };
+
+            global.set(store.as_context_mut(), value)?;
+        }
+
+        for TableSnapshot { name, elements } in &self.tables {
+            let Extern::Table(table) = instance.export(name)? else {
+                anyhow::bail!("export {name:?} is not a table");
+            };
+
+            let size = u32::try_from(elements.len())?;
+            let current = table.size(store.as_context());
+            if current > size {
+                anyhow::bail!(
+                    "cannot restore table {name:?} with {size} elements into a table with \
+                     {current} elements"
+                );
+            }
+            if current < size {
+                table.grow(store.as_context_mut(), size - current, Value::FuncRef(None))?;
+            }
+
+            for (index, element) in (0..size).zip(elements) {
+                let element = match element {
+                    None => None,
+                    Some(function) => match functions.get(function) {
+                        Some(func) => Some(func.clone()),
+                        None => anyhow::bail!(
+                            "cannot restore element {index} of table {name:?}, since function \
+                             {function} is neither exported nor contained in an exported table"
+                        ),
+                    },
+                };
+
+                table.set(store.as_context_mut(), index, Value::FuncRef(element))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[must_use]
+    /// Serializes the snapshot into a self-describing binary format.
+    ///
+    /// The format starts with a magic number and a format version, which are
+    /// checked by [`Snapshot::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(MAGIC);
+        write_u32(&mut bytes, VERSION);
+        write_u64(&mut bytes, self.module);
+
+        write_len(&mut bytes, self.memories.len());
+        for MemorySnapshot {
+            name,
+            pages,
+            bytes: contents,
+        } in &self.memories
+        {
+            write_str(&mut bytes, name);
+            write_u32(&mut bytes, *pages);
+            write_len(&mut bytes, contents.len());
+            bytes.extend_from_slice(contents);
+        }
+
+        write_len(&mut bytes, self.globals.len());
+        for GlobalSnapshot { name, value } in &self.globals {
+            write_str(&mut bytes, name);
+            match *value {
+                GlobalValue::I32(value) => {
+                    bytes.push(0x7F);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                },
+                GlobalValue::I64(value) => {
+                    bytes.push(0x7E);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                },
+                GlobalValue::F32(value) => {
+                    bytes.push(0x7D);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                },
+                GlobalValue::F64(value) => {
+                    bytes.push(0x7C);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                },
+            }
+        }
+
+        write_len(&mut bytes, self.tables.len());
+        for TableSnapshot { name, elements } in &self.tables {
+            write_str(&mut bytes, name);
+            write_len(&mut bytes, elements.len());
+            for element in elements {
+                match element {
+                    None => bytes.push(0),
+                    Some(index) => {
+                        bytes.push(1);
+                        write_u32(&mut bytes, *index);
+                    },
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Deserializes a snapshot from the binary format produced by
+    /// [`Snapshot::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SnapshotFormatError`] if the `bytes` are not a valid
+    /// snapshot of a supported format version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotFormatError> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotFormatError::InvalidMagic);
+        }
+
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(SnapshotFormatError::UnsupportedVersion { version });
+        }
+
+        let module = reader.u64()?;
+
+        let memories = (0..reader.len()?)
+            .map(|_| {
+                let name = reader.str()?;
+                let pages = reader.u32()?;
+                let len = reader.len()?;
+                if memory_len(pages) != Some(len) {
+                    return Err(SnapshotFormatError::Malformed);
+                }
+                let bytes = reader.take(len)?.to_vec();
+                Ok(MemorySnapshot { name, pages, bytes })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let globals = (0..reader.len()?)
+            .map(|_| {
+                let name = reader.str()?;
+                let value = match reader.u8()? {
+                    0x7F => GlobalValue::I32(i32::from_le_bytes(reader.array()?)),
+                    0x7E => GlobalValue::I64(i64::from_le_bytes(reader.array()?)),
+                    0x7D => GlobalValue::F32(reader.u32()?),
+                    0x7C => GlobalValue::F64(reader.u64()?),
+                    _ => return Err(SnapshotFormatError::Malformed),
+                };
+                Ok(GlobalSnapshot { name, value })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let tables = (0..reader.len()?)
+            .map(|_| {
+                let name = reader.str()?;
+                let elements = (0..reader.len()?)
+                    .map(|_| match reader.u8()? {
+                        0 => Ok(None),
+                        1 => Ok(Some(reader.u32()?)),
+                        _ => Err(SnapshotFormatError::Malformed),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(TableSnapshot { name, elements })
+            })
+            .collect::<Result<_, _>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(SnapshotFormatError::Malformed);
+        }
+
+        Ok(Self {
+            module,
+            memories,
+            globals,
+            tables,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Error that is returned when deserializing an invalid [`Snapshot`].
+pub enum SnapshotFormatError {
+    /// The bytes do not start with the snapshot magic number
+    InvalidMagic,
+    /// The snapshot has an unsupported format `version`
+    UnsupportedVersion {
+        /// The format version of the snapshot
+        version: u32,
+    },
+    /// The snapshot ended unexpectedly
+    UnexpectedEnd,
+    /// The snapshot is malformed
+    Malformed,
+}
+
+impl fmt::Display for SnapshotFormatError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidMagic => fmt.write_str("the bytes are not a snapshot"),
+            Self::UnsupportedVersion { version } => write!(
+                fmt,
+                "the snapshot format version {version} is not supported, expected version \
+                 {VERSION}"
+            ),
+            Self::UnexpectedEnd => fmt.write_str("the snapshot ended unexpectedly"),
+            Self::Malformed => fmt.write_str("the snapshot is malformed"),
+        }
+    }
+}
+
+impl Error for SnapshotFormatError {}
+
+/// Returns the names and types of the exports that are included in a
+/// [`Snapshot`] of the `instance`, in declaration order
+fn snapshot_exports(instance: &Instance) -> Vec<(&str, ExternType)> {
+    instance
+        .module()
+        .parsed()
+        .exports()
+        .filter(|(_, ty)| match ty {
+            ExternType::Memory(_) => true,
+            ExternType::Global(ty) => {
+                ty.mutable()
+                    && matches!(
+                        ty.content(),
+                        ValueType::I32 | ValueType::I64 | ValueType::F32 | ValueType::F64
+                    )
+            },
+            ExternType::Table(ty) => ty.element() == ValueType::FuncRef,
+            ExternType::Func(_) => false,
+        })
+        .collect()
+}
+
+/// Collects all functions that are exported by the `instance` or contained in
+/// its exported tables, indexed by their function index
+fn exported_functions<T>(
+    instance: &Instance,
+    store: &mut StoreContextMut<T>,
+) -> anyhow::Result<FxHashMap<u32, Func>> {
+    let mut functions = FxHashMap::default();
+
+    for (name, ty) in instance.module().parsed().exports() {
+        match (instance.export(name)?, ty) {
+            (Extern::Func(func), _) => {
+                if let Some(index) = function_index(&func) {
+                    functions.entry(index).or_insert(func);
+                }
+            },
+            (Extern::Table(table), ExternType::Table(ty)) if ty.element() == ValueType::FuncRef => {
+                for index in 0..table.size(store.as_context()) {
+                    if let Some(Value::FuncRef(Some(func))) =
+                        table.get(store.as_context_mut(), index)
+                    {
+                        if let Some(index) = function_index(&func) {
+                            functions.entry(index).or_insert(func);
+                        }
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    Ok(functions)
+}
+
+/// Returns the index of the WebAssembly function `func` in the function index
+/// space of its instance
+///
+/// The JavaScript API names every exported WebAssembly function by its index.
+fn function_index(func: &Func) -> Option<u32> {
+    Python::with_gil(|py| {
+        let func = func.to_py(py);
+        let name: String = func
+            .bind(py)
+            .getattr(intern!(py, "name"))
+            .ok()?
+            .extract()
+            .ok()?;
+        name.parse().ok()
+    })
+}
+
+/// Returns the length in bytes of a memory with the given number of `pages`,
+/// if it fits into the address space
+fn memory_len(pages: u32) -> Option<usize> {
+    usize::try_from(pages).ok()?.checked_mul(PAGE_SIZE)
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize) {
+    write_u64(bytes, len as u64);
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    write_len(bytes, value.len());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+/// Reader over the bytes of a serialized [`Snapshot`]
+struct Reader<'a> {
+    /// The remaining bytes
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotFormatError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotFormatError::UnexpectedEnd);
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotFormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotFormatError> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotFormatError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotFormatError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn len(&mut self) -> Result<usize, SnapshotFormatError> {
+        let len = usize::try_from(self.u64()?).map_err(|_| SnapshotFormatError::Malformed)?;
+
+        // every element takes at least one byte, which bounds pre-allocations
+        if len > self.bytes.len() {
+            return Err(SnapshotFormatError::UnexpectedEnd);
+        }
+
+        Ok(len)
+    }
+
+    fn str(&mut self) -> Result<String, SnapshotFormatError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotFormatError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::backend::{Imports, WasmGlobal, WasmInstance, WasmMemory, WasmTable};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn serialization_roundtrip() {
+        let snapshot = Snapshot {
+            module: 42,
+            memories: vec![MemorySnapshot {
+                name: String::from("memory"),
+                pages: 1,
+                bytes: (0..PAGE_SIZE).map(|i| (i % 251) as u8).collect(),
+            }],
+            globals: vec![
+                GlobalSnapshot {
+                    name: String::from("counter"),
+                    value: GlobalValue::I64(-1),
+                },
+                GlobalSnapshot {
+                    name: String::from("nan"),
+                    value: GlobalValue::F32(0x7FA0_0001),
+                },
+            ],
+            tables: vec![TableSnapshot {
+                name: String::from("table"),
+                elements: vec![None, Some(3), Some(0)],
+            }],
+        };
+
+        let bytes = snapshot.to_bytes();
+
+        assert_eq!(Snapshot::from_bytes(&bytes).as_ref(), Ok(&snapshot));
+
+        assert_eq!(
+            Snapshot::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(SnapshotFormatError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Snapshot::from_bytes(b"not a snapshot"),
+            Err(SnapshotFormatError::InvalidMagic)
+        );
+
+        // the memory contents must match the number of pages
+        let mut malformed = snapshot;
+        malformed.memories[0].pages = 2;
+        assert_eq!(
+            Snapshot::from_bytes(&malformed.to_bytes()),
+            Err(SnapshotFormatError::Malformed)
+        );
+    }
+
+    #[test]
+    fn capture_and_restore() {
+        const MODULE: &str = r#"
+            (module
+                (memory (export "memory") 1 3)
+                (global (export "counter") (mut i64) (i64.const 0))
+                (global (export "scale") (mut f32) (f32.const 1))
+                (table (export "table") 2 funcref)
+                (elem (i32.const 0) $one)
+                (func $one (export "one") (result i32) (i32.const 1))
+                (func $two (export "two") (result i32) (i32.const 2))
+                (func (export "call") (param i32) (result i32)
+                    (call_indirect (result i32) (local.get 0)))
+            )
+        "#;
+
+        let mut store = test_utils::store();
+
+        let instance = test_utils::instantiate(&mut store, MODULE, &Imports::default()).unwrap();
+        let export = |name| instance.export(name).unwrap();
+        let (
+            Extern::Memory(memory),
+            Extern::Global(counter),
+            Extern::Global(scale),
+            Extern::Table(table),
+        ) = (
+            export("memory"),
+            export("counter"),
+            export("scale"),
+            export("table"),
+        )
+        else {
+            panic!("unexpected exports");
+        };
+
+        memory.grow(&mut store, 1).unwrap();
+        memory.write(&mut store, 0x1_FFFC, b"wasm").unwrap();
+        counter.set(&mut store, Value::I64(-42)).unwrap();
+        scale
+            .set(&mut store, Value::F32(f32::from_bits(0x7FA0_0001)))
+            .unwrap();
+        let two = test_utils::export_func(&store, &instance, "two");
+        table
+            .grow(&mut store, 1, Value::FuncRef(Some(two)))
+            .unwrap();
+
+        let snapshot = instance.snapshot(&mut store).unwrap();
+        let snapshot = Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+
+        // the snapshot is restored into a fresh instance of the same module
+        let restored = test_utils::instantiate(&mut store, MODULE, &Imports::default()).unwrap();
+        restored.restore(&mut store, &snapshot).unwrap();
+
+        let export = |name| restored.export(name).unwrap();
+        let (
+            Extern::Memory(memory),
+            Extern::Global(counter),
+            Extern::Global(scale),
+            Extern::Table(table),
+        ) = (
+            export("memory"),
+            export("counter"),
+            export("scale"),
+            export("table"),
+        )
+        else {
+            panic!("unexpected exports");
+        };
+
+        assert_eq!(memory.current_pages(&store), 2);
+        let mut buffer = [0; 4];
+        memory.read(&store, 0x1_FFFC, &mut buffer).unwrap();
+        assert_eq!(&buffer, b"wasm");
+        assert!(matches!(counter.get(&mut store), Value::I64(-42)));
+        assert!(matches!(scale.get(&mut store), Value::F32(x) if x.is_nan()));
+        assert_eq!(table.size(&store), 3);
+
+        // the table elements refer to the functions of the restored instance
+        let call = test_utils::export_func(&store, &restored, "call")
+            .typed::<i32, i32>(&store)
+            .unwrap();
+        assert_eq!(call.call(&mut store, 0).unwrap(), 1);
+        assert_eq!(call.call(&mut store, 2).unwrap(), 2);
+        assert!(call.call(&mut store, 1).is_err());
+
+        // a snapshot cannot be restored into an instance of another module
+        let other = test_utils::instantiate(&mut store, "(module)", &Imports::default()).unwrap();
+        assert!(other.restore(&mut store, &snapshot).is_err());
+    }
+
+    #[test]
+    fn modules_without_bytes() {
+        const MODULE: &str = r#"(module (global (export "counter") (mut i32) (i32.const 0)))"#;
+
+        let mut store = test_utils::store();
+
+        let instance = test_utils::instantiate(&mut store, MODULE, &Imports::default()).unwrap();
+        let snapshot = instance.snapshot(&mut store).unwrap();
+
+        // a module with the same exports but unknown bytes cannot be told
+        // apart from the module that the snapshot was taken from
+        let module = Python::with_gil(|py| {
+            let bytes = wat::parse_str(MODULE).unwrap();
+            let buffer = crate::js::uint8_array_new(py)
+                .unwrap()
+                .call1((bytes.as_slice(),))
+                .unwrap();
+            let module = crate::js::web_assembly_module_new(py)
+                .unwrap()
+                .call1((buffer,))
+                .unwrap();
+            crate::Module::from_js_module(&store.engine().clone().into_backend(), &module, None)
+                .unwrap()
+        });
+        let wrapped = Instance::new(&mut store, &module, &Imports::default()).unwrap();
+
+        let err = wrapped.restore(&mut store, &snapshot).unwrap_err();
+        assert!(err.to_string().contains("without its bytes"), "{err:?}");
+        let err = wrapped.snapshot(&mut store).unwrap_err();
+        assert!(err.to_string().contains("without its bytes"), "{err:?}");
+    }
+}
@@ -4,12 +4,17 @@ use std::{
     sync::{Arc, Weak},
 };
 
+use pyo3::{exceptions::PyRecursionError, intern, prelude::*};
 use wasm_runtime_layer::backend::{
-    AsContext, AsContextMut, WasmStore, WasmStoreContext, WasmStoreContextMut,
+    AsContext, AsContextMut, Value, WasmStore, WasmStoreContext, WasmStoreContextMut,
 };
 use wobbly::sync::Wobbly;
 
-use crate::{func::PyHostFuncFn, Engine};
+use crate::{
+    func::PyHostFuncFn,
+    limits::{ResourceLimitError, ResourceLimiter},
+    CallFrame, Engine, Global, StackOverflowError, Trap,
+};
 
 /// A store for the [`Engine`], which stores host-defined data `T` and internal
 /// state.
@@ -66,6 +71,126 @@ struct StoreInner<T> {
     /// The user host functions, which must live in Rust and not JS to avoid a
     /// cross-language reference cycle
     host_funcs: Vec<Wobbly<PyHostFuncFn>>,
+    /// The optional resource limiter
+    limiter: Option<Box<dyn ResourceLimiter>>,
+    /// The number of instances that have been created in this store
+    instances: usize,
+    /// The number of memories that have been created in this store
+    memories: usize,
+    /// The number of tables that have been created in this store
+    tables: usize,
+    /// The fuel counter global, which is lazily created
+    fuel: Option<Global>,
+    /// The epoch deadline global, which is lazily created
+    epoch_deadline: Option<Global>,
+    /// The stack of nested guest and host calls
+    call_stack: Vec<CallFrame>,
+    /// The stack overflow that is currently unwinding, if any
+    stack_overflow: Option<StackOverflowError>,
+}
+
+impl<T> StoreInner<T> {
+    /// Returns the fuel counter global, which is created on first use
+    fn fuel_global(&mut self, py: Python) -> anyhow::Result<&Global> {
+        if !self.engine.config().consumes_fuel() {
+            anyhow::bail!("fuel consumption is not enabled in the engine's config");
+        }
+
+        if self.fuel.is_none() {
+            self.fuel = Some(Global::new_with_value(py, &Value::I64(0), true)?);
+        }
+
+        let Some(fuel) = &self.fuel else {
+            unreachable!("the fuel global has just been initialised")
+        };
+
+        Ok(fuel)
+    }
+
+    /// Returns the raw value of the fuel counter, which is negative if the
+    /// fuel has been exhausted
+    fn raw_fuel(&self, py: Python) -> anyhow::Result<i64> {
+        if !self.engine.config().consumes_fuel() {
+            anyhow::bail!("fuel consumption is not enabled in the engine's config");
+        }
+
+        let Some(fuel) = &self.fuel else {
+            return Ok(0);
+        };
+
+        match fuel.get_value(py)? {
+            Value::I64(fuel) => Ok(fuel),
+            value => anyhow::bail!("fuel global has an invalid value {value:?}"),
+        }
+    }
+
+    fn get_fuel(&self) -> anyhow::Result<u64> {
+        Python::with_gil(|py| {
+            let fuel = self.raw_fuel(py)?;
+            Ok(u64::try_from(fuel).unwrap_or(0))
+        })
+    }
+
+    fn set_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        Python::with_gil(|py| {
+            let fuel = i64::try_from(fuel).unwrap_or(i64::MAX);
+            self.fuel_global(py)?.set_value(py, &Value::I64(fuel))?;
+            Ok(())
+        })
+    }
+
+    fn add_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        let fuel = self.get_fuel()?.saturating_add(fuel);
+        self.set_fuel(fuel)
+    }
+
+    /// Returns the epoch deadline global, which is created on first use
+    fn epoch_deadline_global(&mut self, py: Python) -> anyhow::Result<&Global> {
+        if !self.engine.config().epoch_interruption_enabled() {
+            anyhow::bail!("epoch interruption is not enabled in the engine's config");
+        }
+
+        if self.epoch_deadline.is_none() {
+            self.epoch_deadline = Some(Global::new_with_value(py, &Value::I64(0), true)?);
+        }
+
+        let Some(epoch_deadline) = &self.epoch_deadline else {
+            unreachable!("the epoch deadline global has just been initialised")
+        };
+
+        Ok(epoch_deadline)
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn set_epoch_deadline(&mut self, ticks_beyond_current: u64) -> anyhow::Result<()> {
+        let deadline = self
+            .engine
+            .current_epoch()?
+            .saturating_add(ticks_beyond_current);
+
+        Python::with_gil(|py| {
+            // the deadline is compared as an unsigned integer
+            self.epoch_deadline_global(py)?
+                .set_value(py, &Value::I64(deadline as i64))?;
+            Ok(())
+        })
+    }
+
+    /// Checks whether the engine's epoch has reached the store's deadline
+    fn epoch_deadline_reached(&self, py: Python) -> anyhow::Result<bool> {
+        let epoch = self.engine.epoch_global(py)?.get_value(py)?;
+
+        let deadline = match &self.epoch_deadline {
+            Some(deadline) => deadline.get_value(py)?,
+            None => Value::I64(0),
+        };
+
+        match (epoch, deadline) {
+            #[allow(clippy::cast_sign_loss)]
+            (Value::I64(epoch), Value::I64(deadline)) => Ok((epoch as u64) >= (deadline as u64)),
+            _ => anyhow::bail!("epoch globals have invalid values"),
+        }
+    }
 }
 
 impl<T> WasmStore<T, Engine> for Store<T> {
@@ -78,6 +203,14 @@ impl<T> WasmStore<T, Engine> for Store<T> {
                 engine: engine.clone(),
                 data,
                 host_funcs: Vec::new(),
+                limiter: None,
+                instances: 0,
+                memories: 0,
+                tables: 0,
+                fuel: None,
+                epoch_deadline: None,
+                call_stack: Vec::new(),
+                stack_overflow: None,
             })))),
             _marker: PhantomData::<T>,
         }
@@ -172,6 +305,67 @@ impl<T> Drop for Store<T> {
 }
 
 impl<T> Store<T> {
+    /// Installs a [`ResourceLimiter`] that limits the resources which can be
+    /// allocated in this store.
+    ///
+    /// The limiter replaces any previously installed limiter. Resources that
+    /// have already been allocated are not checked retroactively.
+    pub fn set_limiter(&mut self, limiter: impl 'static + ResourceLimiter) {
+        self.as_inner_mut().limiter = Some(Box::new(limiter));
+    }
+
+    /// Returns the remaining fuel in this store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled with
+    /// [`Config::consume_fuel`].
+    ///
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    pub fn get_fuel(&self) -> anyhow::Result<u64> {
+        self.as_inner().get_fuel()
+    }
+
+    /// Sets the remaining fuel in this store to `fuel`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled with
+    /// [`Config::consume_fuel`].
+    ///
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    pub fn set_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        self.as_inner_mut().set_fuel(fuel)
+    }
+
+    /// Adds `fuel` to the remaining fuel in this store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled with
+    /// [`Config::consume_fuel`].
+    ///
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    pub fn add_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        self.as_inner_mut().add_fuel(fuel)
+    }
+
+    /// Sets the epoch deadline of this store to `ticks_beyond_current` epochs
+    /// after the engine's current epoch.
+    ///
+    /// Guest code that is executing in this store is interrupted with
+    /// [`Trap::Interrupt`] once the engine's epoch reaches the deadline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if epoch interruption is not enabled with
+    /// [`Config::epoch_interruption`].
+    ///
+    /// [`Config::epoch_interruption`]: crate::Config::epoch_interruption
+    pub fn set_epoch_deadline(&mut self, ticks_beyond_current: u64) -> anyhow::Result<()> {
+        self.as_inner_mut().set_epoch_deadline(ticks_beyond_current)
+    }
+
     fn as_inner(&self) -> &StoreInner<T> {
         // Safety:
         //
@@ -207,6 +401,19 @@ pub struct StoreContextMut<'a, T: 'a> {
     proof: &'a mut Arc<StoreProof>,
 }
 
+impl<'a, T: 'a> StoreContext<'a, T> {
+    /// Returns the remaining fuel in this store.
+    ///
+    /// See [`Store::get_fuel`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled.
+    pub fn get_fuel(&self) -> anyhow::Result<u64> {
+        self.store.get_fuel()
+    }
+}
+
 impl<'a, T: 'a> StoreContextMut<'a, T> {
     #[allow(clippy::needless_pass_by_ref_mut)]
     /// Returns a weak proof for having a mutable borrow of the inner store
@@ -234,6 +441,237 @@ impl<'a, T: 'a> StoreContextMut<'a, T> {
         }
     }
 
+    /// Installs a [`ResourceLimiter`] that limits the resources which can be
+    /// allocated in this store.
+    ///
+    /// See [`Store::set_limiter`] for more details.
+    pub fn set_limiter(&mut self, limiter: impl 'static + ResourceLimiter) {
+        self.store.limiter = Some(Box::new(limiter));
+    }
+
+    /// Returns the remaining fuel in this store.
+    ///
+    /// See [`Store::get_fuel`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled.
+    pub fn get_fuel(&self) -> anyhow::Result<u64> {
+        self.store.get_fuel()
+    }
+
+    /// Sets the remaining fuel in this store to `fuel`.
+    ///
+    /// See [`Store::set_fuel`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled.
+    pub fn set_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        self.store.set_fuel(fuel)
+    }
+
+    /// Adds `fuel` to the remaining fuel in this store.
+    ///
+    /// See [`Store::add_fuel`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel consumption is not enabled.
+    pub fn add_fuel(&mut self, fuel: u64) -> anyhow::Result<()> {
+        self.store.add_fuel(fuel)
+    }
+
+    /// Sets the epoch deadline of this store to `ticks_beyond_current` epochs
+    /// after the engine's current epoch.
+    ///
+    /// See [`Store::set_epoch_deadline`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if epoch interruption is not enabled.
+    pub fn set_epoch_deadline(&mut self, ticks_beyond_current: u64) -> anyhow::Result<()> {
+        self.store.set_epoch_deadline(ticks_beyond_current)
+    }
+
+    #[must_use]
+    /// Returns the number of nested guest and host calls that are currently
+    /// executing in this store.
+    pub fn call_depth(&self) -> usize {
+        self.store.call_stack.len()
+    }
+
+    /// Returns the fuel counter global that is imported by instrumented
+    /// modules
+    pub(crate) fn fuel_global(&mut self, py: Python) -> anyhow::Result<Global> {
+        self.store.fuel_global(py).cloned()
+    }
+
+    /// Returns the epoch deadline global that is imported by instrumented
+    /// modules
+    pub(crate) fn epoch_deadline_global(&mut self, py: Python) -> anyhow::Result<Global> {
+        self.store.epoch_deadline_global(py).cloned()
+    }
+
+    /// Enters a nested guest or host call `frame`, which is exited once the
+    /// returned [`CallGuard`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Trap::StackOverflow`] error if the call would exceed the
+    /// maximum call depth, in which case the call is not entered.
+    pub(crate) fn enter_call(&mut self, frame: CallFrame) -> anyhow::Result<CallGuard<'_, 'a, T>> {
+        let limit = self.store.engine.config().call_depth_limit();
+
+        if matches!(limit, Some(limit) if self.store.call_stack.len() >= limit) {
+            let mut frames = self.store.call_stack.clone();
+            frames.push(frame);
+
+            let overflow = StackOverflowError::new(limit, frames);
+            self.store.stack_overflow = Some(overflow.clone());
+
+            return Err(anyhow::Error::new(overflow).context(Trap::StackOverflow));
+        }
+
+        // a stack overflow that is still recorded was caught by a host
+        // function, which is now making an unrelated call
+        self.store.stack_overflow = None;
+        self.store.call_stack.push(frame);
+
+        Ok(CallGuard {
+            store: self,
+            returned: false,
+        })
+    }
+
+    /// Annotates an `err`or that was raised by a guest call with the [`Trap`]
+    /// that caused it, if any.
+    ///
+    /// This method must be called before the guest call is exited, i.e.
+    /// while its [`CallGuard`] is still alive.
+    pub(crate) fn annotate_trap(&mut self, py: Python, err: PyErr) -> anyhow::Error {
+        if self.store.stack_overflow.is_none() && is_stack_overflow(py, &err) {
+            self.store.stack_overflow = Some(StackOverflowError::new(
+                self.store.engine.config().call_depth_limit(),
+                self.store.call_stack.clone(),
+            ));
+        }
+
+        let err = anyhow::Error::from(err);
+
+        if let Some(overflow) = &self.store.stack_overflow {
+            return err.context(overflow.clone()).context(Trap::StackOverflow);
+        }
+
+        if self.store.engine.config().consumes_fuel()
+            && matches!(self.store.raw_fuel(py), Ok(fuel) if fuel < 0)
+        {
+            return err.context(Trap::OutOfFuel);
+        }
+
+        if self.store.engine.config().epoch_interruption_enabled()
+            && matches!(self.store.epoch_deadline_reached(py), Ok(true))
+        {
+            return err.context(Trap::Interrupt);
+        }
+
+        err
+    }
+
+    /// Asks the resource limiter, if any, whether a memory may grow from
+    /// `current` to `desired` pages.
+    pub(crate) fn limit_memory_growth(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<()> {
+        if let Some(limiter) = &mut self.store.limiter {
+            if !limiter.memory_growing(current, desired, maximum)? {
+                anyhow::bail!(ResourceLimitError::MemoryGrowth { current, desired });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Asks the resource limiter, if any, whether a table may grow from
+    /// `current` to `desired` elements.
+    pub(crate) fn limit_table_growth(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<()> {
+        if let Some(limiter) = &mut self.store.limiter {
+            if !limiter.table_growing(current, desired, maximum)? {
+                anyhow::bail!(ResourceLimitError::TableGrowth { current, desired });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the maximum number of pages that a new host-created memory
+    /// with the given `maximum` may be grown to.
+    pub(crate) fn limit_memory_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        self.store
+            .limiter
+            .as_ref()
+            .map_or(maximum, |limiter| limiter.memory_maximum(maximum))
+    }
+
+    /// Returns the maximum number of elements that a new host-created table
+    /// with the given `maximum` may be grown to.
+    pub(crate) fn limit_table_maximum(&self, maximum: Option<u32>) -> Option<u32> {
+        self.store
+            .limiter
+            .as_ref()
+            .map_or(maximum, |limiter| limiter.table_maximum(maximum))
+    }
+
+    /// Checks whether `instances` new instances, `memories` new memories, and
+    /// `tables` new tables may be created in the store.
+    ///
+    /// The new resources are only accounted for once they have been created
+    /// successfully, using [`Self::count_new_resources`].
+    pub(crate) fn limit_new_resources(
+        &self,
+        instances: usize,
+        memories: usize,
+        tables: usize,
+    ) -> anyhow::Result<()> {
+        let Some(limiter) = &self.store.limiter else {
+            return Ok(());
+        };
+
+        if instances > 0 && self.store.instances.saturating_add(instances) > limiter.instances() {
+            anyhow::bail!(ResourceLimitError::Instances {
+                limit: limiter.instances()
+            });
+        }
+        if memories > 0 && self.store.memories.saturating_add(memories) > limiter.memories() {
+            anyhow::bail!(ResourceLimitError::Memories {
+                limit: limiter.memories()
+            });
+        }
+        if tables > 0 && self.store.tables.saturating_add(tables) > limiter.tables() {
+            anyhow::bail!(ResourceLimitError::Tables {
+                limit: limiter.tables()
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Accounts for the successful creation of `instances` new instances,
+    /// `memories` new memories, and `tables` new tables in the store.
+    pub(crate) fn count_new_resources(&mut self, instances: usize, memories: usize, tables: usize) {
+        self.store.instances = self.store.instances.saturating_add(instances);
+        self.store.memories = self.store.memories.saturating_add(memories);
+        self.store.tables = self.store.tables.saturating_add(tables);
+    }
+
     pub(crate) fn register_host_func(&mut self, func: Arc<PyHostFuncFn>) -> Wobbly<PyHostFuncFn> {
         let func = Wobbly::new(func);
         self.store.host_funcs.push(func.clone());
@@ -241,6 +679,54 @@ impl<'a, T: 'a> StoreContextMut<'a, T> {
     }
 }
 
+/// A nested guest or host call that has been entered in a store with
+/// [`StoreContextMut::enter_call`]
+///
+/// The call is exited when the guard is dropped, including when the call
+/// panics.
+#[allow(clippy::redundant_pub_crate)]
+pub(crate) struct CallGuard<'s, 'a, T> {
+    /// The store in which the call was entered
+    store: &'s mut StoreContextMut<'a, T>,
+    /// Whether the call has returned without an error
+    returned: bool,
+}
+
+impl<T> CallGuard<'_, '_, T> {
+    /// Exits the call, which has `returned` without an error or not
+    pub(crate) fn exit(mut self, returned: bool) {
+        self.returned = returned;
+    }
+}
+
+impl<'a, T> std::ops::Deref for CallGuard<'_, 'a, T> {
+    type Target = StoreContextMut<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.store
+    }
+}
+
+impl<T> std::ops::DerefMut for CallGuard<'_, '_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.store
+    }
+}
+
+impl<T> Drop for CallGuard<'_, '_, T> {
+    fn drop(&mut self) {
+        let store = &mut *self.store.store;
+        store.call_stack.pop();
+
+        // a call that returned has handled any stack overflow inside it, and
+        // an unhandled stack overflow has been fully unwound once the
+        // outermost call is exited
+        if self.returned || store.call_stack.is_empty() {
+            store.stack_overflow = None;
+        }
+    }
+}
+
 impl<'a, T: 'a> WasmStoreContext<'a, T, Engine> for StoreContext<'a, T> {
     fn engine(&self) -> &Engine {
         &self.store.engine
@@ -314,3 +800,284 @@ impl StoreProof {
         self.0.cast()
     }
 }
+
+/// Checks whether the error `err` was raised because the web browser's
+/// JavaScript engine or the Python interpreter ran out of stack space
+fn is_stack_overflow(py: Python, err: &PyErr) -> bool {
+    if err.is_instance_of::<PyRecursionError>(py) {
+        return true;
+    }
+
+    let err = err.value(py);
+
+    let (Ok(name), Ok(message)) = (
+        err.getattr(intern!(py, "name"))
+            .and_then(|name| name.extract::<String>()),
+        err.getattr(intern!(py, "message"))
+            .and_then(|message| message.extract::<String>()),
+    ) else {
+        return false;
+    };
+
+    // V8 and JavaScriptCore raise a RangeError, SpiderMonkey an InternalError
+    matches!(name.as_str(), "RangeError" | "InternalError")
+        && (message.contains("call stack") || message.contains("recursion"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use wasm_runtime_layer::{
+        backend::{Extern, Imports, WasmFunc},
+        FuncType, ValueType,
+    };
+
+    use super::*;
+    use crate::{test_utils, Config};
+
+    /// A guest that spins in a loop for the given number of iterations
+    const SPIN: &str = r#"
+        (module
+            (func (export "spin") (param $n i32)
+                (loop $continue
+                    (br_if $continue
+                        (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))))
+    "#;
+
+    #[test]
+    fn fuel() {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let mut store = test_utils::store_with(&config, ());
+
+        let instance = test_utils::instantiate(&mut store, SPIN, &Imports::default()).unwrap();
+        let spin = test_utils::export_func(&store, &instance, "spin")
+            .typed::<i32, ()>(&store)
+            .unwrap();
+
+        // a store starts without any fuel
+        assert_eq!(store.as_context_mut().get_fuel().unwrap(), 0);
+        let err = spin.call(&mut store, 1).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::OutOfFuel));
+
+        store.as_context_mut().set_fuel(1_000).unwrap();
+        store.as_context_mut().add_fuel(500).unwrap();
+        assert_eq!(store.as_context_mut().get_fuel().unwrap(), 1_500);
+
+        spin.call(&mut store, 10).unwrap();
+        let remaining = store.as_context_mut().get_fuel().unwrap();
+        assert!((1_000..1_500).contains(&remaining), "{remaining}");
+
+        // running out of fuel traps and leaves no fuel behind
+        let err = spin.call(&mut store, 1_000).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::OutOfFuel));
+        assert_eq!(store.as_context_mut().get_fuel().unwrap(), 0);
+
+        store.as_context_mut().add_fuel(remaining).unwrap();
+        spin.call(&mut store, 10).unwrap();
+    }
+
+    #[test]
+    fn epoch_interruption() {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let mut store = test_utils::store_with(&config, ());
+        let engine = store.engine().clone().into_backend();
+
+        // the host advances the epoch on every loop iteration of the guest
+        let tick = crate::Func::new(&mut store, FuncType::new([], []), {
+            let engine = engine.clone();
+            move |_caller, _args, _results| engine.increment_epoch()
+        });
+        let mut imports = Imports::default();
+        imports.define("env", "tick", Extern::Func(tick));
+
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (import "env" "tick" (func $tick))
+                (func (export "run") (param $n i32)
+                    (loop $continue
+                        (call $tick)
+                        (br_if $continue
+                            (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))))
+            "#,
+            &imports,
+        )
+        .unwrap();
+        let run = test_utils::export_func(&store, &instance, "run")
+            .typed::<i32, ()>(&store)
+            .unwrap();
+
+        // a store's deadline starts at zero, so the guest is interrupted immediately
+        let err = run.call(&mut store, 1).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::Interrupt));
+        assert_eq!(engine.current_epoch().unwrap(), 0);
+
+        store.as_context_mut().set_epoch_deadline(3).unwrap();
+        run.call(&mut store, 2).unwrap();
+        assert_eq!(engine.current_epoch().unwrap(), 2);
+
+        // the guest is interrupted at the next loop header after the deadline
+        let err = run.call(&mut store, 10).unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::Interrupt));
+        assert_eq!(engine.current_epoch().unwrap(), 3);
+
+        store.as_context_mut().set_epoch_deadline(1).unwrap();
+        run.call(&mut store, 1).unwrap();
+    }
+
+    /// Instantiates a guest whose `recurse` export calls back into itself
+    /// through the host `n` times, and whose `catch` export calls a host
+    /// function that catches a stack overflow and then either returns
+    /// (`0`) or calls the trapping `trap` export (`1`)
+    fn reentrant_instance(store: &mut test_utils::Store) -> crate::Instance {
+        let exports = Arc::new(OnceLock::<[crate::Func; 2]>::new());
+
+        let reenter = crate::Func::new(&mut *store, FuncType::new([ValueType::I32], []), {
+            let exports = exports.clone();
+            move |mut caller, args, _results| {
+                let [Value::I32(n)] = args else {
+                    anyhow::bail!("expected one i32 argument");
+                };
+                assert!(*n >= 0, "the host was asked to panic");
+                if *n > 0 {
+                    let [recurse, _] = exports.get().expect("the guest should be instantiated");
+                    recurse.call::<()>(caller.as_context_mut(), &[Value::I32(n - 1)], &mut [])?;
+                }
+                Ok(())
+            }
+        });
+
+        let catch = crate::Func::new(&mut *store, FuncType::new([ValueType::I32], []), {
+            let exports = exports.clone();
+            move |mut caller, args, _results| {
+                let [recurse, trap] = exports.get().expect("the guest should be instantiated");
+                let err = recurse
+                    .call::<()>(caller.as_context_mut(), &[Value::I32(100)], &mut [])
+                    .unwrap_err();
+                assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::StackOverflow));
+
+                match args {
+                    [Value::I32(0)] => Ok(()),
+                    _ => trap.call::<()>(caller.as_context_mut(), &[], &mut []),
+                }
+            }
+        });
+
+        let mut imports = Imports::default();
+        imports.define("env", "reenter", Extern::Func(reenter));
+        imports.define("env", "catch", Extern::Func(catch));
+
+        let instance = test_utils::instantiate(
+            store,
+            r#"
+            (module
+                (import "env" "reenter" (func $reenter (param i32)))
+                (import "env" "catch" (func $catch (param i32)))
+                (func (export "recurse") (param i32)
+                    (call $reenter (local.get 0)))
+                (func (export "trap")
+                    unreachable)
+                (func (export "catch") (param i32)
+                    (call $catch (local.get 0))
+                    unreachable)
+            )"#,
+            &imports,
+        )
+        .unwrap();
+
+        let _ = exports
+            .set(["recurse", "trap"].map(|name| test_utils::export_func(store, &instance, name)));
+
+        instance
+    }
+
+    #[test]
+    fn call_depth_limit() {
+        let mut config = Config::new();
+        config.max_call_depth(Some(4));
+        let mut store = test_utils::store_with(&config, ());
+
+        let instance = reentrant_instance(&mut store);
+        let recurse = test_utils::export_func(&store, &instance, "recurse");
+
+        // guest, host, guest, host
+        recurse
+            .call::<()>(&mut store, &[Value::I32(1)], &mut [])
+            .unwrap();
+
+        let err = recurse
+            .call::<()>(&mut store, &[Value::I32(2)], &mut [])
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), Some(&Trap::StackOverflow));
+        let overflow = err.downcast_ref::<StackOverflowError>().unwrap();
+        assert_eq!(overflow.limit(), Some(4));
+        assert_eq!(overflow.frames().len(), 5);
+        assert!(matches!(
+            overflow.frames(),
+            [CallFrame::Guest { name: Some(name), .. }, CallFrame::Host { .. }, ..]
+                if &**name == "recurse"
+        ));
+        assert_eq!(store.as_context_mut().call_depth(), 0);
+    }
+
+    #[test]
+    fn call_depth_after_panic() {
+        let mut config = Config::new();
+        config.max_call_depth(Some(4));
+        let mut store = test_utils::store_with(&config, ());
+
+        let instance = reentrant_instance(&mut store);
+        let recurse = test_utils::export_func(&store, &instance, "recurse");
+
+        // PyO3 prints the Python traceback of a panic when it resumes it, so
+        // the report is captured instead of cluttering the test output
+        let report = Python::with_gil(|py| {
+            let sys = py.import("sys").unwrap();
+            let stderr = sys.getattr("stderr").unwrap();
+            let report = py.import("io").unwrap().call_method0("StringIO").unwrap();
+            sys.setattr("stderr", &report).unwrap();
+
+            // a panicking host function still exits its call and the guest call
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                recurse.call::<()>(&mut store, &[Value::I32(-1)], &mut [])
+            }));
+
+            sys.setattr("stderr", stderr).unwrap();
+            assert!(result.is_err());
+
+            report
+                .call_method0("getvalue")
+                .unwrap()
+                .extract::<String>()
+                .unwrap()
+        });
+        assert!(report.contains("the host was asked to panic"), "{report}");
+        assert_eq!(store.as_context_mut().call_depth(), 0);
+    }
+
+    #[test]
+    fn caught_stack_overflow() {
+        let mut config = Config::new();
+        config.max_call_depth(Some(4));
+        let mut store = test_utils::store_with(&config, ());
+
+        let instance = reentrant_instance(&mut store);
+        let catch = test_utils::export_func(&store, &instance, "catch");
+
+        // the guest traps after the host has returned from the overflow
+        let err = catch
+            .call::<()>(&mut store, &[Value::I32(0)], &mut [])
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), None, "{err:?}");
+
+        // the host makes an unrelated call that traps after the overflow
+        let err = catch
+            .call::<()>(&mut store, &[Value::I32(1)], &mut [])
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Trap>(), None, "{err:?}");
+    }
+}
@@ -1,4 +1,4 @@
-use pyo3::{intern, prelude::*, sync::GILOnceCell};
+use pyo3::{intern, prelude::*};
 use wasm_runtime_layer::{
     backend::{AsContext, AsContextMut, Value, WasmTable},
     TableType, ValueType,
@@ -6,6 +6,9 @@ use wasm_runtime_layer::{
 
 use crate::{
     conversion::{create_js_object, instanceof, ToPy, ValueExt, ValueTypeExt},
+    js::{web_assembly_table, web_assembly_table_new},
+    reflection::{reflect_type, table_type_from_js},
+    store::StoreContextMut,
     Engine,
 };
 
@@ -33,7 +36,7 @@ impl Clone for Table {
 
 impl WasmTable<Engine> for Table {
     fn new(
-        _ctx: impl AsContextMut<Engine>,
+        mut ctx: impl AsContextMut<Engine>,
         ty: TableType,
         init: Value<Engine>,
     ) -> anyhow::Result<Self> {
@@ -41,6 +44,16 @@ impl WasmTable<Engine> for Table {
             #[cfg(feature = "tracing")]
             tracing::debug!(?ty, ?init, "Table::new");
 
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+
+            store.limit_new_resources(0, 0, 1)?;
+            store.limit_table_growth(0, ty.minimum(), ty.maximum())?;
+            let ty = TableType::new(
+                ty.element(),
+                ty.minimum(),
+                store.limit_table_maximum(ty.maximum()),
+            );
+
             let desc = create_js_object(py)?;
             desc.setattr(intern!(py, "element"), ty.element().as_js_descriptor())?;
             desc.setattr(intern!(py, "initial"), ty.minimum())?;
@@ -51,6 +64,7 @@ impl WasmTable<Engine> for Table {
             let init = init.to_py(py);
 
             let table = web_assembly_table_new(py)?.call1((desc, init))?;
+            store.count_new_resources(0, 0, 1);
 
             Ok(Self {
                 table: table.unbind(),
@@ -80,16 +94,25 @@ impl WasmTable<Engine> for Table {
     /// Grows the table by the given amount of elements.
     fn grow(
         &self,
-        _ctx: impl AsContextMut<Engine>,
+        mut ctx: impl AsContextMut<Engine>,
         delta: u32,
         init: Value<Engine>,
     ) -> anyhow::Result<u32> {
+        let current = self.size(ctx.as_context());
+
         Python::with_gil(|py| {
             let table = self.table.bind(py);
 
             #[cfg(feature = "tracing")]
             tracing::debug!(table = %table, ?self.ty, delta, ?init, "Table::grow");
 
+            let Some(desired) = current.checked_add(delta) else {
+                anyhow::bail!("cannot grow a table of {current} elements by {delta} elements");
+            };
+
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+            store.limit_table_growth(current, desired, self.ty.maximum())?;
+
             let init = init.to_py(py);
 
             let old_len = table
@@ -148,7 +171,53 @@ impl ToPy for Table {
 }
 
 impl Table {
-    /// Creates a new table from a Python value
+    /// Wraps an existing JavaScript [`WebAssembly.Table`] with the given type
+    /// `ty`, or with the type that is inferred using JS Type Reflection if
+    /// `ty` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `table` is not a [`WebAssembly.Table`], if it is
+    /// smaller than the minimum size of `ty`, if `ty` is not a `funcref`
+    /// table, if its maximum size exceeds that of `ty` where JS Type
+    /// Reflection is supported, or if `ty` is `None` and the type cannot be
+    /// inferred since the web browser does not support JS Type Reflection.
+    ///
+    /// [`WebAssembly.Table`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Table
+    pub fn from_js(table: &Bound<PyAny>, ty: Option<TableType>) -> anyhow::Result<Self> {
+        if !instanceof(table, web_assembly_table(table.py())?)? {
+            anyhow::bail!("expected WebAssembly.Table but found {table}");
+        }
+
+        let ty = reflect_type(table, ty, table_type_from_js)?;
+
+        if ty.element() != ValueType::FuncRef {
+            anyhow::bail!(
+                "only funcref tables are supported, but found {}",
+                ty.element()
+            );
+        }
+
+        let length: u32 = table.getattr(intern!(table.py(), "length"))?.extract()?;
+        if length < ty.minimum() {
+            anyhow::bail!(
+                "{table} has {length} elements but at least {} are required",
+                ty.minimum()
+            );
+        }
+
+        Self::from_exported_table(table.clone(), ty)
+    }
+
+    #[must_use]
+    /// Returns the underlying JavaScript [`WebAssembly.Table`].
+    ///
+    /// [`WebAssembly.Table`]: https://developer.mozilla.org/en-US/docs/WebAssembly/JavaScript_interface/Table
+    pub fn as_js<'py>(&self, py: Python<'py>) -> &Bound<'py, PyAny> {
+        self.table.bind(py)
+    }
+
+    /// Creates a new table of the trusted type `ty` from a Python value
     pub(crate) fn from_exported_table(table: Bound<PyAny>, ty: TableType) -> anyhow::Result<Self> {
         if !instanceof(&table, web_assembly_table(table.py())?)? {
             anyhow::bail!("expected WebAssembly.Table but found {table}");
@@ -169,12 +238,50 @@ impl Table {
     }
 }
 
-fn web_assembly_table(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_TABLE: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_TABLE.import(py, "js.WebAssembly", "Table")
-}
-
-fn web_assembly_table_new(py: Python) -> Result<&Bound<PyAny>, PyErr> {
-    static WEB_ASSEMBLY_TABLE_NEW: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
-    WEB_ASSEMBLY_TABLE_NEW.import(py, "js.WebAssembly.Table", "new")
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::Value;
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn externref_elements() {
+        let mut store = test_utils::store();
+
+        let table = wasm_runtime_layer::Table::new(
+            &mut store,
+            TableType::new(ValueType::ExternRef, 2, Some(3)),
+            Value::ExternRef(None),
+        )
+        .unwrap();
+
+        let answer = wasm_runtime_layer::ExternRef::new(&mut store, 42_u32);
+        table
+            .set(&mut store, 1, Value::ExternRef(Some(answer)))
+            .unwrap();
+        assert!(table.set(&mut store, 2, Value::ExternRef(None)).is_err());
+
+        assert_eq!(
+            table.grow(&mut store, 1, Value::ExternRef(None)).unwrap(),
+            2
+        );
+        assert!(table.grow(&mut store, 1, Value::ExternRef(None)).is_err());
+        assert_eq!(table.size(&store), 3);
+
+        assert!(matches!(
+            table.get(&mut store, 0),
+            Some(Value::ExternRef(None))
+        ));
+        let Some(Value::ExternRef(Some(answer))) = table.get(&mut store, 1) else {
+            panic!("expected a non-null externref");
+        };
+        assert_eq!(
+            answer
+                .downcast::<u32, _, _>(wasm_runtime_layer::AsContext::as_context(&store))
+                .ok(),
+            Some(&42)
+        );
+        assert!(table.get(&mut store, 3).is_none());
+    }
 }
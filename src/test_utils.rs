@@ -0,0 +1,53 @@
+//! Shared helpers for the unit tests, which run against the native mock of
+//! the JavaScript APIs
+
+use wasm_runtime_layer::backend::{Extern, Imports, WasmInstance, WasmModule};
+
+use crate::{Config, Engine, Func, Instance, Module};
+
+/// The [`wasm_runtime_layer::Store`] of this backend
+pub type Store<T = ()> = wasm_runtime_layer::Store<T, Engine>;
+
+/// Installs the mock JavaScript APIs and creates a new store with the default
+/// configuration
+pub fn store() -> Store {
+    store_with(&Config::default(), ())
+}
+
+/// Installs the mock JavaScript APIs and creates a new store with the given
+/// `config`uration and user `data`
+pub fn store_with<T>(config: &Config, data: T) -> Store<T> {
+    crate::js::mock::install();
+
+    Store::new(&wasm_runtime_layer::Engine::new(Engine::new(config)), data)
+}
+
+/// Compiles and instantiates the WebAssembly text format `wat` with the
+/// `imports`
+///
+/// # Panics
+///
+/// Panics if `wat` cannot be parsed or compiled.
+pub fn instantiate<T>(
+    store: &mut Store<T>,
+    wat: &str,
+    imports: &Imports<Engine>,
+) -> anyhow::Result<Instance> {
+    let bytes = wat::parse_str(wat).expect("the test module should be valid");
+    let module = Module::new(&store.engine().clone().into_backend(), bytes.as_slice())
+        .expect("the test module should compile");
+
+    Instance::new(store, &module, imports)
+}
+
+/// Returns the function export `name` of the `instance`
+///
+/// # Panics
+///
+/// Panics if the `instance` has no such function export.
+pub fn export_func<T>(store: &Store<T>, instance: &Instance, name: &str) -> Func {
+    match instance.get_export(store, name) {
+        Some(Extern::Func(func)) => func,
+        _ => panic!("missing function export {name:?}"),
+    }
+}
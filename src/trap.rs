@@ -0,0 +1,151 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use wasm_runtime_layer::FuncType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+/// A trap that was raised by this crate's instrumentation of a WASM guest.
+///
+/// Traps are attached to the error returned by [`Func::call`] and can be
+/// inspected with [`anyhow::Error::downcast_ref`].
+///
+/// [`Func::call`]: https://docs.rs/wasm_runtime_layer/0.4/wasm_runtime_layer/struct.Func.html#method.call
+pub enum Trap {
+    /// The guest ran out of fuel, see [`Config::consume_fuel`]
+    ///
+    /// [`Config::consume_fuel`]: crate::Config::consume_fuel
+    OutOfFuel,
+    /// The guest was interrupted because the engine's epoch reached the
+    /// store's deadline, see [`Config::epoch_interruption`]
+    ///
+    /// [`Config::epoch_interruption`]: crate::Config::epoch_interruption
+    Interrupt,
+    /// The nested guest and host calls exhausted the call stack, see
+    /// [`Config::max_call_depth`]
+    ///
+    /// The error additionally contains a [`StackOverflowError`] that describes
+    /// the chain of calls.
+    ///
+    /// [`Config::max_call_depth`]: crate::Config::max_call_depth
+    StackOverflow,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfFuel => fmt.write_str("all fuel consumed by WebAssembly"),
+            Self::Interrupt => fmt.write_str("interrupted WebAssembly at an epoch deadline"),
+            Self::StackOverflow => fmt.write_str("call stack exhausted"),
+        }
+    }
+}
+
+impl Error for Trap {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A frame on a [`Store`]'s stack of nested guest and host calls.
+///
+/// [`Store`]: crate::Store
+pub enum CallFrame {
+    /// A call from the host into a guest function
+    Guest {
+        /// The export name of the guest function, if known
+        name: Option<Arc<str>>,
+        /// The type of the guest function
+        ty: FuncType,
+    },
+    /// A call from a guest into a host function
+    Host {
+        /// The type of the host function
+        ty: FuncType,
+    },
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Guest {
+                name: Some(name),
+                ty,
+            } => write!(fmt, "guest `{name}` {ty}"),
+            Self::Guest { name: None, ty } => write!(fmt, "guest {ty}"),
+            Self::Host { ty } => write!(fmt, "host {ty}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error that describes the chain of nested calls which exhausted the call
+/// stack, see [`Trap::StackOverflow`].
+pub struct StackOverflowError {
+    /// The maximum call depth that was configured, if any
+    limit: Option<usize>,
+    /// The chain of calls, starting with the outermost call
+    frames: Vec<CallFrame>,
+}
+
+impl StackOverflowError {
+    /// The number of outermost and innermost frames that are displayed
+    const DISPLAYED_FRAMES: usize = 8;
+
+    pub(crate) const fn new(limit: Option<usize>, frames: Vec<CallFrame>) -> Self {
+        Self { limit, frames }
+    }
+
+    #[must_use]
+    /// Returns the maximum call depth that was configured, if any.
+    ///
+    /// If there is no limit, the call stack was exhausted by the web
+    /// browser's JavaScript engine or the Python interpreter.
+    pub const fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    #[must_use]
+    /// Returns the chain of nested calls, starting with the outermost call.
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+}
+
+impl fmt::Display for StackOverflowError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.limit {
+            Some(limit) => write!(
+                fmt,
+                "the call depth limit of {limit} was exceeded by {} nested calls",
+                self.frames.len()
+            )?,
+            None => write!(
+                fmt,
+                "the call stack was exhausted after {} nested calls",
+                self.frames.len()
+            )?,
+        }
+
+        if self.frames.len() <= 2 * Self::DISPLAYED_FRAMES {
+            for frame in &self.frames {
+                write!(fmt, "\n  {frame}")?;
+            }
+        } else {
+            let (outer, rest) = self.frames.split_at(Self::DISPLAYED_FRAMES);
+            let inner = &rest[rest.len() - Self::DISPLAYED_FRAMES..];
+
+            for frame in outer {
+                write!(fmt, "\n  {frame}")?;
+            }
+            write!(
+                fmt,
+                "\n  ... {} more calls ...",
+                rest.len() - Self::DISPLAYED_FRAMES
+            )?;
+            for frame in inner {
+                write!(fmt, "\n  {frame}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for StackOverflowError {}
@@ -0,0 +1,488 @@
+use std::{convert::Infallible, fmt, marker::PhantomData};
+
+use pyo3::{
+    prelude::*,
+    types::{PyIterator, PyTuple},
+};
+use wasm_runtime_layer::{backend::AsContextMut, FuncType, ValueType};
+
+use crate::{conversion::try_i64_from_js_bigint, float_bits, store::StoreContextMut, Engine, Func};
+
+/// A [`Func`] with the statically known parameter types `Params` and result
+/// types `Results`, which is created with [`Func::typed`].
+///
+/// The function's type is only checked once when it is created. Calls then
+/// convert the native Rust parameters and results directly from and to
+/// JavaScript values, without going through the dynamically typed
+/// [`Value`]s of [`WasmFunc::call`].
+///
+/// [`Value`]: wasm_runtime_layer::backend::Value
+/// [`WasmFunc::call`]: wasm_runtime_layer::backend::WasmFunc::call
+pub struct TypedFunc<Params, Results> {
+    /// The untyped function
+    func: Func,
+    /// Whether the bits of floats are passed to and from the function
+    preserve_float_bits: bool,
+    /// The parameter and result types
+    _marker: PhantomData<fn(Params) -> Results>,
+}
+
+impl<Params, Results> Clone for TypedFunc<Params, Results> {
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+            preserve_float_bits: self.preserve_float_bits,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Params, Results> fmt::Debug for TypedFunc<Params, Results> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TypedFunc")
+            .field("func", &self.func)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Params: WasmParams, Results: WasmResults> TypedFunc<Params, Results> {
+    /// Creates a typed function from the `func`, whose type must match the
+    /// `Params` and `Results`
+    pub(crate) fn new(func: Func, engine: &Engine) -> anyhow::Result<Self> {
+        let ty = func.func_type();
+
+        if ty.params() != Params::TYPES || ty.results() != Results::TYPES {
+            anyhow::bail!(
+                "function has type {ty:?} but {:?} was requested",
+                FuncType::new(
+                    Params::TYPES.iter().copied(),
+                    Results::TYPES.iter().copied()
+                )
+            );
+        }
+
+        let preserve_float_bits =
+            engine.config().preserves_float_bits() && float_bits::has_floats(ty);
+
+        Ok(Self {
+            func,
+            preserve_float_bits,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Calls the function with the `params` and returns its results.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the function fails or traps.
+    pub fn call(
+        &self,
+        mut ctx: impl AsContextMut<Engine>,
+        params: Params,
+    ) -> anyhow::Result<Results> {
+        Python::with_gil(|py| {
+            let mut store: StoreContextMut<_> = ctx.as_context_mut();
+
+            self.func.assert_user_state(&store);
+
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("call_guest_typed", ty = ?self.func.func_type()).entered();
+
+            let func = self.func.caller(py, self.preserve_float_bits)?;
+            let args = params.to_py_args(py, self.preserve_float_bits)?;
+
+            let results = self.func.call_in_frame(&mut store, &func, args)?;
+
+            Ok(Results::from_py_results(results, self.preserve_float_bits)?)
+        })
+    }
+
+    #[must_use]
+    /// Returns the untyped function.
+    pub const fn func(&self) -> &Func {
+        &self.func
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A WebAssembly number type that can be passed to and from a [`TypedFunc`],
+/// i.e. `i32`, `i64`, `f32`, or `f64`.
+pub trait WasmTy: sealed::Sealed + Sized {
+    /// The WebAssembly value type
+    const TYPE: ValueType;
+
+    #[doc(hidden)]
+    /// Converts this value into a JavaScript value, or the bits of a float if
+    /// `preserve_float_bits` is set
+    fn to_py(self, py: Python, preserve_float_bits: bool) -> Py<PyAny>;
+
+    #[doc(hidden)]
+    /// Converts the JavaScript `value`, or the bits of a float if
+    /// `preserve_float_bits` is set, into a value of this type
+    fn from_py(value: Bound<PyAny>, preserve_float_bits: bool) -> Result<Self, PyErr>;
+}
+
+impl sealed::Sealed for i32 {}
+
+impl WasmTy for i32 {
+    const TYPE: ValueType = ValueType::I32;
+
+    fn to_py(self, py: Python, _preserve_float_bits: bool) -> Py<PyAny> {
+        into_py_any(py, self)
+    }
+
+    fn from_py(value: Bound<PyAny>, _preserve_float_bits: bool) -> Result<Self, PyErr> {
+        value.extract()
+    }
+}
+
+impl sealed::Sealed for i64 {}
+
+impl WasmTy for i64 {
+    const TYPE: ValueType = ValueType::I64;
+
+    fn to_py(self, py: Python, _preserve_float_bits: bool) -> Py<PyAny> {
+        // the caller converts the unwrapped int into a BigInt in JavaScript
+        into_py_any(py, self)
+    }
+
+    fn from_py(value: Bound<PyAny>, _preserve_float_bits: bool) -> Result<Self, PyErr> {
+        try_i64_from_js_bigint(value)
+    }
+}
+
+impl sealed::Sealed for f32 {}
+
+impl WasmTy for f32 {
+    const TYPE: ValueType = ValueType::F32;
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn to_py(self, py: Python, preserve_float_bits: bool) -> Py<PyAny> {
+        if preserve_float_bits {
+            into_py_any(py, self.to_bits() as i32)
+        } else {
+            into_py_any(py, self)
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn from_py(value: Bound<PyAny>, preserve_float_bits: bool) -> Result<Self, PyErr> {
+        if preserve_float_bits {
+            Ok(Self::from_bits(value.extract::<i32>()? as u32))
+        } else {
+            value.extract()
+        }
+    }
+}
+
+impl sealed::Sealed for f64 {}
+
+impl WasmTy for f64 {
+    const TYPE: ValueType = ValueType::F64;
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn to_py(self, py: Python, preserve_float_bits: bool) -> Py<PyAny> {
+        if preserve_float_bits {
+            into_py_any(py, self.to_bits() as i64)
+        } else {
+            into_py_any(py, self)
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    fn from_py(value: Bound<PyAny>, preserve_float_bits: bool) -> Result<Self, PyErr> {
+        if preserve_float_bits {
+            Ok(Self::from_bits(try_i64_from_js_bigint(value)? as u64))
+        } else {
+            value.extract()
+        }
+    }
+}
+
+/// The parameters of a [`TypedFunc`], which are either a single [`WasmTy`]
+/// or a tuple of them.
+pub trait WasmParams: sealed::Sealed {
+    /// The WebAssembly value types of the parameters
+    const TYPES: &'static [ValueType];
+
+    #[doc(hidden)]
+    /// Converts the parameters into the arguments of a JavaScript call
+    fn to_py_args(self, py: Python, preserve_float_bits: bool) -> Result<Bound<PyTuple>, PyErr>;
+}
+
+/// The results of a [`TypedFunc`], which are either a single [`WasmTy`] or a
+/// tuple of them.
+pub trait WasmResults: sealed::Sealed + Sized {
+    /// The WebAssembly value types of the results
+    const TYPES: &'static [ValueType];
+
+    #[doc(hidden)]
+    /// Converts the result of a JavaScript call into the results, which is
+    /// an array if there are multiple
+    fn from_py_results(results: Bound<PyAny>, preserve_float_bits: bool) -> Result<Self, PyErr>;
+}
+
+impl<T: WasmTy> WasmParams for T {
+    const TYPES: &'static [ValueType] = &[T::TYPE];
+
+    fn to_py_args(self, py: Python, preserve_float_bits: bool) -> Result<Bound<PyTuple>, PyErr> {
+        PyTuple::new(py, [self.to_py(py, preserve_float_bits)])
+    }
+}
+
+impl<T: WasmTy> WasmResults for T {
+    const TYPES: &'static [ValueType] = &[T::TYPE];
+
+    fn from_py_results(results: Bound<PyAny>, preserve_float_bits: bool) -> Result<Self, PyErr> {
+        T::from_py(results, preserve_float_bits)
+    }
+}
+
+impl sealed::Sealed for () {}
+
+impl WasmParams for () {
+    const TYPES: &'static [ValueType] = &[];
+
+    fn to_py_args(self, py: Python, _preserve_float_bits: bool) -> Result<Bound<PyTuple>, PyErr> {
+        Ok(PyTuple::empty(py))
+    }
+}
+
+impl WasmResults for () {
+    const TYPES: &'static [ValueType] = &[];
+
+    fn from_py_results(_results: Bound<PyAny>, _preserve_float_bits: bool) -> Result<Self, PyErr> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_wasm_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: WasmTy),+> sealed::Sealed for ($($t,)+) {}
+
+        impl<$($t: WasmTy),+> WasmParams for ($($t,)+) {
+            const TYPES: &'static [ValueType] = &[$($t::TYPE),+];
+
+            #[allow(non_snake_case)]
+            fn to_py_args(
+                self,
+                py: Python,
+                preserve_float_bits: bool,
+            ) -> Result<Bound<PyTuple>, PyErr> {
+                let ($($t,)+) = self;
+                PyTuple::new(py, [$($t.to_py(py, preserve_float_bits)),+])
+            }
+        }
+
+        impl<$($t: WasmTy),+> WasmResults for ($($t,)+) {
+            const TYPES: &'static [ValueType] = &[$($t::TYPE),+];
+
+            fn from_py_results(
+                results: Bound<PyAny>,
+                preserve_float_bits: bool,
+            ) -> Result<Self, PyErr> {
+                let mut results = UnpackResults::new(results, <Self as WasmResults>::TYPES.len())?;
+                let values = ($($t::from_py(results.next()?, preserve_float_bits)?,)+);
+                results.finish()?;
+                Ok(values)
+            }
+        }
+    };
+}
+
+impl_wasm_tuple!(A);
+impl_wasm_tuple!(A, B);
+impl_wasm_tuple!(A, B, C);
+impl_wasm_tuple!(A, B, C, D);
+impl_wasm_tuple!(A, B, C, D, E);
+impl_wasm_tuple!(A, B, C, D, E, F);
+impl_wasm_tuple!(A, B, C, D, E, F, G);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H, I);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Unpacks the result of a JavaScript call, which is the single result
+/// itself or an array of multiple results
+enum UnpackResults<'py> {
+    /// The single result, until it has been taken
+    Single(Option<Bound<'py, PyAny>>),
+    /// An iterator over the array of results
+    Multiple(Bound<'py, PyIterator>),
+}
+
+impl<'py> UnpackResults<'py> {
+    fn new(results: Bound<'py, PyAny>, len: usize) -> Result<Self, PyErr> {
+        if len == 1 {
+            Ok(Self::Single(Some(results)))
+        } else {
+            Ok(Self::Multiple(results.try_iter()?))
+        }
+    }
+
+    fn next(&mut self) -> Result<Bound<'py, PyAny>, PyErr> {
+        let result = match self {
+            Self::Single(result) => result.take().map(Ok),
+            Self::Multiple(results) => results.next(),
+        };
+
+        result.unwrap_or_else(|| {
+            Err(pyo3::exceptions::PyValueError::new_err(
+                "function returned fewer results than its type requires",
+            ))
+        })
+    }
+
+    fn finish(self) -> Result<(), PyErr> {
+        match self {
+            Self::Single(_) => Ok(()),
+            Self::Multiple(mut results) => match results.next().transpose()? {
+                None => Ok(()),
+                Some(_) => Err(pyo3::exceptions::PyValueError::new_err(
+                    "function returned more results than its type requires",
+                )),
+            },
+        }
+    }
+}
+
+/// Converts the infallibly convertible `value` into a Python object
+fn into_py_any<'py, T: IntoPyObject<'py, Output = Bound<'py, S>, Error = Infallible>, S>(
+    py: Python<'py>,
+    value: T,
+) -> Py<PyAny> {
+    value
+        .into_pyobject(py)
+        .map_or_else(|err| match err {}, |value| value.into_any().unbind())
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_runtime_layer::backend::{Imports, Value, WasmFunc};
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn typed_guest_and_host_calls() {
+        let mut store = test_utils::store();
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (func (export "swap") (param i64 f32) (result f32 i64)
+                    (local.get 1) (local.get 0))
+                (func (export "count") (result i32)
+                    (i32.const 42))
+                (func (export "nop"))
+            )"#,
+            &Imports::default(),
+        )
+        .unwrap();
+
+        let [swap, count, nop] =
+            ["swap", "count", "nop"].map(|name| test_utils::export_func(&store, &instance, name));
+
+        let swap = swap.typed::<(i64, f32), (f32, i64)>(&store).unwrap();
+        for x in [i64::MIN, -(1 << 53), 1 << 53, i64::MAX] {
+            assert_eq!(swap.call(&mut store, (x, 0.5)).unwrap(), (0.5, x));
+        }
+
+        let count = count.typed::<(), i32>(&store).unwrap();
+        assert_eq!(count.call(&mut store, ()).unwrap(), 42);
+
+        let nop = nop.typed::<(), ()>(&store).unwrap();
+        nop.call(&mut store, ()).unwrap();
+
+        let err = count.func().typed::<i32, i32>(&store).unwrap_err();
+        assert!(err.to_string().contains("was requested"), "{err:?}");
+
+        let host = crate::Func::new(
+            &mut store,
+            FuncType::new([ValueType::F64, ValueType::I64], [ValueType::I64]),
+            |_caller, args, results| {
+                let (Value::F64(a), Value::I64(b)) = (&args[0], &args[1]) else {
+                    anyhow::bail!("expected f64 and i64 arguments");
+                };
+                #[allow(clippy::cast_possible_truncation)]
+                let a = *a as i64;
+                results[0] = Value::I64(a.wrapping_mul(*b));
+                Ok(())
+            },
+        );
+        let host = host.typed::<(f64, i64), i64>(&store).unwrap();
+        assert_eq!(host.call(&mut store, (-2.0, i64::MAX)).unwrap(), 2);
+    }
+
+    #[test]
+    fn typed_result_count_mismatch() {
+        let mut store = test_utils::store();
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (func (export "three") (result i32 i32 i32)
+                    (i32.const 1) (i32.const 2) (i32.const 3))
+            )"#,
+            &Imports::default(),
+        )
+        .unwrap();
+        let three = test_utils::export_func(&store, &instance, "three");
+
+        let [fewer, more] = [
+            [ValueType::I32; 2].as_slice(),
+            [ValueType::I32; 4].as_slice(),
+        ]
+        .map(|results| {
+            Python::with_gil(|py| {
+                crate::Func::from_js_function(
+                    three.as_js(py),
+                    FuncType::new([], results.iter().copied()),
+                )
+                .unwrap()
+            })
+        });
+
+        let fewer = fewer.typed::<(), (i32, i32)>(&store).unwrap();
+        let err = fewer.call(&mut store, ()).unwrap_err();
+        assert!(err.to_string().contains("more results"), "{err:?}");
+
+        let more = more.typed::<(), (i32, i32, i32, i32)>(&store).unwrap();
+        let err = more.call(&mut store, ()).unwrap_err();
+        assert!(err.to_string().contains("fewer results"), "{err:?}");
+    }
+
+    #[test]
+    fn typed_nan_bits_round_trip() {
+        let mut config = crate::Config::new();
+        config.preserve_float_bits(true);
+        let mut store = test_utils::store_with(&config, ());
+
+        let instance = test_utils::instantiate(
+            &mut store,
+            r#"
+            (module
+                (func (export "identity") (param f32 f64) (result f32 f64)
+                    (local.get 0) (local.get 1))
+            )"#,
+            &Imports::default(),
+        )
+        .unwrap();
+        let identity = test_utils::export_func(&store, &instance, "identity")
+            .typed::<(f32, f64), (f32, f64)>(&store)
+            .unwrap();
+
+        let (a, b) = (
+            f32::from_bits(0x7F80_0001),
+            f64::from_bits(0x7FF0_0000_0000_0001),
+        );
+        let (x, y) = identity.call(&mut store, (a, b)).unwrap();
+        assert_eq!((x.to_bits(), y.to_bits()), (a.to_bits(), b.to_bits()));
+    }
+}
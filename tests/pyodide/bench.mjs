@@ -0,0 +1,30 @@
+// Runs the micro-benchmarks inside Pyodide under Node.js and prints the
+// average time per call of each
+//
+// Usage: node bench.mjs <wheel> [iterations]
+
+import process from "node:process";
+
+import { loadHarness } from "./harness.mjs";
+
+const [wheel, iterations = "10000"] = process.argv.slice(2);
+
+if (wheel === undefined) {
+    console.error("usage: node bench.mjs <wheel> [iterations]");
+    process.exit(2);
+}
+
+const pyodide = await loadHarness(wheel);
+
+pyodide.globals.set("iterations", Number.parseInt(iterations, 10));
+pyodide.runPython(`
+from pyodide_webassembly_runtime_layer_tests import run_bench
+
+# the first run warms up the JIT and the caches
+run_bench(iterations)
+results = run_bench(iterations)
+
+print(f"\\nrunning {len(results)} benchmarks with {iterations} iterations")
+for name, nanos in results:
+    print(f"{name:<36} {nanos / 1000:>10.3f} us/call")
+`);
@@ -0,0 +1,14 @@
+#!/usr/bin/env bash
+#
+# Builds the integration tests and runs the micro-benchmarks inside Pyodide
+# under Node.js, which requires the Pyodide distribution to be vendored by
+# `npm install`.
+#
+# Usage: ./bench.sh [iterations]
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+./build.sh
+node bench.mjs dist/*.whl "$@"
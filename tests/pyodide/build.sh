@@ -0,0 +1,20 @@
+#!/usr/bin/env bash
+#
+# Builds the integration tests for wasm32-unknown-emscripten into a wheel in
+# the dist directory.
+#
+# Requirements:
+# - pyodide-build, e.g. `pip install pyodide-build`, with the cross-build
+#   environment for the Pyodide version in package.json, see
+#   `pyodide xbuildenv install`
+# - the Emscripten and Rust toolchains reported by
+#   `pyodide config get emscripten_version` and
+#   `pyodide config get rust_toolchain`, with the
+#   wasm32-unknown-emscripten target installed
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+rm -rf dist
+pyodide build --outdir dist
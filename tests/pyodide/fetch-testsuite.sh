@@ -0,0 +1,40 @@
+#!/usr/bin/env bash
+#
+# Vendors the WebAssembly spec testsuite into the testsuite directory at the
+# revision that is pinned in testsuite-revision.txt, so that conformance
+# reports only change when the pin is updated.
+#
+# Usage: ./fetch-testsuite.sh [--update [ref]]
+#
+# With --update, the testsuite is instead vendored at the given ref (default:
+# main) and the resolved commit is written to testsuite-revision.txt, which
+# must then be committed.
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+PIN=testsuite-revision.txt
+
+if [ "${1:-}" = "--update" ]; then
+    REVISION="${2:-main}"
+elif [ -s "${PIN}" ]; then
+    REVISION="$(tr -d '[:space:]' < "${PIN}")"
+else
+    echo "no testsuite revision is pinned in ${PIN}, run ./fetch-testsuite.sh --update" >&2
+    exit 1
+fi
+
+if [ ! -d testsuite ]; then
+    git clone --quiet https://github.com/WebAssembly/testsuite.git testsuite
+fi
+
+git -C testsuite fetch --quiet origin "${REVISION}"
+git -C testsuite checkout --quiet --detach FETCH_HEAD
+
+if [ "${1:-}" = "--update" ]; then
+    git -C testsuite rev-parse HEAD > "${PIN}"
+    echo "pinned the testsuite at $(cat "${PIN}") in ${PIN}"
+fi
+
+echo "vendored the testsuite at $(git -C testsuite rev-parse HEAD)"
@@ -0,0 +1,28 @@
+// Loads Pyodide and installs the integration tests wheel into it
+//
+// Pyodide is loaded from the vendored distribution in `node_modules/pyodide`,
+// or from the directory given by the `PYODIDE_INDEX_URL` environment
+// variable, so that the tests run fully offline.
+
+import { readFile } from "node:fs/promises";
+import { createRequire } from "node:module";
+import path from "node:path";
+import process from "node:process";
+import { pathToFileURL } from "node:url";
+
+export async function loadHarness(wheel) {
+    const require = createRequire(import.meta.url);
+    const indexDir = process.env.PYODIDE_INDEX_URL ?? path.dirname(require.resolve("pyodide"));
+    const indexURL = indexDir.endsWith(path.sep) ? indexDir : `${indexDir}${path.sep}`;
+
+    const { loadPyodide } = await import(
+        pathToFileURL(path.join(indexURL, "pyodide.mjs")).href
+    );
+    const pyodide = await loadPyodide({ indexURL });
+
+    console.log(`Pyodide ${pyodide.version} on Node.js ${process.version}`);
+
+    pyodide.unpackArchive(await readFile(wheel), "wheel");
+
+    return pyodide;
+}
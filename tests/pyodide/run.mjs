@@ -0,0 +1,42 @@
+// Runs the integration tests inside Pyodide under Node.js
+//
+// Usage: node --experimental-wasm-type-reflection run.mjs <wheel> [filter]
+//
+// Pyodide is loaded from a vendored distribution, see `harness.mjs`.
+
+import process from "node:process";
+
+import { loadHarness } from "./harness.mjs";
+
+const [wheel, filter] = process.argv.slice(2);
+
+if (wheel === undefined) {
+    console.error("usage: node run.mjs <wheel> [filter]");
+    process.exit(2);
+}
+
+const pyodide = await loadHarness(wheel);
+
+pyodide.globals.set("test_filter", filter);
+const failed = pyodide.runPython(`
+from pyodide_webassembly_runtime_layer_tests import run
+
+results = run(test_filter)
+failures = [(name, error) for name, error in results if error is not None]
+
+print(f"\\nrunning {len(results)} tests")
+for name, error in results:
+    print(f"test {name} ... {'ok' if error is None else 'FAILED'}")
+
+for name, error in failures:
+    print(f"\\n---- {name} ----\\n{error}")
+
+print(
+    f"\\ntest result: {'ok' if len(failures) == 0 else 'FAILED'}. "
+    f"{len(results) - len(failures)} passed; {len(failures)} failed"
+)
+
+len(failures)
+`);
+
+process.exitCode = failed === 0 ? 0 : 1;
@@ -0,0 +1,14 @@
+#!/usr/bin/env bash
+#
+# Builds the integration tests and runs them inside Pyodide under Node.js,
+# which requires the Pyodide distribution to be vendored by `npm install`.
+#
+# Usage: ./run.sh [filter]
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+./build.sh
+# funcref values can only be converted with JS Type Reflection support
+node --experimental-wasm-type-reflection run.mjs dist/*.whl "$@"
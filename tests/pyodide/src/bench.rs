@@ -0,0 +1,318 @@
+//! Micro-benchmarks of the per-call cost of passing values across the
+//! JavaScript boundary
+//!
+//! Every benchmark is compared with an `i32` baseline, which needs no
+//! conversion in JavaScript. The `i64` benchmarks additionally compare
+//! [`Func::call`] with calling the exported JavaScript function directly
+//! after converting every `i64` with one call into JavaScript each, which
+//! is how this backend used to pass `i64`s. The typed and batched benchmarks
+//! compare [`Func::call`] with [`TypedFunc::call`] and with
+//! [`Func::call_batch`] in batches of [`BATCH_SIZE`] calls. Host functions
+//! are also compared with host functions that are implemented directly in
+//! JavaScript with [`Func::from_js_source`].
+//!
+//! [`Func::from_js_source`]: pyodide_webassembly_runtime_layer::Func::from_js_source
+//! [`TypedFunc::call`]: pyodide_webassembly_runtime_layer::TypedFunc::call
+//! [`Func::call_batch`]: pyodide_webassembly_runtime_layer::Func::call_batch
+
+use std::{io::Cursor, time::Instant};
+
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyTuple};
+use wasm_runtime_layer::{
+    backend::{self, WasmInstance, WasmModule},
+    Extern, Func, FuncType, Imports, Instance, Module, Value, ValueType,
+};
+
+use crate::{engine, export_func, Store};
+
+/// The number of calls in each batch of the batched benchmarks
+const BATCH_SIZE: usize = 100;
+
+const GUEST_WAT: &str = r#"(module
+    (func (export "add_i32") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1)))
+    (func (export "add_i64") (param i64 i64) (result i64)
+        (i64.add (local.get 0) (local.get 1)))
+)"#;
+
+const HOST_WAT: &str = r#"(module
+    (import "env" "host_i32" (func $host_i32 (param i32 i32) (result i32)))
+    (import "env" "host_i64" (func $host_i64 (param i64 i64) (result i64)))
+    (func (export "loop_i32") (param $n i32) (result i32) (local $acc i32)
+        (loop $continue
+            (local.set $acc (call $host_i32 (local.get $acc) (local.get $n)))
+            (br_if $continue (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+        (local.get $acc))
+    (func (export "loop_i64") (param $n i32) (result i64) (local $acc i64)
+        (loop $continue
+            (local.set $acc (call $host_i64 (local.get $acc) (i64.extend_i32_u (local.get $n))))
+            (br_if $continue (local.tee $n (i32.sub (local.get $n) (i32.const 1)))))
+        (local.get $acc))
+)"#;
+
+#[pyfunction]
+#[pyo3(signature = (iterations = 10_000))]
+/// Runs all micro-benchmarks with the given number of `iterations` and
+/// returns the name and the average number of nanoseconds per call of each
+pub fn run_bench(py: Python, iterations: u32) -> PyResult<Vec<(&'static str, f64)>> {
+    bench(py, iterations).map_err(|err| PyRuntimeError::new_err(format!("{err:?}")))
+}
+
+fn bench(py: Python, iterations: u32) -> anyhow::Result<Vec<(&'static str, f64)>> {
+    let mut benchmarks = bench_guest(py, iterations)?;
+    benchmarks.extend(bench_backend(iterations)?);
+    benchmarks.extend(bench_js_host(py, iterations)?);
+    benchmarks.extend(bench_host(iterations)?);
+    Ok(benchmarks)
+}
+
+/// Benchmarks single calls of guest functions from the host
+fn bench_guest(py: Python, iterations: u32) -> anyhow::Result<Vec<(&'static str, f64)>> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let bytes = wat::parse_str(GUEST_WAT)?;
+    let module = Module::new(&engine, Cursor::new(&bytes))?;
+    let instance = Instance::new(&mut store, &module, &Imports::new())?;
+
+    let add_i32 = export_func(&store, &instance, "add_i32")?;
+    let add_i64 = export_func(&store, &instance, "add_i64")?;
+
+    let mut results = [Value::I32(0)];
+    let guest_i32 = per_call(iterations, || {
+        add_i32.call(&mut store, &[Value::I32(-1), Value::I32(42)], &mut results)
+    })?;
+
+    let mut results = [Value::I64(0)];
+    let guest_i64 = per_call(iterations, || {
+        add_i64.call(&mut store, &[Value::I64(-1), Value::I64(42)], &mut results)
+    })?;
+
+    let per_value = per_value_add_i64(py, &bytes)?;
+    let guest_i64_per_value = per_call(iterations, || per_value.call(-1, 42))?;
+
+    Ok(vec![
+        ("guest i32 (baseline)", guest_i32),
+        ("guest i64", guest_i64),
+        ("guest i64 (per-value conversion)", guest_i64_per_value),
+    ])
+}
+
+/// Benchmarks typed and batched calls of guest functions from the host,
+/// which use the backend API directly since they are specific to this backend
+fn bench_backend(iterations: u32) -> anyhow::Result<Vec<(&'static str, f64)>> {
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        Cursor::new(wat::parse_str(GUEST_WAT)?),
+    )?;
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(
+        &mut store,
+        &module,
+        &backend::Imports::default(),
+    )?;
+
+    let export = |name| match instance.get_export(&store, name) {
+        Some(backend::Extern::Func(func)) => Ok(func),
+        _ => Err(anyhow::anyhow!("missing function export {name:?}")),
+    };
+    let add_i32 = export("add_i32")?;
+    let add_i64 = export("add_i64")?;
+
+    let typed_add_i32 = add_i32.typed::<(i32, i32), i32>(&store)?;
+    let typed_i32 = per_call(iterations, || {
+        anyhow::ensure!(typed_add_i32.call(&mut store, (-1, 42))? == 41);
+        Ok(())
+    })?;
+
+    let typed_add_i64 = add_i64.typed::<(i64, i64), i64>(&store)?;
+    let typed_i64 = per_call(iterations, || {
+        anyhow::ensure!(typed_add_i64.call(&mut store, (-1, 42))? == 41);
+        Ok(())
+    })?;
+
+    let batches = iterations.div_ceil(u32::try_from(BATCH_SIZE)?);
+
+    let args = vec![[backend::Value::I32(-1), backend::Value::I32(42)]; BATCH_SIZE];
+    let mut results = vec![[backend::Value::I32(0)]; BATCH_SIZE];
+    let batched_i32 = per_call(batches, || {
+        add_i32.call_batch(&mut store, &args, &mut results)
+    })? / f64::from(u32::try_from(BATCH_SIZE)?);
+
+    let args = vec![[backend::Value::I64(-1), backend::Value::I64(42)]; BATCH_SIZE];
+    let mut results = vec![[backend::Value::I64(0)]; BATCH_SIZE];
+    let batched_i64 = per_call(batches, || {
+        add_i64.call_batch(&mut store, &args, &mut results)
+    })? / f64::from(u32::try_from(BATCH_SIZE)?);
+
+    Ok(vec![
+        ("guest i32 (typed)", typed_i32),
+        ("guest i64 (typed)", typed_i64),
+        ("guest i32 (batched)", batched_i32),
+        ("guest i64 (batched)", batched_i64),
+    ])
+}
+
+/// Benchmarks calls of host functions that are implemented in JavaScript
+/// from a guest loop
+fn bench_js_host(py: Python, iterations: u32) -> anyhow::Result<Vec<(&'static str, f64)>> {
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let host_i32 = pyodide_webassembly_runtime_layer::Func::from_js_source(
+        py,
+        "(a, b) => (a + b) | 0",
+        FuncType::new([ValueType::I32, ValueType::I32], [ValueType::I32]),
+    )?;
+    let host_i64 = pyodide_webassembly_runtime_layer::Func::from_js_source(
+        py,
+        "(a, b) => BigInt.asIntN(64, a + b)",
+        FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]),
+    )?;
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        Cursor::new(wat::parse_str(HOST_WAT)?),
+    )?;
+    let mut imports = backend::Imports::default();
+    imports.define("env", "host_i32", backend::Extern::Func(host_i32));
+    imports.define("env", "host_i64", backend::Extern::Func(host_i64));
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(&mut store, &module, &imports)?;
+
+    let export = |name| match instance.get_export(&store, name) {
+        Some(backend::Extern::Func(func)) => Ok(func),
+        _ => Err(anyhow::anyhow!("missing function export {name:?}")),
+    };
+    let loop_i32 = export("loop_i32")?.typed::<i32, i32>(&store)?;
+    let loop_i64 = export("loop_i64")?.typed::<i32, i64>(&store)?;
+
+    let n = i32::try_from(iterations)?;
+
+    let host_i32 = per_call(1, || {
+        loop_i32.call(&mut store, n)?;
+        Ok(())
+    })? / f64::from(iterations);
+
+    let host_i64 = per_call(1, || {
+        loop_i64.call(&mut store, n)?;
+        Ok(())
+    })? / f64::from(iterations);
+
+    Ok(vec![
+        ("host i32 from guest (JavaScript)", host_i32),
+        ("host i64 from guest (JavaScript)", host_i64),
+    ])
+}
+
+/// Benchmarks calls of host functions from a guest loop
+fn bench_host(iterations: u32) -> anyhow::Result<Vec<(&'static str, f64)>> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let host_i32 = Func::new(
+        &mut store,
+        FuncType::new([ValueType::I32, ValueType::I32], [ValueType::I32]),
+        |_caller, args, results| {
+            let (Value::I32(a), Value::I32(b)) = (&args[0], &args[1]) else {
+                anyhow::bail!("expected two i32 arguments");
+            };
+            results[0] = Value::I32(a.wrapping_add(*b));
+            Ok(())
+        },
+    );
+    let host_i64 = Func::new(
+        &mut store,
+        FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]),
+        |_caller, args, results| {
+            let (Value::I64(a), Value::I64(b)) = (&args[0], &args[1]) else {
+                anyhow::bail!("expected two i64 arguments");
+            };
+            results[0] = Value::I64(a.wrapping_add(*b));
+            Ok(())
+        },
+    );
+
+    let module = Module::new(&engine, Cursor::new(wat::parse_str(HOST_WAT)?))?;
+    let mut imports = Imports::new();
+    imports.define("env", "host_i32", Extern::Func(host_i32));
+    imports.define("env", "host_i64", Extern::Func(host_i64));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let loop_i32 = export_func(&store, &instance, "loop_i32")?;
+    let loop_i64 = export_func(&store, &instance, "loop_i64")?;
+
+    let n = [Value::I32(iterations.try_into()?)];
+
+    let mut results = [Value::I32(0)];
+    let host_i32 =
+        per_call(1, || loop_i32.call(&mut store, &n, &mut results))? / f64::from(iterations);
+
+    let mut results = [Value::I64(0)];
+    let host_i64 =
+        per_call(1, || loop_i64.call(&mut store, &n, &mut results))? / f64::from(iterations);
+
+    Ok(vec![
+        ("host i32 from guest (baseline)", host_i32),
+        ("host i64 from guest", host_i64),
+    ])
+}
+
+/// Returns the average number of nanoseconds per call of `f`
+fn per_call(iterations: u32, mut f: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<f64> {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f()?;
+    }
+    Ok(start.elapsed().as_secs_f64() * 1e9 / f64::from(iterations))
+}
+
+/// The exported `add_i64` function of the guest module, which is called
+/// directly and converts every `i64` with one call into JavaScript each
+struct PerValueAddI64<'py> {
+    add_i64: Bound<'py, PyAny>,
+    big_int: Bound<'py, PyAny>,
+    object_wrapped_big_int: Bound<'py, PyAny>,
+}
+
+fn per_value_add_i64<'py>(py: Python<'py>, bytes: &[u8]) -> anyhow::Result<PerValueAddI64<'py>> {
+    let js = py.import("js")?;
+    let web_assembly = js.getattr("WebAssembly")?;
+
+    let buffer = js.getattr("Uint8Array")?.call_method1("new", (bytes,))?;
+    let module = web_assembly
+        .getattr("Module")?
+        .call_method1("new", (buffer,))?;
+    let instance = web_assembly
+        .getattr("Instance")?
+        .call_method1("new", (module,))?;
+
+    let run_js = py.import("pyodide.code")?.getattr("run_js")?;
+
+    Ok(PerValueAddI64 {
+        add_i64: instance.getattr("exports")?.getattr("add_i64")?,
+        big_int: js.getattr("BigInt")?,
+        object_wrapped_big_int: run_js.call1(("(v) => Object(BigInt(v))",))?,
+    })
+}
+
+impl PerValueAddI64<'_> {
+    fn call(&self, a: i64, b: i64) -> anyhow::Result<()> {
+        let py = self.add_i64.py();
+
+        let args = PyTuple::new(
+            py,
+            [
+                self.object_wrapped_big_int.call1((a,))?,
+                self.object_wrapped_big_int.call1((b,))?,
+            ],
+        )?;
+        let result = self.add_i64.call1(args)?;
+        let result: i64 = self.big_int.call1((result,))?.extract()?;
+
+        anyhow::ensure!(result == a.wrapping_add(b));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,820 @@
+//! Differential tests, which run identical modules and call sequences through
+//! this backend and through the [`wasmi_runtime_layer`] reference backend,
+//! and compare the results, traps, and memory contents of both.
+//!
+//! Each scenario is written once, generic over the backend, and records a
+//! trace with one line per observable step. Floating point values are
+//! compared bit-exactly, except that NaNs produced by the numeric
+//! instructions only need to be NaNs, since their payloads are
+//! nondeterministic.
+
+use std::{fmt::Write, io::Cursor};
+
+use wasm_runtime_layer::{
+    backend::WasmEngine, Engine, ExternRef, Func, FuncType, Imports, Instance, Module, Store,
+    Table, TableType, Value, ValueType,
+};
+
+use crate::Test;
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "differential::numeric",
+        test: numeric,
+    },
+    Test {
+        name: "differential::traps",
+        test: traps,
+    },
+    Test {
+        name: "differential::host",
+        test: host,
+    },
+    Test {
+        name: "differential::memory",
+        test: memory,
+    },
+    Test {
+        name: "differential::table",
+        test: table,
+    },
+];
+
+/// The maximum number of mismatching steps that are reported
+const REPORTED_MISMATCHES: usize = 16;
+
+/// A trace of the observable steps of a scenario
+type Trace = Vec<String>;
+
+/// Every numeric instruction on edge-case and pseudo-random operands
+fn numeric() -> anyhow::Result<()> {
+    compare(
+        &numeric_trace(&crate::engine())?,
+        &numeric_trace(&reference_engine())?,
+    )
+}
+
+/// Guest traps, and guest calls after a trap
+fn traps() -> anyhow::Result<()> {
+    compare(
+        &traps_trace(&crate::engine())?,
+        &traps_trace(&reference_engine())?,
+    )
+}
+
+/// Values crossing the host boundary through calls and globals, which must
+/// preserve every bit of NaNs
+fn host() -> anyhow::Result<()> {
+    compare(
+        &host_trace(&crate::float_bits_engine())?,
+        &host_trace(&reference_engine())?,
+    )
+}
+
+/// A pseudo-random sequence of guest and host memory accesses
+fn memory() -> anyhow::Result<()> {
+    compare(
+        &memory_trace(&crate::engine())?,
+        &memory_trace(&reference_engine())?,
+    )
+}
+
+/// Host and guest table accesses, including out-of-range ones
+fn table() -> anyhow::Result<()> {
+    compare(
+        &table_trace(&crate::engine())?,
+        &table_trace(&reference_engine())?,
+    )
+}
+
+/// Returns an engine of the reference backend
+fn reference_engine() -> Engine<wasmi_runtime_layer::Engine> {
+    Engine::new(wasmi_runtime_layer::Engine::default())
+}
+
+/// Compares the `actual` trace of this backend with the `expected` trace of
+/// the reference backend
+fn compare(actual: &[String], expected: &[String]) -> anyhow::Result<()> {
+    let mismatches = actual
+        .iter()
+        .zip(expected)
+        .filter(|(actual, expected)| actual != expected)
+        .map(|(actual, expected)| format!("\n  pyodide: {actual}\n  wasmi:   {expected}"))
+        .collect::<Vec<_>>();
+
+    anyhow::ensure!(
+        mismatches.is_empty(),
+        "{} of {} steps differ:{}",
+        mismatches.len(),
+        expected.len(),
+        mismatches
+            .iter()
+            .take(REPORTED_MISMATCHES)
+            .map(String::as_str)
+            .collect::<String>()
+    );
+    anyhow::ensure!(
+        actual.len() == expected.len(),
+        "pyodide recorded {} steps but wasmi recorded {}",
+        actual.len(),
+        expected.len()
+    );
+
+    Ok(())
+}
+
+/// Compiles and instantiates the WebAssembly text format `wat`
+fn instantiate<T, E: WasmEngine>(
+    store: &mut Store<T, E>,
+    wat: &str,
+    imports: &Imports,
+) -> anyhow::Result<Instance> {
+    let module = Module::new(store.engine(), Cursor::new(wat::parse_str(wat)?))?;
+    Instance::new(store, &module, imports)
+}
+
+/// Returns the function export `name` of the `instance`
+fn export_func<T, E: WasmEngine>(
+    store: &Store<T, E>,
+    instance: &Instance,
+    name: &str,
+) -> anyhow::Result<Func> {
+    instance
+        .get_export(store, name)
+        .and_then(wasm_runtime_layer::Extern::into_func)
+        .ok_or_else(|| anyhow::anyhow!("missing function export {name:?}"))
+}
+
+/// Calls the `func` and describes its results or trap
+fn call<T, E: WasmEngine>(store: &mut Store<T, E>, func: &Func, args: &[Value]) -> String {
+    call_with(store, func, args, describe)
+}
+
+/// Calls the `func` and describes its results with `describe`, or its trap
+fn call_with<T, E: WasmEngine>(
+    store: &mut Store<T, E>,
+    func: &Func,
+    args: &[Value],
+    describe: fn(&Value) -> String,
+) -> String {
+    let mut results = func
+        .ty(&*store)
+        .results()
+        .iter()
+        .map(|ty| default_value(*ty))
+        .collect::<Vec<_>>();
+
+    match func.call(&mut *store, args, &mut results) {
+        Ok(()) => results.iter().map(describe).collect::<Vec<_>>().join(" "),
+        Err(_) => String::from("trap"),
+    }
+}
+
+/// Returns the zero value of the type `ty`
+const fn default_value(ty: ValueType) -> Value {
+    match ty {
+        ValueType::I32 => Value::I32(0),
+        ValueType::I64 => Value::I64(0),
+        ValueType::F32 => Value::F32(0.0),
+        ValueType::F64 => Value::F64(0.0),
+        ValueType::FuncRef => Value::FuncRef(None),
+        ValueType::ExternRef => Value::ExternRef(None),
+    }
+}
+
+/// Describes the `value` such that equal descriptions imply bit-exactly equal
+/// values
+fn describe(value: &Value) -> String {
+    match value {
+        Value::I32(v) => format!("i32:{v}"),
+        Value::I64(v) => format!("i64:{v}"),
+        Value::F32(v) => format!("f32:{:#010x}", v.to_bits()),
+        Value::F64(v) => format!("f64:{:#018x}", v.to_bits()),
+        Value::FuncRef(None) => String::from("funcref:null"),
+        Value::FuncRef(Some(_)) => String::from("funcref"),
+        Value::ExternRef(None) => String::from("externref:null"),
+        Value::ExternRef(Some(_)) => String::from("externref"),
+    }
+}
+
+/// Describes the `value` like [`describe`], but considers all NaNs equal
+fn describe_arithmetic(value: &Value) -> String {
+    match value {
+        Value::F32(v) if v.is_nan() => String::from("f32:nan"),
+        Value::F64(v) if v.is_nan() => String::from("f64:nan"),
+        value => describe(value),
+    }
+}
+
+/// Describes the `values`, see [`describe`]
+fn describe_values(values: &[Value]) -> String {
+    values.iter().map(describe).collect::<Vec<_>>().join(" ")
+}
+
+/// Describes an outcome, which is either a description or an error
+fn describe_outcome<T>(result: anyhow::Result<T>, describe: impl FnOnce(T) -> String) -> String {
+    result.map_or_else(|_| String::from("error"), describe)
+}
+
+/// A small linear congruential generator, which makes the pseudo-random
+/// scenarios reproducible
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn below(&mut self, bound: u32) -> u32 {
+        ((self.next() >> 32) % u64::from(bound)) as u32
+    }
+}
+
+/// Returns the edge-case and pseudo-random operands of the type `ty`
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn operands(ty: ValueType, lcg: &mut Lcg) -> Vec<Value> {
+    let mut operands = match ty {
+        ValueType::I32 => [0, 1, -1, 7, 31, 32, 0x80, i32::MIN, i32::MAX]
+            .into_iter()
+            .map(Value::I32)
+            .collect(),
+        ValueType::I64 => [
+            0,
+            1,
+            -1,
+            63,
+            64,
+            1 << 53,
+            (1 << 53) + 1,
+            -(1 << 53) - 1,
+            i64::MIN,
+            i64::MAX,
+        ]
+        .into_iter()
+        .map(Value::I64)
+        .collect(),
+        ValueType::F32 => [
+            0.0,
+            -0.0,
+            0.5,
+            -1.5,
+            2.5,
+            2_147_483_648.0,
+            -2_147_483_904.0,
+            4_294_967_296.0,
+            f32::MIN_POSITIVE,
+            f32::MAX,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+        ]
+        .into_iter()
+        .map(Value::F32)
+        .collect(),
+        ValueType::F64 => [
+            0.0,
+            -0.0,
+            0.5,
+            -1.5,
+            2.5,
+            2_147_483_648.0,
+            -2_147_483_649.0,
+            9_223_372_036_854_775_808.0,
+            18_446_744_073_709_551_616.0,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+        ]
+        .into_iter()
+        .map(Value::F64)
+        .collect(),
+        ValueType::FuncRef | ValueType::ExternRef => Vec::new(),
+    };
+
+    for _ in 0..4 {
+        let bits = lcg.next();
+        operands.push(match ty {
+            ValueType::I32 => Value::I32(bits as i32),
+            ValueType::I64 => Value::I64(bits as i64),
+            ValueType::F32 => Value::F32(f32::from_bits(bits as u32)),
+            ValueType::F64 => Value::F64(f64::from_bits(bits)),
+            ValueType::FuncRef | ValueType::ExternRef => continue,
+        });
+    }
+
+    operands
+}
+
+/// Returns every numeric instruction, together with its parameter and result
+/// types
+fn numeric_instructions() -> Vec<(String, Vec<ValueType>, ValueType)> {
+    let mut instructions = Vec::new();
+
+    for (int, bits) in [(ValueType::I32, 32), (ValueType::I64, 64)] {
+        for op in [
+            "add", "sub", "mul", "div_s", "div_u", "rem_s", "rem_u", "and", "or", "xor", "shl",
+            "shr_s", "shr_u", "rotl", "rotr",
+        ] {
+            instructions.push((format!("{int}.{op}"), vec![int, int], int));
+        }
+        for op in [
+            "eq", "ne", "lt_s", "lt_u", "gt_s", "gt_u", "le_s", "le_u", "ge_s", "ge_u",
+        ] {
+            instructions.push((format!("{int}.{op}"), vec![int, int], ValueType::I32));
+        }
+        for op in ["clz", "ctz", "popcnt", "extend8_s", "extend16_s"] {
+            instructions.push((format!("{int}.{op}"), vec![int], int));
+        }
+        instructions.push((format!("{int}.eqz"), vec![int], ValueType::I32));
+
+        for (float, float_bits) in [(ValueType::F32, 32), (ValueType::F64, 64)] {
+            for sign in ["s", "u"] {
+                instructions.push((format!("{int}.trunc_{float}_{sign}"), vec![float], int));
+                instructions.push((format!("{int}.trunc_sat_{float}_{sign}"), vec![float], int));
+                instructions.push((format!("{float}.convert_{int}_{sign}"), vec![int], float));
+            }
+            if bits == float_bits {
+                instructions.push((format!("{int}.reinterpret_{float}"), vec![float], int));
+                instructions.push((format!("{float}.reinterpret_{int}"), vec![int], float));
+            }
+        }
+    }
+
+    instructions.push((
+        String::from("i32.wrap_i64"),
+        vec![ValueType::I64],
+        ValueType::I32,
+    ));
+    instructions.push((
+        String::from("i64.extend32_s"),
+        vec![ValueType::I64],
+        ValueType::I64,
+    ));
+    for sign in ["s", "u"] {
+        instructions.push((
+            format!("i64.extend_i32_{sign}"),
+            vec![ValueType::I32],
+            ValueType::I64,
+        ));
+    }
+
+    for float in [ValueType::F32, ValueType::F64] {
+        for op in ["add", "sub", "mul", "div", "min", "max", "copysign"] {
+            instructions.push((format!("{float}.{op}"), vec![float, float], float));
+        }
+        for op in ["eq", "ne", "lt", "gt", "le", "ge"] {
+            instructions.push((format!("{float}.{op}"), vec![float, float], ValueType::I32));
+        }
+        for op in ["abs", "neg", "ceil", "floor", "trunc", "nearest", "sqrt"] {
+            instructions.push((format!("{float}.{op}"), vec![float], float));
+        }
+    }
+
+    instructions.push((
+        String::from("f32.demote_f64"),
+        vec![ValueType::F64],
+        ValueType::F32,
+    ));
+    instructions.push((
+        String::from("f64.promote_f32"),
+        vec![ValueType::F32],
+        ValueType::F64,
+    ));
+
+    instructions
+}
+
+fn numeric_trace<E: WasmEngine>(engine: &Engine<E>) -> anyhow::Result<Trace> {
+    let mut store = Store::new(engine, ());
+    let mut lcg = Lcg(0x5EED);
+
+    let instructions = numeric_instructions();
+
+    let mut wat = String::from("(module");
+    for (instruction, params, result) in &instructions {
+        let params = params.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let locals = (0..params.len())
+            .map(|i| format!("(local.get {i})"))
+            .collect::<Vec<_>>();
+        writeln!(
+            wat,
+            "(func (export \"{instruction}\") (param {}) (result {result}) ({instruction} {}))",
+            params.join(" "),
+            locals.join(" "),
+        )?;
+    }
+    wat.push(')');
+
+    let instance = instantiate(&mut store, &wat, &Imports::new())?;
+
+    let mut trace = Trace::new();
+
+    for (instruction, params, _) in &instructions {
+        let func = export_func(&store, &instance, instruction)?;
+
+        let mut arguments = vec![Vec::new()];
+        for param in params {
+            let operands = operands(*param, &mut lcg);
+            arguments = arguments
+                .into_iter()
+                .flat_map(|args| {
+                    operands.iter().map(move |operand| {
+                        let mut args = args.clone();
+                        args.push(operand.clone());
+                        args
+                    })
+                })
+                .collect();
+        }
+
+        for args in arguments {
+            trace.push(format!(
+                "{instruction}({}) = {}",
+                describe_values(&args),
+                call_with(&mut store, &func, &args, describe_arithmetic)
+            ));
+        }
+    }
+
+    Ok(trace)
+}
+
+fn traps_trace<E: WasmEngine>(engine: &Engine<E>) -> anyhow::Result<Trace> {
+    let mut store = Store::new(engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (type $nullary (func (result i32)))
+            (memory 1)
+            (table 2 funcref)
+            (elem (i32.const 1) $unary)
+            (func $unary (param i32) (result i32) (local.get 0))
+            (func $recurse (export "recurse") (param i32) (result i32)
+                (call $recurse (i32.add (local.get 0) (i32.const 1))))
+            (func (export "unreachable") (unreachable))
+            (func (export "div") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1)))
+            (func (export "rem") (param i64 i64) (result i64)
+                (i64.rem_s (local.get 0) (local.get 1)))
+            (func (export "load") (param i32) (result i64)
+                (i64.load (local.get 0)))
+            (func (export "store") (param i32)
+                (i32.store16 offset=2 (local.get 0) (i32.const -1)))
+            (func (export "indirect") (param i32) (result i32)
+                (call_indirect (type $nullary) (local.get 0)))
+            (func (export "trunc") (param f64) (result i32)
+                (i32.trunc_f64_u (local.get 0)))
+        )"#,
+        &Imports::new(),
+    )?;
+
+    let mut trace = Trace::new();
+
+    for (name, args) in [
+        ("unreachable", vec![]),
+        ("div", vec![Value::I32(7), Value::I32(0)]),
+        ("div", vec![Value::I32(i32::MIN), Value::I32(-1)]),
+        ("div", vec![Value::I32(-7), Value::I32(2)]),
+        ("rem", vec![Value::I64(i64::MIN), Value::I64(-1)]),
+        ("rem", vec![Value::I64(1), Value::I64(0)]),
+        ("load", vec![Value::I32(65_528)]),
+        ("load", vec![Value::I32(65_529)]),
+        ("load", vec![Value::I32(-1)]),
+        ("store", vec![Value::I32(65_532)]),
+        ("store", vec![Value::I32(65_533)]),
+        ("load", vec![Value::I32(65_528)]),
+        ("indirect", vec![Value::I32(0)]),
+        ("indirect", vec![Value::I32(1)]),
+        ("indirect", vec![Value::I32(2)]),
+        ("trunc", vec![Value::F64(4_294_967_295.5)]),
+        ("trunc", vec![Value::F64(4_294_967_296.0)]),
+        ("trunc", vec![Value::F64(-1.0)]),
+        ("trunc", vec![Value::F64(f64::NAN)]),
+        ("recurse", vec![Value::I32(0)]),
+        // the instance remains usable after any trap
+        ("div", vec![Value::I32(42), Value::I32(6)]),
+    ] {
+        let func = export_func(&store, &instance, name)?;
+        trace.push(format!(
+            "{name}({}) = {}",
+            describe_values(&args),
+            call(&mut store, &func, &args)
+        ));
+    }
+
+    Ok(trace)
+}
+
+fn host_trace<E: WasmEngine>(engine: &Engine<E>) -> anyhow::Result<Trace> {
+    let mut store = Store::new(engine, Trace::new());
+
+    // the host function records the arguments it observes and returns them
+    let echo = Func::new(
+        &mut store,
+        FuncType::new(
+            [ValueType::I64, ValueType::F32, ValueType::F64],
+            [ValueType::I64, ValueType::F32, ValueType::F64],
+        ),
+        |mut caller, args, results| {
+            caller
+                .data_mut()
+                .push(format!("echo({})", describe_values(args)));
+            results.clone_from_slice(args);
+            Ok(())
+        },
+    );
+
+    let mut imports = Imports::new();
+    imports.define("env", "echo", wasm_runtime_layer::Extern::Func(echo));
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (import "env" "echo" (func $echo (param i64 f32 f64) (result i64 f32 f64)))
+            (global $wide (export "wide") (mut i64) (i64.const 0))
+            (func (export "roundtrip") (param i64 f32 f64) (result i64 f32 f64)
+                (call $echo (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "increment") (result i64)
+                (global.set $wide (i64.add (global.get $wide) (i64.const 1)))
+                (global.get $wide))
+        )"#,
+        &imports,
+    )?;
+
+    let roundtrip = export_func(&store, &instance, "roundtrip")?;
+    let increment = export_func(&store, &instance, "increment")?;
+    let wide = instance
+        .get_export(&store, "wide")
+        .and_then(wasm_runtime_layer::Extern::into_global)
+        .ok_or_else(|| anyhow::anyhow!("missing global export"))?;
+
+    let mut trace = Trace::new();
+    let mut lcg = Lcg(0xB16);
+
+    let wides = operands(ValueType::I64, &mut lcg);
+    let narrows = operands(ValueType::F32, &mut lcg);
+    let doubles = operands(ValueType::F64, &mut lcg);
+
+    for (i, wide_value) in wides.iter().enumerate() {
+        let args = [
+            wide_value.clone(),
+            narrows[i % narrows.len()].clone(),
+            doubles[i % doubles.len()].clone(),
+        ];
+        let results = call(&mut store, &roundtrip, &args);
+        trace.append(store.data_mut());
+        trace.push(format!("roundtrip({}) = {results}", describe_values(&args)));
+
+        trace.push(format!(
+            "wide.set({}) = {}",
+            describe(wide_value),
+            describe_outcome(wide.set(&mut store, wide_value.clone()), |()| {
+                String::from("ok")
+            })
+        ));
+        trace.push(format!(
+            "increment() = {}",
+            call(&mut store, &increment, &[])
+        ));
+        trace.push(format!("wide.get() = {}", describe(&wide.get(&mut store))));
+    }
+
+    Ok(trace)
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn memory_trace<E: WasmEngine>(engine: &Engine<E>) -> anyhow::Result<Trace> {
+    const STEPS: usize = 256;
+
+    let mut store = Store::new(engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (memory (export "memory") 1 3)
+            (func (export "store8") (param i32 i32) (i32.store8 (local.get 0) (local.get 1)))
+            (func (export "store64") (param i32 i64) (i64.store (local.get 0) (local.get 1)))
+            (func (export "load16_s") (param i32) (result i32) (i32.load16_s (local.get 0)))
+            (func (export "load64") (param i32) (result i64) (i64.load (local.get 0)))
+            (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0)))
+            (func (export "size") (result i32) (memory.size))
+            (func (export "fill") (param i32 i32 i32)
+                (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "copy") (param i32 i32 i32)
+                (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+        )"#,
+        &Imports::new(),
+    )?;
+
+    let memory = instance
+        .get_export(&store, "memory")
+        .and_then(wasm_runtime_layer::Extern::into_memory)
+        .ok_or_else(|| anyhow::anyhow!("missing memory export"))?;
+
+    let mut trace = Trace::new();
+    let mut lcg = Lcg(0x3E3);
+
+    for _ in 0..STEPS {
+        let size = memory.current_pages(&store) * 65_536;
+        // addresses are mostly in bounds, but sometimes just out of bounds
+        let address = |lcg: &mut Lcg| lcg.below(size + 16) as i32;
+
+        let (name, args) = match lcg.below(9) {
+            0 => (
+                "store8",
+                vec![
+                    Value::I32(address(&mut lcg)),
+                    Value::I32(lcg.below(256).try_into()?),
+                ],
+            ),
+            1 => (
+                "store64",
+                vec![Value::I32(address(&mut lcg)), Value::I64(lcg.next() as i64)],
+            ),
+            2 => ("load16_s", vec![Value::I32(address(&mut lcg))]),
+            3 => ("load64", vec![Value::I32(address(&mut lcg))]),
+            4 => ("grow", vec![Value::I32(lcg.below(2).try_into()?)]),
+            5 => ("size", vec![]),
+            6 => (
+                "fill",
+                vec![
+                    Value::I32(address(&mut lcg)),
+                    Value::I32(lcg.below(256).try_into()?),
+                    Value::I32(lcg.below(64).try_into()?),
+                ],
+            ),
+            7 => (
+                "copy",
+                vec![
+                    Value::I32(address(&mut lcg)),
+                    Value::I32(address(&mut lcg)),
+                    Value::I32(lcg.below(64).try_into()?),
+                ],
+            ),
+            _ => {
+                // host accesses through the backend's memory API
+                let offset = lcg.below(size + 16) as usize;
+                let mut buffer = [0_u8; 8];
+                if lcg.below(2) == 0 {
+                    buffer.fill(lcg.below(256).try_into()?);
+                    trace.push(format!(
+                        "memory.write({offset}, {buffer:?}) = {}",
+                        describe_outcome(memory.write(&mut store, offset, &buffer), |()| {
+                            String::from("ok")
+                        })
+                    ));
+                } else {
+                    trace.push(format!(
+                        "memory.read({offset}) = {}",
+                        describe_outcome(memory.read(&store, offset, &mut buffer), |()| {
+                            format!("{buffer:?}")
+                        })
+                    ));
+                }
+                continue;
+            },
+        };
+
+        let func = export_func(&store, &instance, name)?;
+        trace.push(format!(
+            "{name}({}) = {}",
+            describe_values(&args),
+            call(&mut store, &func, &args)
+        ));
+    }
+
+    // finally compare the entire memory contents in chunks
+    let mut contents = vec![0; (memory.current_pages(&store) * 65_536) as usize];
+    memory.read(&store, 0, &mut contents)?;
+    for (i, chunk) in contents.chunks(4096).enumerate() {
+        trace.push(format!("memory[{i}] = {:#018x}", fnv1a(chunk)));
+    }
+
+    Ok(trace)
+}
+
+/// Hashes the `bytes` with the 64-bit FNV-1a hash function
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn table_trace<E: WasmEngine>(engine: &Engine<E>) -> anyhow::Result<Trace> {
+    let mut store = Store::new(engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (table $funcs (export "funcs") 2 4 funcref)
+            (table $externs 1 externref)
+            (elem (table $funcs) (i32.const 0) func $answer)
+            (func $answer (export "answer") (result i32) (i32.const 42))
+            (func (export "get") (param i32) (result externref)
+                (table.get $externs (local.get 0)))
+            (func (export "set") (param i32 externref)
+                (table.set $externs (local.get 0) (local.get 1)))
+            (func (export "grow") (param i32 externref) (result i32)
+                (table.grow $externs (local.get 1) (local.get 0)))
+            (func (export "size") (result i32) (table.size $externs))
+            (func (export "call") (param i32) (result i32)
+                (call_indirect $funcs (result i32) (local.get 0)))
+        )"#,
+        &Imports::new(),
+    )?;
+
+    let funcs = instance
+        .get_export(&store, "funcs")
+        .and_then(wasm_runtime_layer::Extern::into_table)
+        .ok_or_else(|| anyhow::anyhow!("missing table export"))?;
+    let answer = export_func(&store, &instance, "answer")?;
+
+    let mut trace = Trace::new();
+
+    let describe_get =
+        |value: Option<Value>| value.map_or_else(|| String::from("none"), |v| describe(&v));
+    let describe_unit =
+        |result: anyhow::Result<()>| describe_outcome(result, |()| String::from("ok"));
+    let describe_u32 = |result: anyhow::Result<u32>| describe_outcome(result, |n| n.to_string());
+
+    // host accesses to an exported table
+    trace.push(format!("funcs.size() = {}", funcs.size(&store)));
+    for index in [0, 1, 2, 4, u32::MAX] {
+        trace.push(format!(
+            "funcs.get({index}) = {}",
+            describe_get(funcs.get(&mut store, index))
+        ));
+    }
+    for index in [1, 2] {
+        let result = funcs.set(&mut store, index, Value::FuncRef(Some(answer.clone())));
+        trace.push(format!("funcs.set({index}) = {}", describe_unit(result)));
+    }
+    for delta in [1, 2, 0] {
+        let result = funcs.grow(&mut store, delta, Value::FuncRef(None));
+        trace.push(format!("funcs.grow({delta}) = {}", describe_u32(result)));
+    }
+    trace.push(format!("funcs.size() = {}", funcs.size(&store)));
+
+    // guest accesses, which include calls through the host-updated table
+    let call_indirect = export_func(&store, &instance, "call")?;
+    for index in [0, 1, 2, 3] {
+        trace.push(format!(
+            "call({index}) = {}",
+            call(&mut store, &call_indirect, &[Value::I32(index)])
+        ));
+    }
+
+    let extern_ref = Value::ExternRef(Some(ExternRef::new(&mut store, 42_u32)));
+    for (name, args) in [
+        ("get", vec![Value::I32(0)]),
+        ("get", vec![Value::I32(1)]),
+        ("set", vec![Value::I32(0), extern_ref.clone()]),
+        ("set", vec![Value::I32(1), extern_ref.clone()]),
+        ("get", vec![Value::I32(0)]),
+        ("grow", vec![Value::I32(2), extern_ref.clone()]),
+        ("grow", vec![Value::I32(-1), Value::ExternRef(None)]),
+        ("size", vec![]),
+        ("get", vec![Value::I32(2)]),
+        ("get", vec![Value::I32(3)]),
+    ] {
+        let func = export_func(&store, &instance, name)?;
+        trace.push(format!(
+            "{name}({}) = {}",
+            describe_values(&args),
+            call(&mut store, &func, &args)
+        ));
+    }
+
+    // host accesses to a host table
+    let host = Table::new(
+        &mut store,
+        TableType::new(ValueType::ExternRef, 1, Some(2)),
+        Value::ExternRef(None),
+    )?;
+    trace.push(format!(
+        "host.get(1) = {}",
+        describe_get(host.get(&mut store, 1))
+    ));
+    trace.push(format!(
+        "host.set(1) = {}",
+        describe_unit(host.set(&mut store, 1, extern_ref.clone()))
+    ));
+    for delta in [1, 1] {
+        let result = host.grow(&mut store, delta, extern_ref.clone());
+        trace.push(format!("host.grow({delta}) = {}", describe_u32(result)));
+    }
+    trace.push(format!(
+        "host.get(1) = {}",
+        describe_get(host.get(&mut store, 1))
+    ));
+
+    Ok(trace)
+}
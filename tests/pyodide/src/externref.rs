@@ -0,0 +1,103 @@
+use wasm_runtime_layer::{AsContext, ExternRef, Global, Table, TableType, Value, ValueType};
+
+use crate::{engine, export_func, instantiate, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "externref::downcast",
+        test: downcast,
+    },
+    Test {
+        name: "externref::guest",
+        test: guest,
+    },
+    Test {
+        name: "externref::global_table",
+        test: global_table,
+    },
+];
+
+/// `WasmExternRef::{new, downcast}`
+fn downcast() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let hello = ExternRef::new(&mut store, String::from("hello"));
+    anyhow::ensure!(hello.downcast::<String, _, _>(store.as_context())? == "hello");
+    anyhow::ensure!(hello.downcast::<u32, _, _>(store.as_context()).is_err());
+
+    Ok(())
+}
+
+/// Extern references are passed through guest code unchanged
+fn guest() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func (export "select") (param externref externref i32) (result externref)
+                (select (result externref) (local.get 0) (local.get 1) (local.get 2)))
+        )"#,
+    )?;
+    let select = export_func(&store, &instance, "select")?;
+
+    let a = ExternRef::new(&mut store, 1_u32);
+    let b = ExternRef::new(&mut store, 2_u32);
+
+    let mut results = [Value::ExternRef(None)];
+    select.call(
+        &mut store,
+        &[
+            Value::ExternRef(Some(a.clone())),
+            Value::ExternRef(Some(b)),
+            Value::I32(0),
+        ],
+        &mut results,
+    )?;
+    let [Value::ExternRef(Some(selected))] = &results else {
+        anyhow::bail!("unexpected results {results:?}");
+    };
+    anyhow::ensure!(*selected.downcast::<u32, _, _>(store.as_context())? == 2);
+
+    select.call(
+        &mut store,
+        &[
+            Value::ExternRef(Some(a)),
+            Value::ExternRef(None),
+            Value::I32(0),
+        ],
+        &mut results,
+    )?;
+    anyhow::ensure!(matches!(results, [Value::ExternRef(None)]));
+
+    Ok(())
+}
+
+/// Extern references are stored in host globals and tables
+fn global_table() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let data = ExternRef::new(&mut store, vec![1_u8, 2, 3]);
+
+    let global = Global::new(&mut store, Value::ExternRef(None), true);
+    global.set(&mut store, Value::ExternRef(Some(data.clone())))?;
+    let Value::ExternRef(Some(stored)) = global.get(&mut store) else {
+        anyhow::bail!("expected an extern reference");
+    };
+    anyhow::ensure!(stored.downcast::<Vec<u8>, _, _>(store.as_context())? == &[1, 2, 3]);
+
+    let table = Table::new(
+        &mut store,
+        TableType::new(ValueType::ExternRef, 2, None),
+        Value::ExternRef(Some(data)),
+    )?;
+    let Some(Value::ExternRef(Some(stored))) = table.get(&mut store, 1) else {
+        anyhow::bail!("expected an extern reference");
+    };
+    anyhow::ensure!(stored.downcast::<Vec<u8>, _, _>(store.as_context())? == &[1, 2, 3]);
+
+    Ok(())
+}
@@ -0,0 +1,661 @@
+use wasm_runtime_layer::{Extern, Func, FuncType, Imports, Instance, Value, ValueType};
+
+use crate::{engine, export_func, float_bits_engine, instantiate, module, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "func::guest",
+        test: guest,
+    },
+    Test {
+        name: "func::host",
+        test: host,
+    },
+    Test {
+        name: "func::host_from_guest",
+        test: host_from_guest,
+    },
+    Test {
+        name: "func::host_error",
+        test: host_error,
+    },
+    Test {
+        name: "func::trap",
+        test: trap,
+    },
+    Test {
+        name: "func::backtrace",
+        test: backtrace,
+    },
+    Test {
+        name: "func::funcref",
+        test: funcref,
+    },
+    Test {
+        name: "func::float_bits",
+        test: float_bits,
+    },
+    Test {
+        name: "func::call_batch",
+        test: call_batch,
+    },
+    Test {
+        name: "func::typed",
+        test: typed,
+    },
+    Test {
+        name: "func::js_host",
+        test: js_host,
+    },
+    Test {
+        name: "func::py_host",
+        test: py_host,
+    },
+];
+
+/// `WasmFunc::{ty, call}` for guest functions with every number type
+fn guest() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func (export "mix") (param i32 i64 f32 f64) (result f64 i64)
+                (f64.add
+                    (f64.add (f64.convert_i32_s (local.get 0)) (f64.promote_f32 (local.get 2)))
+                    (local.get 3))
+                (i64.mul (local.get 1) (i64.const -2)))
+        )"#,
+    )?;
+    let mix = export_func(&store, &instance, "mix")?;
+
+    let ty = mix.ty(&store);
+    anyhow::ensure!(
+        ty == FuncType::new(
+            [
+                ValueType::I32,
+                ValueType::I64,
+                ValueType::F32,
+                ValueType::F64
+            ],
+            [ValueType::F64, ValueType::I64],
+        ),
+        "unexpected type {ty}"
+    );
+
+    let mut results = [Value::F64(0.0), Value::I64(0)];
+    mix.call(
+        &mut store,
+        &[
+            Value::I32(-3),
+            Value::I64(i64::MAX / 4),
+            Value::F32(0.5),
+            Value::F64(10.25),
+        ],
+        &mut results,
+    )?;
+    anyhow::ensure!(
+        matches!(results, [Value::F64(x), Value::I64(y)] if x.to_bits() == 7.75_f64.to_bits() && y == -(i64::MAX / 4) * 2),
+        "unexpected results {results:?}"
+    );
+
+    Ok(())
+}
+
+/// `WasmFunc::{new, ty, call}` for host functions
+fn host() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let ty = FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]);
+    let sub = Func::new(&mut store, ty.clone(), |_ctx, args, results| {
+        let [Value::I64(a), Value::I64(b)] = args else {
+            anyhow::bail!("expected two i64 arguments");
+        };
+        results[0] = Value::I64(a - b);
+        Ok(())
+    });
+
+    anyhow::ensure!(sub.ty(&store) == ty);
+
+    let mut results = [Value::I64(0)];
+    sub.call(&mut store, &[Value::I64(5), Value::I64(8)], &mut results)?;
+    anyhow::ensure!(
+        matches!(results, [Value::I64(-3)]),
+        "unexpected results {results:?}"
+    );
+
+    Ok(())
+}
+
+/// Guest code calls an imported host function that accesses the store
+fn host_from_guest() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, Vec::new());
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "log" (func $log (param i32)))
+            (func (export "run")
+                (call $log (i32.const 1))
+                (call $log (i32.const 2))
+                (call $log (i32.const 3)))
+        )"#,
+    )?;
+
+    let log = Func::new(
+        &mut store,
+        FuncType::new([ValueType::I32], []),
+        |mut ctx, args, _results| {
+            let Value::I32(x) = args[0] else {
+                anyhow::bail!("expected an i32 argument");
+            };
+            ctx.data_mut().push(x);
+            Ok(())
+        },
+    );
+
+    let mut imports = Imports::new();
+    imports.define("env", "log", Extern::Func(log));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    export_func(&store, &instance, "run")?.call(&mut store, &[], &mut [])?;
+    anyhow::ensure!(
+        *store.data() == [1, 2, 3],
+        "unexpected log {:?}",
+        store.data()
+    );
+
+    Ok(())
+}
+
+/// Errors returned by host functions propagate through guest code
+fn host_error() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "fail" (func $fail))
+            (func (export "run") (call $fail))
+        )"#,
+    )?;
+
+    let fail = Func::new(
+        &mut store,
+        FuncType::new([], []),
+        |_ctx, _args, _results| anyhow::bail!("host function failed on purpose"),
+    );
+
+    let mut imports = Imports::new();
+    imports.define("env", "fail", Extern::Func(fail));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let Err(err) = export_func(&store, &instance, "run")?.call(&mut store, &[], &mut []) else {
+        anyhow::bail!("calling a failing host function succeeded");
+    };
+    anyhow::ensure!(
+        format!("{err:#}").contains("host function failed on purpose"),
+        "unexpected error {err:#}"
+    );
+
+    Ok(())
+}
+
+/// Guest traps are returned as errors
+fn trap() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func (export "unreachable") unreachable)
+            (func (export "div") (param i32) (result i32)
+                (i32.div_u (i32.const 1) (local.get 0)))
+        )"#,
+    )?;
+
+    anyhow::ensure!(export_func(&store, &instance, "unreachable")?
+        .call(&mut store, &[], &mut [])
+        .is_err());
+
+    let div = export_func(&store, &instance, "div")?;
+    let mut results = [Value::I32(0)];
+    div.call(&mut store, &[Value::I32(1)], &mut results)?;
+    anyhow::ensure!(div
+        .call(&mut store, &[Value::I32(0)], &mut results)
+        .is_err());
+
+    // the instance remains usable after a trap
+    div.call(&mut store, &[Value::I32(1)], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(1)]));
+
+    Ok(())
+}
+
+/// Traps carry a backtrace of the guest frames, which skips the frames of the
+/// adapters that preserve the bits of floats
+fn backtrace() -> anyhow::Result<()> {
+    for engine in [engine(), float_bits_engine()] {
+        let mut store = Store::new(&engine, ());
+
+        let instance = instantiate(
+            &mut store,
+            r#"(module
+                (func $fail (param f32) unreachable)
+                (func (export "trap") (param f32) (call $fail (local.get 0)))
+            )"#,
+        )?;
+
+        let Err(err) = export_func(&store, &instance, "trap")?.call(
+            &mut store,
+            &[Value::F32(f32::NAN)],
+            &mut [],
+        ) else {
+            anyhow::bail!("expected a trap");
+        };
+        let Some(backtrace) =
+            err.downcast_ref::<pyodide_webassembly_runtime_layer::WasmBacktrace>()
+        else {
+            anyhow::bail!("expected a backtrace in {err:?}");
+        };
+
+        let frames = backtrace
+            .frames()
+            .iter()
+            .map(pyodide_webassembly_runtime_layer::FrameInfo::func_index)
+            .collect::<Vec<_>>();
+        anyhow::ensure!(frames == [0, 1], "unexpected frames {frames:?}");
+    }
+
+    Ok(())
+}
+
+/// Function references are passed between guest and host code
+fn funcref() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func $seven (result i32) (i32.const 7))
+            (elem declare func $seven)
+            (func (export "get") (result funcref) (ref.func $seven))
+            (func (export "is_null") (param funcref) (result i32)
+                (ref.is_null (local.get 0)))
+        )"#,
+    )?;
+
+    let mut results = [Value::FuncRef(None)];
+    export_func(&store, &instance, "get")?.call(&mut store, &[], &mut results)?;
+    let [Value::FuncRef(Some(seven))] = &results else {
+        anyhow::bail!("unexpected results {results:?}");
+    };
+
+    let mut results = [Value::I32(0)];
+    seven.call(&mut store, &[], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(7)]));
+
+    let is_null = export_func(&store, &instance, "is_null")?;
+    is_null.call(&mut store, &[Value::FuncRef(None)], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(1)]));
+    is_null.call(
+        &mut store,
+        &[Value::FuncRef(Some(seven.clone()))],
+        &mut results,
+    )?;
+    anyhow::ensure!(matches!(results, [Value::I32(0)]));
+
+    Ok(())
+}
+
+/// Signalling NaNs and NaN payloads are passed unchanged to and from guest
+/// and host functions when float bits are preserved
+fn float_bits() -> anyhow::Result<()> {
+    let engine = float_bits_engine();
+    let mut store = Store::new(&engine, Vec::new());
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "swap" (func $swap (param f32 f64) (result f64 f32)))
+            (func (export "identity") (param f32 f64) (result i32 i64 f32 f64)
+                (i32.reinterpret_f32 (local.get 0)) (i64.reinterpret_f64 (local.get 1))
+                (local.get 0) (local.get 1))
+            (func (export "swap") (param f32 f64) (result f64 f32)
+                (call $swap (local.get 0) (local.get 1)))
+        )"#,
+    )?;
+
+    let swap = Func::new(
+        &mut store,
+        FuncType::new(
+            [ValueType::F32, ValueType::F64],
+            [ValueType::F64, ValueType::F32],
+        ),
+        |mut caller, args, results| {
+            let (Value::F32(a), Value::F64(b)) = (&args[0], &args[1]) else {
+                anyhow::bail!("expected f32 and f64 arguments");
+            };
+            caller.data_mut().push((a.to_bits(), b.to_bits()));
+            results[0] = Value::F64(*b);
+            results[1] = Value::F32(*a);
+            Ok(())
+        },
+    );
+
+    let mut imports = Imports::new();
+    imports.define("env", "swap", Extern::Func(swap.clone()));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    let identity = export_func(&store, &instance, "identity")?;
+    let guest_swap = export_func(&store, &instance, "swap")?;
+
+    for (a, b) in [
+        (0x7F80_0001_u32, 0x7FF0_0000_0000_0001_u64),
+        (0xFF80_0001, 0xFFF0_0000_0000_0001),
+        (0x7FBF_FFFF, 0x7FF7_FFFF_FFFF_FFFF),
+        (0x7FC0_0001, 0x7FF8_0000_0000_0001),
+        (0xFFFF_FFFF, 0xFFFF_FFFF_FFFF_FFFF),
+    ] {
+        let args = [Value::F32(f32::from_bits(a)), Value::F64(f64::from_bits(b))];
+
+        let mut results = [
+            Value::I32(0),
+            Value::I64(0),
+            Value::F32(0.0),
+            Value::F64(0.0),
+        ];
+        identity.call(&mut store, &args, &mut results)?;
+        #[allow(clippy::cast_possible_wrap)]
+        let (expected_a, expected_b) = (a as i32, b as i64);
+        anyhow::ensure!(
+            matches!(results, [Value::I32(x), Value::I64(y), Value::F32(z), Value::F64(w)]
+                if x == expected_a && y == expected_b && z.to_bits() == a && w.to_bits() == b),
+            "{a:#x} and {b:#x} were changed into {results:?}"
+        );
+
+        for func in [&guest_swap, &swap] {
+            let mut results = [Value::F64(0.0), Value::F32(0.0)];
+            func.call(&mut store, &args, &mut results)?;
+            anyhow::ensure!(
+                matches!(results, [Value::F64(x), Value::F32(y)]
+                    if x.to_bits() == b && y.to_bits() == a),
+                "{a:#x} and {b:#x} were swapped into {results:?}"
+            );
+        }
+
+        anyhow::ensure!(store.data_mut().split_off(0) == [(a, b), (a, b)]);
+    }
+
+    Ok(())
+}
+
+/// `Func::call_batch` for a guest function with multiple results, including
+/// `i64`s beyond the safe integer range of JavaScript numbers
+fn call_batch() -> anyhow::Result<()> {
+    use wasm_runtime_layer::backend::{self, WasmInstance, WasmModule};
+
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        std::io::Cursor::new(wat::parse_str(
+            r#"(module
+                (func (export "step") (param i64 f64) (result i64 f64)
+                    (i64.add (local.get 0) (i64.const 1))
+                    (f64.mul (local.get 1) (f64.const 2)))
+                (func (export "trap") (param i32) (result i32)
+                    (i32.div_u (i32.const 1) (local.get 0)))
+            )"#,
+        )?),
+    )?;
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(
+        &mut store,
+        &module,
+        &backend::Imports::default(),
+    )?;
+
+    let export = |name| match instance.get_export(&store, name) {
+        Some(backend::Extern::Func(func)) => Ok(func),
+        _ => Err(anyhow::anyhow!("missing function export {name:?}")),
+    };
+    let step = export("step")?;
+    let trap = export("trap")?;
+
+    let inputs = [i64::MIN, -1, (1 << 53) - 1, 1 << 53, i64::MAX - 1];
+    let args = inputs
+        .iter()
+        .map(|x| [backend::Value::I64(*x), backend::Value::F64(0.75)])
+        .collect::<Vec<_>>();
+    let mut results = vec![[backend::Value::I64(0), backend::Value::F64(0.0)]; args.len()];
+    step.call_batch(&mut store, &args, &mut results)?;
+
+    for (x, results) in inputs.iter().zip(&results) {
+        anyhow::ensure!(
+            matches!(results, [backend::Value::I64(y), backend::Value::F64(z)]
+                if *y == x + 1 && z.to_bits() == 1.5_f64.to_bits()),
+            "{x} was stepped into {results:?}"
+        );
+    }
+
+    let mut results = vec![[backend::Value::I32(0)]; 3];
+    anyhow::ensure!(trap
+        .call_batch(
+            &mut store,
+            &[
+                [backend::Value::I32(1)],
+                [backend::Value::I32(0)],
+                [backend::Value::I32(1)]
+            ],
+            &mut results,
+        )
+        .is_err());
+
+    Ok(())
+}
+
+/// `Func::typed` for guest and host functions with multiple results,
+/// including `i64`s beyond the safe integer range of JavaScript numbers
+fn typed() -> anyhow::Result<()> {
+    use wasm_runtime_layer::backend::{self, WasmFunc, WasmInstance, WasmModule};
+
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        std::io::Cursor::new(wat::parse_str(
+            r#"(module
+                (func (export "swap") (param i64 f64) (result f64 i64)
+                    (local.get 1) (local.get 0))
+            )"#,
+        )?),
+    )?;
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(
+        &mut store,
+        &module,
+        &backend::Imports::default(),
+    )?;
+
+    let Some(backend::Extern::Func(swap)) = instance.get_export(&store, "swap") else {
+        anyhow::bail!("missing function export \"swap\"");
+    };
+    let swap = swap.typed::<(i64, f64), (f64, i64)>(&store)?;
+
+    for x in [i64::MIN, -(1 << 53), (1 << 53) - 1, 1 << 53, i64::MAX] {
+        let (y, z) = swap.call(&mut store, (x, 0.5))?;
+        anyhow::ensure!(
+            y.to_bits() == 0.5_f64.to_bits() && z == x,
+            "{x} was swapped into {y} and {z}"
+        );
+    }
+
+    anyhow::ensure!(swap.func().typed::<(i64, f64), i64>(&store).is_err());
+
+    let negate = pyodide_webassembly_runtime_layer::Func::new(
+        &mut store,
+        FuncType::new([ValueType::I64], [ValueType::I64]),
+        |_caller, args, results| {
+            let backend::Value::I64(x) = args[0] else {
+                anyhow::bail!("expected an i64 argument");
+            };
+            results[0] = backend::Value::I64(x.wrapping_neg());
+            Ok(())
+        },
+    );
+    let negate = negate.typed::<i64, i64>(&store)?;
+
+    anyhow::ensure!(negate.call(&mut store, i64::MAX)? == -i64::MAX);
+    anyhow::ensure!(negate.call(&mut store, 1 << 53)? == -(1 << 53));
+
+    Ok(())
+}
+
+/// `Func::from_js_source` and `Func::from_js_function` for host functions
+/// that are implemented directly in JavaScript, called from the host and
+/// from the guest
+fn js_host() -> anyhow::Result<()> {
+    use pyo3::prelude::*;
+    use wasm_runtime_layer::backend::{self, WasmFunc, WasmInstance, WasmModule};
+
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let (mul, now) = Python::with_gil(|py| -> anyhow::Result<_> {
+        let mul = pyodide_webassembly_runtime_layer::Func::from_js_source(
+            py,
+            "(a, b) => BigInt.asIntN(64, a * b)",
+            FuncType::new([ValueType::I64, ValueType::I64], [ValueType::I64]),
+        )?;
+
+        // performance.now must be bound to performance
+        let now = py
+            .import("js")?
+            .getattr("performance")?
+            .getattr("now")?
+            .call_method1("bind", (py.import("js")?.getattr("performance")?,))?;
+        let now = pyodide_webassembly_runtime_layer::Func::from_js_function(
+            &now,
+            FuncType::new([], [ValueType::F64]),
+        )?;
+
+        anyhow::ensure!(pyodide_webassembly_runtime_layer::Func::from_js_source(
+            py,
+            "42",
+            FuncType::new([], [ValueType::I32]),
+        )
+        .is_err());
+
+        Ok((mul, now))
+    })?;
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        std::io::Cursor::new(wat::parse_str(
+            r#"(module
+                (import "env" "mul" (func $mul (param i64 i64) (result i64)))
+                (import "env" "now" (func $now (result f64)))
+                (func (export "square") (param i64) (result i64)
+                    (call $mul (local.get 0) (local.get 0)))
+                (func (export "elapsed") (result f64)
+                    (f64.sub (call $now) (call $now)))
+            )"#,
+        )?),
+    )?;
+    let mut imports = backend::Imports::default();
+    imports.define("env", "mul", backend::Extern::Func(mul.clone()));
+    imports.define("env", "now", backend::Extern::Func(now.clone()));
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(&mut store, &module, &imports)?;
+
+    let export = |name| match instance.get_export(&store, name) {
+        Some(backend::Extern::Func(func)) => Ok(func),
+        _ => Err(anyhow::anyhow!("missing function export {name:?}")),
+    };
+    let square = export("square")?.typed::<i64, i64>(&store)?;
+    let elapsed = export("elapsed")?.typed::<(), f64>(&store)?;
+
+    anyhow::ensure!(square.call(&mut store, -3_037_000_499)? == 9_223_372_030_926_249_001);
+    anyhow::ensure!(square.call(&mut store, 1 << 32)? == 0);
+    anyhow::ensure!(elapsed.call(&mut store, ())? <= 0.0);
+
+    let mut results = [backend::Value::I64(0)];
+    mul.call::<()>(
+        &mut store,
+        &[backend::Value::I64(1 << 53), backend::Value::I64(-2)],
+        &mut results,
+    )?;
+    anyhow::ensure!(matches!(results, [backend::Value::I64(x)] if x == -(1 << 54)));
+
+    let now = now.typed::<(), f64>(&store)?;
+    anyhow::ensure!(now.call(&mut store, ())? >= 0.0);
+
+    Ok(())
+}
+
+/// `Func::from_py_callable` for host functions that are implemented by a
+/// Python callable, called from the guest, including Python exceptions
+fn py_host() -> anyhow::Result<()> {
+    use pyo3::prelude::*;
+    use wasm_runtime_layer::backend::{self, WasmInstance, WasmModule};
+
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let half_rem = Python::with_gil(|py| -> anyhow::Result<_> {
+        let callable = py.eval(
+            pyo3::ffi::c_str!("lambda a, b: (a // 2, 2.0 % b)"),
+            None,
+            None,
+        )?;
+        let half_rem = pyodide_webassembly_runtime_layer::Func::from_py_callable(
+            &mut store,
+            FuncType::new(
+                [ValueType::I64, ValueType::F64],
+                [ValueType::I64, ValueType::F64],
+            ),
+            &callable,
+        )?;
+
+        anyhow::ensure!(pyodide_webassembly_runtime_layer::Func::from_py_callable(
+            &mut store,
+            FuncType::new([], []),
+            &py.None().into_bound(py),
+        )
+        .is_err());
+
+        Ok(half_rem)
+    })?;
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        std::io::Cursor::new(wat::parse_str(
+            r#"(module
+                (import "env" "half_rem" (func $half_rem (param i64 f64) (result i64 f64)))
+                (func (export "half_rem") (param i64 f64) (result i64 f64)
+                    (call $half_rem (local.get 0) (local.get 1)))
+            )"#,
+        )?),
+    )?;
+    let mut imports = backend::Imports::default();
+    imports.define("env", "half_rem", backend::Extern::Func(half_rem));
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(&mut store, &module, &imports)?;
+
+    let Some(backend::Extern::Func(half_rem)) = instance.get_export(&store, "half_rem") else {
+        anyhow::bail!("missing function export \"half_rem\"");
+    };
+    let half_rem = half_rem.typed::<(i64, f64), (i64, f64)>(&store)?;
+
+    let (q, r) = half_rem.call(&mut store, (i64::MAX, 3.0))?;
+    anyhow::ensure!(q == i64::MAX / 2 && r.to_bits() == 2.0_f64.to_bits());
+
+    let err = half_rem.call(&mut store, (1, 0.0)).unwrap_err();
+    anyhow::ensure!(format!("{err:?}").contains("ZeroDivisionError"));
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use wasm_runtime_layer::{Global, GlobalType, Value, ValueType};
+
+use crate::{engine, export_func, float_bits_engine, instantiate, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "global::host",
+        test: host,
+    },
+    Test {
+        name: "global::immutable",
+        test: immutable,
+    },
+    Test {
+        name: "global::exported",
+        test: exported,
+    },
+    Test {
+        name: "global::float_bits",
+        test: float_bits,
+    },
+];
+
+/// `WasmGlobal::{new, ty, get, set}` for every number type
+fn host() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    for (initial, updated, content) in [
+        (Value::I32(-1), Value::I32(i32::MAX), ValueType::I32),
+        (Value::I64(i64::MIN), Value::I64(42), ValueType::I64),
+        (Value::F32(0.5), Value::F32(-1.5), ValueType::F32),
+        (Value::F64(1e300), Value::F64(-0.0), ValueType::F64),
+    ] {
+        let global = Global::new(&mut store, initial.clone(), true);
+
+        anyhow::ensure!(global.ty(&store) == GlobalType::new(content, true));
+        anyhow::ensure!(global.get(&mut store) == initial);
+
+        global.set(&mut store, updated.clone())?;
+        anyhow::ensure!(global.get(&mut store) == updated);
+    }
+
+    Ok(())
+}
+
+/// `WasmGlobal::set` fails for immutable globals and mismatched values
+fn immutable() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let constant = Global::new(&mut store, Value::I32(1), false);
+    anyhow::ensure!(constant.ty(&store) == GlobalType::new(ValueType::I32, false));
+    anyhow::ensure!(constant.set(&mut store, Value::I32(2)).is_err());
+    anyhow::ensure!(constant.get(&mut store) == Value::I32(1));
+
+    let variable = Global::new(&mut store, Value::I32(1), true);
+    anyhow::ensure!(variable.set(&mut store, Value::I64(2)).is_err());
+
+    Ok(())
+}
+
+/// Exported guest globals observe updates from guest and host code
+fn exported() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (global $counter (export "counter") (mut i64) (i64.const 10))
+            (func (export "increment")
+                (global.set $counter (i64.add (global.get $counter) (i64.const 1))))
+        )"#,
+    )?;
+
+    let counter = instance
+        .get_export(&store, "counter")
+        .and_then(wasm_runtime_layer::Extern::into_global)
+        .ok_or_else(|| anyhow::anyhow!("missing global export"))?;
+    anyhow::ensure!(counter.ty(&store) == GlobalType::new(ValueType::I64, true));
+
+    export_func(&store, &instance, "increment")?.call(&mut store, &[], &mut [])?;
+    anyhow::ensure!(counter.get(&mut store) == Value::I64(11));
+
+    counter.set(&mut store, Value::I64(-5))?;
+    export_func(&store, &instance, "increment")?.call(&mut store, &[], &mut [])?;
+    anyhow::ensure!(counter.get(&mut store) == Value::I64(-4));
+
+    Ok(())
+}
+
+/// Signalling NaNs and NaN payloads are stored unchanged in host and guest
+/// globals when float bits are preserved
+fn float_bits() -> anyhow::Result<()> {
+    let engine = float_bits_engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (global (export "g32") (mut f32) (f32.const 0))
+            (global (export "g64") (mut f64) (f64.const 0))
+            (func (export "bits") (result i32 i64)
+                (i32.reinterpret_f32 (global.get 0)) (i64.reinterpret_f64 (global.get 1)))
+        )"#,
+    )?;
+    let bits = export_func(&store, &instance, "bits")?;
+    let exported = |name| {
+        instance
+            .get_export(&store, name)
+            .and_then(wasm_runtime_layer::Extern::into_global)
+            .ok_or_else(|| anyhow::anyhow!("missing global export {name:?}"))
+    };
+    let (g32, g64) = (exported("g32")?, exported("g64")?);
+
+    for (a, b) in [
+        (0x7F80_0001_u32, 0x7FF0_0000_0000_0001_u64),
+        (0xFF80_0001, 0xFFF0_0000_0000_0001),
+        (0x7FC0_0001, 0x7FF8_0000_0000_0001),
+    ] {
+        let (a_value, b_value) = (Value::F32(f32::from_bits(a)), Value::F64(f64::from_bits(b)));
+
+        g32.set(&mut store, a_value.clone())?;
+        g64.set(&mut store, b_value.clone())?;
+
+        let mut results = [Value::I32(0), Value::I64(0)];
+        bits.call(&mut store, &[], &mut results)?;
+        #[allow(clippy::cast_possible_wrap)]
+        let expected = (a as i32, b as i64);
+        anyhow::ensure!(
+            matches!(results, [Value::I32(x), Value::I64(y)] if (x, y) == expected),
+            "{a:#x} and {b:#x} were stored as {results:?}"
+        );
+
+        for mutable in [false, true] {
+            let host32 = Global::new(&mut store, a_value.clone(), mutable);
+            let host64 = Global::new(&mut store, b_value.clone(), mutable);
+
+            for (global, expected) in [(&g32, a_value.clone()), (&host32, a_value.clone())] {
+                let Value::F32(value) = global.get(&mut store) else {
+                    anyhow::bail!("expected an f32 global");
+                };
+                anyhow::ensure!(
+                    matches!(expected, Value::F32(e) if e.to_bits() == value.to_bits())
+                );
+            }
+            for (global, expected) in [(&g64, b_value.clone()), (&host64, b_value.clone())] {
+                let Value::F64(value) = global.get(&mut store) else {
+                    anyhow::bail!("expected an f64 global");
+                };
+                anyhow::ensure!(
+                    matches!(expected, Value::F64(e) if e.to_bits() == value.to_bits())
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use pyodide_webassembly_runtime_layer::LinkError;
+use wasm_runtime_layer::{
+    Extern, Func, FuncType, Global, Imports, Instance, Memory, MemoryType, Table, TableType, Value,
+    ValueType,
+};
+
+use crate::{engine, export_func, instantiate, module, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "instance::exports",
+        test: exports,
+    },
+    Test {
+        name: "instance::imports",
+        test: imports,
+    },
+    Test {
+        name: "instance::missing_import",
+        test: missing_import,
+    },
+];
+
+/// `WasmInstance::{new, exports, get_export}`
+fn exports() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func (export "nop"))
+            (global (export "answer") i32 (i32.const 42))
+            (memory (export "heap") 1)
+            (table (export "refs") 2 funcref)
+        )"#,
+    )?;
+
+    let mut names = instance
+        .exports(&store)
+        .map(|export| export.name)
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    anyhow::ensure!(
+        names == ["answer", "heap", "nop", "refs"],
+        "unexpected exports {names:?}"
+    );
+
+    anyhow::ensure!(matches!(
+        instance.get_export(&store, "nop"),
+        Some(Extern::Func(_))
+    ));
+    anyhow::ensure!(matches!(
+        instance.get_export(&store, "answer"),
+        Some(Extern::Global(_))
+    ));
+    anyhow::ensure!(matches!(
+        instance.get_export(&store, "heap"),
+        Some(Extern::Memory(_))
+    ));
+    anyhow::ensure!(matches!(
+        instance.get_export(&store, "refs"),
+        Some(Extern::Table(_))
+    ));
+    anyhow::ensure!(instance.get_export(&store, "missing").is_none());
+
+    Ok(())
+}
+
+/// `WasmInstance::new` with host-provided imports of every kind
+fn imports() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "double" (func $double (param i32) (result i32)))
+            (import "env" "offset" (global $offset i32))
+            (import "env" "memory" (memory 1))
+            (import "env" "table" (table 1 funcref))
+            (func (export "run") (result i32)
+                (i32.store (i32.const 8) (call $double (global.get $offset)))
+                (call_indirect (result i32) (i32.const 0))
+                (i32.add (i32.load (i32.const 8))))
+            (func $one (result i32) (i32.const 1))
+            (elem (i32.const 0) $one)
+        )"#,
+    )?;
+
+    let double = Func::new(
+        &mut store,
+        FuncType::new([ValueType::I32], [ValueType::I32]),
+        |_ctx, args, results| {
+            let Value::I32(x) = args[0] else {
+                anyhow::bail!("expected an i32 argument");
+            };
+            results[0] = Value::I32(x * 2);
+            Ok(())
+        },
+    );
+    let offset = Global::new(&mut store, Value::I32(20), false);
+    let memory = Memory::new(&mut store, MemoryType::new(1, None))?;
+    let table = Table::new(
+        &mut store,
+        TableType::new(ValueType::FuncRef, 1, None),
+        Value::FuncRef(None),
+    )?;
+
+    let mut imports = Imports::new();
+    imports.define("env", "double", Extern::Func(double));
+    imports.define("env", "offset", Extern::Global(offset));
+    imports.define("env", "memory", Extern::Memory(memory.clone()));
+    imports.define("env", "table", Extern::Table(table.clone()));
+
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let mut results = [Value::I32(0)];
+    export_func(&store, &instance, "run")?.call(&mut store, &[], &mut results)?;
+    anyhow::ensure!(
+        matches!(results, [Value::I32(41)]),
+        "unexpected results {results:?}"
+    );
+
+    let mut stored = [0_u8; 4];
+    memory.read(&store, 8, &mut stored)?;
+    anyhow::ensure!(i32::from_le_bytes(stored) == 40);
+
+    anyhow::ensure!(matches!(
+        table.get(&mut store, 0),
+        Some(Value::FuncRef(Some(_)))
+    ));
+
+    Ok(())
+}
+
+/// `WasmInstance::new` reports missing imports
+fn missing_import() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let module = module(
+        &engine,
+        r#"(module (import "env" "log" (func (param i32))))"#,
+    )?;
+
+    let Err(err) = Instance::new(&mut store, &module, &Imports::new()) else {
+        anyhow::bail!("instantiation without imports succeeded");
+    };
+
+    let err = err
+        .downcast_ref::<LinkError>()
+        .ok_or_else(|| anyhow::anyhow!("expected a LinkError, found {err:?}"))?;
+    anyhow::ensure!(err.missing().len() == 1);
+    anyhow::ensure!(err.missing()[0].module() == "env" && err.missing()[0].name() == "log");
+
+    Ok(())
+}
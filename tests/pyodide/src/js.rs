@@ -0,0 +1,294 @@
+//! Tests of the JavaScript helper functions that the backend evaluates with
+//! `pyodide.code.run_js`
+//!
+//! The native mock that the backend's unit tests run against cannot evaluate
+//! JavaScript and substitutes Python reimplementations of these helpers, so
+//! their JavaScript source is only covered here.
+
+use pyo3::prelude::*;
+use wasm_runtime_layer::{
+    backend::{self, WasmMemory, WasmTable},
+    Extern, Func, FuncType, Global, Imports, Instance, MemoryType, TableType, Value, ValueType,
+};
+
+use crate::{engine, export_func, instantiate, module, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "js::big_int_params",
+        test: big_int_params,
+    },
+    Test {
+        name: "js::big_int_results",
+        test: big_int_results,
+    },
+    Test {
+        name: "js::call_batch",
+        test: call_batch,
+    },
+    Test {
+        name: "js::is_instance_of",
+        test: is_instance_of,
+    },
+    Test {
+        name: "js::object_wrapped_big_int",
+        test: object_wrapped_big_int,
+    },
+];
+
+/// `i64`s around the largest safe JavaScript integer, which Pyodide passes
+/// either as numbers or as `BigInt`s
+const I64S: [i64; 8] = [
+    i64::MIN,
+    -(1 << 53),
+    -(1 << 53) + 1,
+    -1,
+    0,
+    (1 << 53) - 1,
+    1 << 53,
+    i64::MAX,
+];
+
+/// `bigIntParams` converts only the `i64` arguments of a guest call
+fn big_int_params() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (func (export "mix") (param i64 i32 i64) (result i64 i32)
+                (i64.sub (local.get 0) (local.get 2))
+                (local.get 1))
+        )"#,
+    )?;
+    let mix = export_func(&store, &instance, "mix")?;
+
+    for x in I64S {
+        let mut results = [Value::I64(0), Value::I32(0)];
+        mix.call(
+            &mut store,
+            &[Value::I64(x), Value::I32(-7), Value::I64(1)],
+            &mut results,
+        )?;
+        anyhow::ensure!(
+            matches!(results, [Value::I64(y), Value::I32(-7)] if y == x.wrapping_sub(1)),
+            "{x} was passed as {results:?}"
+        );
+    }
+
+    Ok(())
+}
+
+/// `bigIntResults` converts the single or the indexed `i64` results of a
+/// host function that is called by the guest
+fn big_int_results() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, 0_i64);
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "single" (func $single (result i64)))
+            (import "env" "multi" (func $multi (result i32 i64 i64)))
+            (func (export "single") (result i64) (call $single))
+            (func (export "multi") (result i32 i64 i64) (call $multi))
+        )"#,
+    )?;
+
+    let single = Func::new(
+        &mut store,
+        FuncType::new([], [ValueType::I64]),
+        |ctx, _args, results| {
+            results[0] = Value::I64(*ctx.data());
+            Ok(())
+        },
+    );
+    let multi = Func::new(
+        &mut store,
+        FuncType::new([], [ValueType::I32, ValueType::I64, ValueType::I64]),
+        |ctx, _args, results| {
+            let x = *ctx.data();
+            results[0] = Value::I32(-7);
+            results[1] = Value::I64(x);
+            results[2] = Value::I64(!x);
+            Ok(())
+        },
+    );
+
+    let mut imports = Imports::new();
+    imports.define("env", "single", Extern::Func(single));
+    imports.define("env", "multi", Extern::Func(multi));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+
+    let single = export_func(&store, &instance, "single")?;
+    let multi = export_func(&store, &instance, "multi")?;
+
+    for x in I64S {
+        *store.data_mut() = x;
+
+        let mut results = [Value::I64(0)];
+        single.call(&mut store, &[], &mut results)?;
+        anyhow::ensure!(
+            matches!(results, [Value::I64(y)] if y == x),
+            "{x} was returned as {results:?}"
+        );
+
+        let mut results = [Value::I32(0), Value::I64(0), Value::I64(0)];
+        multi.call(&mut store, &[], &mut results)?;
+        anyhow::ensure!(
+            matches!(results, [Value::I32(-7), Value::I64(y), Value::I64(z)] if y == x && z == !x),
+            "{x} was returned as {results:?}"
+        );
+    }
+
+    Ok(())
+}
+
+/// `callBatch` collects no, one, or several results per call
+fn call_batch() -> anyhow::Result<()> {
+    use wasm_runtime_layer::backend::{WasmInstance, WasmModule};
+
+    let engine = pyodide_webassembly_runtime_layer::Engine::default();
+    let mut store = Store::new(&crate::Engine::new(engine.clone()), ());
+
+    let module = pyodide_webassembly_runtime_layer::Module::new(
+        &engine,
+        std::io::Cursor::new(wat::parse_str(
+            r#"(module
+                (global $calls (export "calls") (mut i32) (i32.const 0))
+                (func (export "count")
+                    (global.set $calls (i32.add (global.get $calls) (i32.const 1))))
+                (func (export "neg") (param i64) (result i64)
+                    (i64.sub (i64.const 0) (local.get 0)))
+                (func (export "swap") (param i32 i64) (result i64 i32)
+                    (local.get 1) (local.get 0))
+            )"#,
+        )?),
+    )?;
+    let instance = pyodide_webassembly_runtime_layer::Instance::new(
+        &mut store,
+        &module,
+        &backend::Imports::default(),
+    )?;
+
+    let export = |name| match instance.get_export(&store, name) {
+        Some(backend::Extern::Func(func)) => Ok(func),
+        _ => Err(anyhow::anyhow!("missing function export {name:?}")),
+    };
+
+    let count = export("count")?;
+    let neg = export("neg")?;
+    let swap = export("swap")?;
+
+    let mut results: [[backend::Value<pyodide_webassembly_runtime_layer::Engine>; 0]; 3] =
+        [[], [], []];
+    count.call_batch(&mut store, &[[], [], []], &mut results)?;
+    let Some(backend::Extern::Global(calls)) = instance.get_export(&store, "calls") else {
+        anyhow::bail!("missing global export \"calls\"");
+    };
+    let calls = backend::WasmGlobal::get(&calls, &mut store);
+    anyhow::ensure!(
+        matches!(calls, backend::Value::I32(3)),
+        "count was called {calls:?} times"
+    );
+
+    let args = I64S.map(|x| [backend::Value::I64(x)]);
+    let mut results = I64S.map(|_| [backend::Value::I64(0)]);
+    neg.call_batch(&mut store, &args, &mut results)?;
+    for (x, results) in I64S.iter().zip(&results) {
+        anyhow::ensure!(
+            matches!(results, [backend::Value::I64(y)] if *y == x.wrapping_neg()),
+            "{x} was negated into {results:?}"
+        );
+    }
+
+    let args = I64S.map(|x| [backend::Value::I32(-7), backend::Value::I64(x)]);
+    let mut results = I64S.map(|_| [backend::Value::I64(0), backend::Value::I32(0)]);
+    swap.call_batch(&mut store, &args, &mut results)?;
+    for (x, results) in I64S.iter().zip(&results) {
+        anyhow::ensure!(
+            matches!(results, [backend::Value::I64(y), backend::Value::I32(-7)] if y == x),
+            "{x} was swapped into {results:?}"
+        );
+    }
+
+    Ok(())
+}
+
+/// `isInstanceOf` distinguishes the JavaScript WebAssembly classes
+fn is_instance_of() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let memory =
+        pyodide_webassembly_runtime_layer::Memory::new(&mut store, MemoryType::new(1, None))?;
+    let table = pyodide_webassembly_runtime_layer::Table::new(
+        &mut store,
+        TableType::new(ValueType::FuncRef, 1, None),
+        backend::Value::FuncRef(None),
+    )?;
+
+    Python::with_gil(|py| {
+        let (memory, table) = (memory.as_js(py), table.as_js(py));
+
+        pyodide_webassembly_runtime_layer::Memory::from_js(memory, None)?;
+        pyodide_webassembly_runtime_layer::Table::from_js(table, None)?;
+
+        anyhow::ensure!(
+            pyodide_webassembly_runtime_layer::Memory::from_js(table, None).is_err(),
+            "a table was accepted as a memory"
+        );
+        anyhow::ensure!(
+            pyodide_webassembly_runtime_layer::Table::from_js(memory, None).is_err(),
+            "a memory was accepted as a table"
+        );
+        anyhow::ensure!(
+            pyodide_webassembly_runtime_layer::Global::from_js(memory, None).is_err(),
+            "a memory was accepted as a global"
+        );
+
+        Ok(())
+    })
+}
+
+/// `objectWrappedBigInt` passes safe `i64` integers to JavaScript as
+/// `BigInt`s, e.g. into `i64` globals
+fn object_wrapped_big_int() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "global" (global $global (mut i64)))
+            (func (export "get") (result i64) (global.get $global))
+        )"#,
+    )?;
+
+    let global = Global::new(&mut store, Value::I64(0), true);
+
+    let mut imports = Imports::new();
+    imports.define("env", "global", Extern::Global(global.clone()));
+    let instance = Instance::new(&mut store, &module, &imports)?;
+    let get = export_func(&store, &instance, "get")?;
+
+    for x in I64S {
+        global.set(&mut store, Value::I64(x))?;
+
+        let value = global.get(&mut store);
+        anyhow::ensure!(
+            matches!(value, Value::I64(y) if y == x),
+            "{x} was stored as {value:?}"
+        );
+
+        let mut results = [Value::I64(0)];
+        get.call(&mut store, &[], &mut results)?;
+        anyhow::ensure!(
+            matches!(results, [Value::I64(y)] if y == x),
+            "{x} was read by the guest as {results:?}"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,159 @@
+#![deny(clippy::complexity)]
+#![deny(clippy::correctness)]
+#![warn(clippy::nursery)]
+#![warn(clippy::pedantic)]
+#![deny(clippy::perf)]
+#![deny(clippy::style)]
+#![deny(clippy::suspicious)]
+
+//! Integration tests for `pyodide-webassembly-runtime-layer`, which exercise
+//! every [`wasm_runtime_layer`] backend trait method against the real
+//! `WebAssembly` runtime of a JavaScript engine.
+//!
+//! The tests are compiled into a Python extension module for
+//! `wasm32-unknown-emscripten`, which is then loaded into Pyodide running
+//! under Node.js, see `run.sh` and `run.mjs`.
+//!
+//! The differential tests additionally compare this backend with the
+//! `wasmi` reference backend, see [`differential`].
+//!
+//! The micro-benchmarks measure the per-call cost of passing values across
+//! the JavaScript boundary, see [`bench`], `bench.sh`, and `bench.mjs`.
+//!
+//! The crate also provides a runner for the `.wast` scripts of the
+//! WebAssembly spec testsuite, which produces a per-proposal conformance
+//! report, see `wast.sh` and `wast.mjs`.
+
+use std::{any::Any, io::Cursor, panic};
+
+use pyo3::prelude::*;
+
+mod bench;
+mod differential;
+mod externref;
+mod func;
+mod global;
+mod instance;
+mod js;
+mod memory;
+mod module;
+mod store;
+mod table;
+mod wast;
+
+/// The [`wasm_runtime_layer::Engine`] of the backend under test
+type Engine = wasm_runtime_layer::Engine<pyodide_webassembly_runtime_layer::Engine>;
+/// The [`wasm_runtime_layer::Store`] of the backend under test
+type Store<T = ()> = wasm_runtime_layer::Store<T, pyodide_webassembly_runtime_layer::Engine>;
+
+/// A named integration test
+pub struct Test {
+    /// The name of the test, prefixed by the name of its module
+    name: &'static str,
+    /// The test function, which fails by returning an error or panicking
+    test: fn() -> anyhow::Result<()>,
+}
+
+/// All integration tests, grouped by the backend trait that they cover
+const TESTS: &[&[Test]] = &[
+    store::TESTS,
+    module::TESTS,
+    instance::TESTS,
+    func::TESTS,
+    global::TESTS,
+    memory::TESTS,
+    table::TESTS,
+    externref::TESTS,
+    js::TESTS,
+    differential::TESTS,
+];
+
+#[pyfunction]
+#[pyo3(signature = (filter = None))]
+/// Runs all integration tests whose name contains the `filter`, if given,
+/// and returns the name and, if it failed, the error message of each test
+fn run(filter: Option<&str>) -> Vec<(&'static str, Option<String>)> {
+    TESTS
+        .iter()
+        .flat_map(|tests| tests.iter())
+        .filter(|test| filter.map_or(true, |filter| test.name.contains(filter)))
+        .map(|test| {
+            let error = match panic::catch_unwind(test.test) {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => Some(format!("{err:?}")),
+                Err(payload) => Some(format!("panicked: {}", panic_message(&*payload))),
+            };
+
+            (test.name, error)
+        })
+        .collect()
+}
+
+/// Extracts the message of a panic `payload`
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "pyodide_webassembly_runtime_layer_tests")]
+fn pymodule(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(run, module)?)?;
+    module.add_function(wrap_pyfunction!(bench::run_bench, module)?)?;
+    module.add_function(wrap_pyfunction!(wast::run_wast, module)?)
+}
+
+/// Creates a new engine with the default configuration
+fn engine() -> Engine {
+    Engine::new(pyodide_webassembly_runtime_layer::Engine::default())
+}
+
+/// Creates a new engine that preserves the bits of floats across the
+/// JavaScript boundary
+fn float_bits_engine() -> Engine {
+    let mut config = pyodide_webassembly_runtime_layer::Config::new();
+    config.preserve_float_bits(true);
+    Engine::new(pyodide_webassembly_runtime_layer::Engine::new(&config))
+}
+
+/// Compiles the WebAssembly text format `wat` into a module
+fn module(engine: &Engine, wat: &str) -> anyhow::Result<wasm_runtime_layer::Module> {
+    wasm_runtime_layer::Module::new(engine, Cursor::new(wat::parse_str(wat)?))
+}
+
+/// Compiles and instantiates the WebAssembly text format `wat` without any
+/// imports
+fn instantiate<T>(store: &mut Store<T>, wat: &str) -> anyhow::Result<wasm_runtime_layer::Instance> {
+    let module = module(store.engine(), wat)?;
+    wasm_runtime_layer::Instance::new(store, &module, &wasm_runtime_layer::Imports::new())
+}
+
+/// Returns the function export `name` of the `instance`
+fn export_func<T>(
+    store: &Store<T>,
+    instance: &wasm_runtime_layer::Instance,
+    name: &str,
+) -> anyhow::Result<wasm_runtime_layer::Func> {
+    instance
+        .get_export(store, name)
+        .and_then(wasm_runtime_layer::Extern::into_func)
+        .ok_or_else(|| anyhow::anyhow!("missing function export {name:?}"))
+}
+
+/// Checks whether two extern types are equal
+fn extern_type_eq(a: &wasm_runtime_layer::ExternType, b: &wasm_runtime_layer::ExternType) -> bool {
+    use wasm_runtime_layer::ExternType;
+
+    match (a, b) {
+        (ExternType::Func(a), ExternType::Func(b)) => a == b,
+        (ExternType::Global(a), ExternType::Global(b)) => a == b,
+        (ExternType::Memory(a), ExternType::Memory(b)) => a == b,
+        (ExternType::Table(a), ExternType::Table(b)) => a == b,
+        _ => false,
+    }
+}
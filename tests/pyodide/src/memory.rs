@@ -0,0 +1,89 @@
+use wasm_runtime_layer::{Memory, MemoryType, Value};
+
+use crate::{engine, export_func, instantiate, Store, Test};
+
+/// The size of a WebAssembly page in bytes
+const PAGE_SIZE: usize = 1 << 16;
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "memory::host",
+        test: host,
+    },
+    Test {
+        name: "memory::exported",
+        test: exported,
+    },
+];
+
+/// `WasmMemory::{new, ty, current_pages, grow, read, write}`
+fn host() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let memory = Memory::new(&mut store, MemoryType::new(1, Some(3)))?;
+    anyhow::ensure!(memory.ty(&store) == MemoryType::new(1, Some(3)));
+    anyhow::ensure!(memory.current_pages(&store) == 1);
+
+    memory.write(&mut store, PAGE_SIZE - 4, &[1, 2, 3, 4])?;
+    let mut bytes = [0_u8; 6];
+    memory.read(&store, PAGE_SIZE - 6, &mut bytes)?;
+    anyhow::ensure!(bytes == [0, 0, 1, 2, 3, 4], "unexpected bytes {bytes:?}");
+
+    // out-of-bounds accesses fail without partial writes
+    anyhow::ensure!(memory.read(&store, PAGE_SIZE - 2, &mut bytes).is_err());
+    anyhow::ensure!(memory.write(&mut store, PAGE_SIZE - 2, &[9; 4]).is_err());
+    memory.read(&store, PAGE_SIZE - 6, &mut bytes)?;
+    anyhow::ensure!(bytes == [0, 0, 1, 2, 3, 4], "unexpected bytes {bytes:?}");
+
+    anyhow::ensure!(memory.grow(&mut store, 2)? == 1);
+    anyhow::ensure!(memory.current_pages(&store) == 3);
+    anyhow::ensure!(memory.ty(&store).initial_pages() == 3);
+    memory.write(&mut store, PAGE_SIZE * 3 - 1, &[5])?;
+
+    // growing beyond the maximum fails
+    anyhow::ensure!(memory.grow(&mut store, 1).is_err());
+    anyhow::ensure!(memory.current_pages(&store) == 3);
+
+    Ok(())
+}
+
+/// Exported guest memories observe writes from guest and host code
+fn exported() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 16) "hello")
+            (func (export "load") (param i32) (result i32)
+                (i32.load8_u (local.get 0)))
+            (func (export "grow") (result i32)
+                (memory.grow (i32.const 1)))
+        )"#,
+    )?;
+
+    let memory = instance
+        .get_export(&store, "memory")
+        .and_then(wasm_runtime_layer::Extern::into_memory)
+        .ok_or_else(|| anyhow::anyhow!("missing memory export"))?;
+
+    let mut hello = [0_u8; 5];
+    memory.read(&store, 16, &mut hello)?;
+    anyhow::ensure!(&hello == b"hello");
+
+    memory.write(&mut store, 100, &[42])?;
+    let mut results = [Value::I32(0)];
+    export_func(&store, &instance, "load")?.call(&mut store, &[Value::I32(100)], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(42)]));
+
+    // growth by guest code is visible to the host
+    export_func(&store, &instance, "grow")?.call(&mut store, &[], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(1)]));
+    anyhow::ensure!(memory.current_pages(&store) == 2);
+    memory.write(&mut store, PAGE_SIZE + 1, &[1])?;
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::io::Cursor;
+
+use wasm_runtime_layer::{
+    ExternType, FuncType, GlobalType, MemoryType, Module, TableType, ValueType,
+};
+
+use crate::{engine, extern_type_eq, module, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "module::imports_exports",
+        test: imports_exports,
+    },
+    Test {
+        name: "module::invalid",
+        test: invalid,
+    },
+];
+
+/// `WasmModule::{new, imports, exports, get_export}`
+fn imports_exports() -> anyhow::Result<()> {
+    let engine = engine();
+    let module = module(
+        &engine,
+        r#"(module
+            (import "env" "log" (func (param i32)))
+            (import "env" "counter" (global (mut i64)))
+            (import "env" "memory" (memory 1 2))
+            (import "env" "table" (table 3 funcref))
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+            (global (export "pi") f64 (f64.const 3.14))
+            (memory (export "heap") 2)
+            (table (export "refs") 1 4 externref)
+        )"#,
+    )?;
+
+    let expected_imports = [
+        (
+            "env",
+            "log",
+            ExternType::Func(FuncType::new([ValueType::I32], [])),
+        ),
+        (
+            "env",
+            "counter",
+            ExternType::Global(GlobalType::new(ValueType::I64, true)),
+        ),
+        (
+            "env",
+            "memory",
+            ExternType::Memory(MemoryType::new(1, Some(2))),
+        ),
+        (
+            "env",
+            "table",
+            ExternType::Table(TableType::new(ValueType::FuncRef, 3, None)),
+        ),
+    ];
+    let imports = module.imports(&engine).collect::<Vec<_>>();
+    anyhow::ensure!(
+        imports.len() == expected_imports.len()
+            && imports
+                .iter()
+                .zip(&expected_imports)
+                .all(|(import, (module, name, ty))| import.module == *module
+                    && import.name == *name
+                    && extern_type_eq(&import.ty, ty)),
+        "unexpected imports {imports:?}"
+    );
+
+    let expected_exports = [
+        (
+            "add",
+            ExternType::Func(FuncType::new(
+                [ValueType::I32, ValueType::I32],
+                [ValueType::I32],
+            )),
+        ),
+        (
+            "pi",
+            ExternType::Global(GlobalType::new(ValueType::F64, false)),
+        ),
+        ("heap", ExternType::Memory(MemoryType::new(2, None))),
+        (
+            "refs",
+            ExternType::Table(TableType::new(ValueType::ExternRef, 1, Some(4))),
+        ),
+    ];
+    let exports = module.exports(&engine).collect::<Vec<_>>();
+    anyhow::ensure!(
+        exports.len() == expected_exports.len()
+            && exports
+                .iter()
+                .zip(&expected_exports)
+                .all(|(export, (name, ty))| export.name == *name && extern_type_eq(&export.ty, ty)),
+        "unexpected exports {exports:?}"
+    );
+
+    anyhow::ensure!(module
+        .get_export(&engine, "heap")
+        .is_some_and(|ty| extern_type_eq(&ty, &ExternType::Memory(MemoryType::new(2, None)))));
+    anyhow::ensure!(module.get_export(&engine, "missing").is_none());
+
+    Ok(())
+}
+
+/// `WasmModule::new` rejects invalid modules
+fn invalid() -> anyhow::Result<()> {
+    let engine = engine();
+
+    anyhow::ensure!(Module::new(&engine, Cursor::new(b"\0asm\x01\0\0\0garbage")).is_err());
+    anyhow::ensure!(module(&engine, r"(module (func (result i32) (i64.const 0)))").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use wasm_runtime_layer::{AsContext, AsContextMut, Func, FuncType, Value, ValueType};
+
+use crate::{engine, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "store::data",
+        test: data,
+    },
+    Test {
+        name: "store::context",
+        test: context,
+    },
+];
+
+/// `WasmStore::{new, engine, data, data_mut, into_data}`
+fn data() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, 42_u32);
+
+    anyhow::ensure!(*store.data() == 42);
+    *store.data_mut() += 1;
+    anyhow::ensure!(*store.data() == 43);
+
+    let _engine = store.engine();
+
+    anyhow::ensure!(store.into_data() == 43);
+
+    Ok(())
+}
+
+/// `WasmStoreContext::{engine, data}` and
+/// `WasmStoreContextMut::{engine, data, data_mut}`
+fn context() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, 0_i32);
+
+    let counter = Func::new(
+        &mut store,
+        FuncType::new([], [ValueType::I32]),
+        |mut ctx, _args, results| {
+            let _engine = ctx.engine();
+            *ctx.data_mut() += 1;
+            results[0] = Value::I32(*ctx.data());
+            Ok(())
+        },
+    );
+
+    let mut results = [Value::I32(0)];
+    counter.call(&mut store, &[], &mut results)?;
+    counter.call(&mut store, &[], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(2)]));
+
+    let ctx = store.as_context();
+    let _engine = ctx.engine();
+    anyhow::ensure!(*ctx.data() == 2);
+
+    let mut ctx = store.as_context_mut();
+    *ctx.data_mut() = 7;
+    anyhow::ensure!(*ctx.data() == 7);
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use wasm_runtime_layer::{Table, TableType, Value, ValueType};
+
+use crate::{engine, export_func, instantiate, Store, Test};
+
+pub const TESTS: &[Test] = &[
+    Test {
+        name: "table::host",
+        test: host,
+    },
+    Test {
+        name: "table::exported",
+        test: exported,
+    },
+];
+
+/// `WasmTable::{new, ty, size, grow, get, set}`
+fn host() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let table = Table::new(
+        &mut store,
+        TableType::new(ValueType::FuncRef, 1, Some(4)),
+        Value::FuncRef(None),
+    )?;
+    anyhow::ensure!(table.ty(&store) == TableType::new(ValueType::FuncRef, 1, Some(4)));
+    anyhow::ensure!(table.size(&store) == 1);
+
+    // only WebAssembly functions can be stored in a funcref table
+    let instance = instantiate(
+        &mut store,
+        r#"(module (func (export "answer") (result i32) (i32.const 42)))"#,
+    )?;
+    let answer = export_func(&store, &instance, "answer")?;
+
+    anyhow::ensure!(table.grow(&mut store, 2, Value::FuncRef(Some(answer.clone())))? == 1);
+    anyhow::ensure!(table.size(&store) == 3);
+    anyhow::ensure!(matches!(
+        table.get(&mut store, 0),
+        Some(Value::FuncRef(None))
+    ));
+
+    let Some(Value::FuncRef(Some(func))) = table.get(&mut store, 2) else {
+        anyhow::bail!("expected a function reference");
+    };
+    let mut results = [Value::I32(0)];
+    func.call(&mut store, &[], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(42)]));
+
+    table.set(&mut store, 0, Value::FuncRef(Some(answer)))?;
+    anyhow::ensure!(matches!(
+        table.get(&mut store, 0),
+        Some(Value::FuncRef(Some(_)))
+    ));
+    table.set(&mut store, 2, Value::FuncRef(None))?;
+    anyhow::ensure!(matches!(
+        table.get(&mut store, 2),
+        Some(Value::FuncRef(None))
+    ));
+
+    // out-of-bounds accesses and growth beyond the maximum fail
+    anyhow::ensure!(table.get(&mut store, 3).is_none());
+    anyhow::ensure!(table.set(&mut store, 3, Value::FuncRef(None)).is_err());
+    anyhow::ensure!(table.grow(&mut store, 2, Value::FuncRef(None)).is_err());
+    anyhow::ensure!(table.size(&store) == 3);
+
+    // elements must match the table's element type
+    anyhow::ensure!(table.set(&mut store, 0, Value::I32(0)).is_err());
+
+    Ok(())
+}
+
+/// Exported guest tables observe updates from guest and host code
+fn exported() -> anyhow::Result<()> {
+    let engine = engine();
+    let mut store = Store::new(&engine, ());
+
+    let instance = instantiate(
+        &mut store,
+        r#"(module
+            (table $table (export "table") 2 funcref)
+            (func $one (result i32) (i32.const 1))
+            (func $two (result i32) (i32.const 2))
+            (elem (i32.const 0) $one)
+            (elem declare func $two)
+            (func (export "call") (param i32) (result i32)
+                (call_indirect (result i32) (local.get 0)))
+            (func (export "install") (param i32)
+                (table.set $table (local.get 0) (ref.func $two)))
+        )"#,
+    )?;
+
+    let table = instance
+        .get_export(&store, "table")
+        .and_then(wasm_runtime_layer::Extern::into_table)
+        .ok_or_else(|| anyhow::anyhow!("missing table export"))?;
+    anyhow::ensure!(table.ty(&store) == TableType::new(ValueType::FuncRef, 2, None));
+
+    let call = export_func(&store, &instance, "call")?;
+    let mut results = [Value::I32(0)];
+
+    // host code copies a guest function reference
+    let Some(one) = table.get(&mut store, 0) else {
+        anyhow::bail!("missing table element");
+    };
+    table.set(&mut store, 1, one)?;
+    call.call(&mut store, &[Value::I32(1)], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(1)]));
+
+    // guest code installs a function reference
+    export_func(&store, &instance, "install")?.call(&mut store, &[Value::I32(0)], &mut [])?;
+    call.call(&mut store, &[Value::I32(0)], &mut results)?;
+    anyhow::ensure!(matches!(results, [Value::I32(2)]));
+
+    Ok(())
+}
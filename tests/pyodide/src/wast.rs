@@ -0,0 +1,506 @@
+//! Runner for `.wast` scripts from the WebAssembly spec testsuite, which
+//! drives the [`wasm_runtime_layer`] API of the backend under test
+
+use std::{collections::HashMap, fs, panic};
+
+use pyo3::{intern, prelude::*};
+use pyodide_webassembly_runtime_layer::{LinkError, Trap};
+use wasm_runtime_layer::{
+    AsContext, Extern, ExternRef, Func, FuncType, Global, Imports, Instance, Memory, MemoryType,
+    Module, Table, TableType, Value, ValueType,
+};
+use wast::{
+    core::{AbstractHeapType, HeapType, NanPattern, WastArgCore, WastRetCore},
+    parser::{self, ParseBuffer},
+    token::Span,
+    QuoteWat, Wast, WastArg, WastDirective, WastExecute, WastInvoke, WastRet,
+};
+
+use crate::{engine, panic_message, Store};
+
+#[pyfunction]
+/// Runs the `.wast` script at `path` and returns the number of passed and
+/// skipped directives and the `line: directive: error` messages of all failed
+/// ones
+///
+/// A script that cannot be read or parsed counts as a single failure.
+pub fn run_wast(path: &str) -> (usize, usize, Vec<String>) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => return (0, 0, vec![format!("0: read: {err}")]),
+    };
+
+    let buffer = match ParseBuffer::new(&text) {
+        Ok(buffer) => buffer,
+        Err(err) => return (0, 0, vec![format!("0: parse: {}", first_line(&err))]),
+    };
+    let wast = match parser::parse::<Wast>(&buffer) {
+        Ok(wast) => wast,
+        Err(err) => return (0, 0, vec![format!("0: parse: {}", first_line(&err))]),
+    };
+
+    let mut runner = match WastRunner::new() {
+        Ok(runner) => runner,
+        Err(err) => return (0, 0, vec![format!("0: spectest: {}", first_line(&err))]),
+    };
+
+    let mut passed = 0;
+    let mut skipped = 0;
+    let mut failures = Vec::new();
+
+    for directive in wast.directives {
+        let span = directive_span(&directive);
+        let kind = directive_kind(&directive);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| runner.run(directive)))
+            .unwrap_or_else(|payload| {
+                Err(anyhow::anyhow!("panicked: {}", panic_message(&*payload)))
+            });
+
+        match result {
+            Ok(Outcome::Passed) => passed += 1,
+            Ok(Outcome::Skipped) => skipped += 1,
+            Err(err) => {
+                let (line, _column) = span.linecol_in(&text);
+                failures.push(format!("{}: {kind}: {}", line + 1, first_line(&err)));
+            },
+        }
+    }
+
+    (passed, skipped, failures)
+}
+
+/// The outcome of a directive that did not fail
+enum Outcome {
+    /// The backend behaved as the directive expects
+    Passed,
+    /// The directive does not exercise the backend, e.g. since its module is
+    /// already rejected by the text format parser
+    Skipped,
+}
+
+/// The state of a `.wast` script that is being run
+struct WastRunner {
+    /// The store in which all modules of the script are instantiated
+    store: Store,
+    /// The exports of the `spectest` module and of all registered instances
+    imports: Imports,
+    /// The named module definitions
+    modules: HashMap<String, Module>,
+    /// The most recent module definition
+    current_module: Option<Module>,
+    /// The named instances
+    instances: HashMap<String, Instance>,
+    /// The most recent instance
+    current_instance: Option<Instance>,
+}
+
+impl WastRunner {
+    /// Creates a new runner, which provides the `spectest` module imports
+    fn new() -> anyhow::Result<Self> {
+        let engine = engine();
+        let mut store = Store::new(&engine, ());
+
+        let mut imports = Imports::new();
+        Self::define_spectest(&mut store, &mut imports)?;
+
+        Ok(Self {
+            store,
+            imports,
+            modules: HashMap::new(),
+            current_module: None,
+            instances: HashMap::new(),
+            current_instance: None,
+        })
+    }
+
+    /// Defines the imports of the `spectest` module that the testsuite
+    /// expects every host to provide
+    fn define_spectest(store: &mut Store, imports: &mut Imports) -> anyhow::Result<()> {
+        for (name, params) in [
+            ("print", &[][..]),
+            ("print_i32", &[ValueType::I32]),
+            ("print_i64", &[ValueType::I64]),
+            ("print_f32", &[ValueType::F32]),
+            ("print_f64", &[ValueType::F64]),
+            ("print_i32_f32", &[ValueType::I32, ValueType::F32]),
+            ("print_f64_f64", &[ValueType::F64, ValueType::F64]),
+        ] {
+            let print = Func::new(
+                &mut *store,
+                FuncType::new(params.iter().copied(), []),
+                |_ctx, _args, _results| Ok(()),
+            );
+            imports.define("spectest", name, Extern::Func(print));
+        }
+
+        for (name, value) in [
+            ("global_i32", Value::I32(666)),
+            ("global_i64", Value::I64(666)),
+            ("global_f32", Value::F32(666.6)),
+            ("global_f64", Value::F64(666.6)),
+        ] {
+            let global = Global::new(&mut *store, value, false);
+            imports.define("spectest", name, Extern::Global(global));
+        }
+
+        let table = Table::new(
+            &mut *store,
+            TableType::new(ValueType::FuncRef, 10, Some(20)),
+            Value::FuncRef(None),
+        )?;
+        imports.define("spectest", "table", Extern::Table(table));
+
+        let memory = Memory::new(&mut *store, MemoryType::new(1, Some(2)))?;
+        imports.define("spectest", "memory", Extern::Memory(memory));
+
+        Ok(())
+    }
+
+    /// Runs a single `directive`
+    fn run(&mut self, directive: WastDirective) -> anyhow::Result<Outcome> {
+        match directive {
+            WastDirective::Module(mut wat) => {
+                let name = wat.name().map(|id| String::from(id.name()));
+                let module = self.compile(&wat.encode()?)?;
+                let instance = self.instantiate(&module)?;
+                self.define_instance(name, instance);
+            },
+            WastDirective::ModuleDefinition(mut wat) => {
+                let name = wat.name().map(|id| String::from(id.name()));
+                let module = self.compile(&wat.encode()?)?;
+                if let Some(name) = name {
+                    self.modules.insert(name, module.clone());
+                }
+                self.current_module = Some(module);
+            },
+            WastDirective::ModuleInstance {
+                instance, module, ..
+            } => {
+                let module = match module {
+                    Some(id) => self.modules.get(id.name()),
+                    None => self.current_module.as_ref(),
+                }
+                .ok_or_else(|| anyhow::anyhow!("unknown module definition"))?
+                .clone();
+                let instance_ = self.instantiate(&module)?;
+                self.define_instance(instance.map(|id| String::from(id.name())), instance_);
+            },
+            WastDirective::AssertMalformed { module, .. }
+            | WastDirective::AssertInvalid { module, .. } => return self.assert_invalid(module),
+            WastDirective::Register { name, module, .. } => {
+                let instance = self.instance(module.map(|id| id.name()))?.clone();
+                for export in instance.exports(&self.store) {
+                    self.imports.define(name, &export.name, export.value);
+                }
+            },
+            WastDirective::Invoke(invoke) => {
+                self.invoke(&invoke)?;
+            },
+            WastDirective::AssertTrap { exec, message, .. } => match self.execute(exec) {
+                Ok(_) => anyhow::bail!("expected a trap"),
+                Err(err) => assert_trap(&err, message)?,
+            },
+            WastDirective::AssertReturn { exec, results, .. } => {
+                let values = self.execute(exec)?;
+                self.assert_results(&values, &results)?;
+            },
+            WastDirective::AssertExhaustion { call, message, .. } => match self.invoke(&call) {
+                Ok(_) => anyhow::bail!("expected resource exhaustion"),
+                Err(err) if err.downcast_ref::<Trap>() == Some(&Trap::StackOverflow) => (),
+                Err(err) => assert_trap(&err, message)?,
+            },
+            WastDirective::AssertUnlinkable {
+                mut module,
+                message,
+                ..
+            } => {
+                let module = self.compile(&module.encode()?)?;
+                match self.instantiate(&module) {
+                    Ok(_) => anyhow::bail!("expected a link error"),
+                    Err(err) if err.downcast_ref::<LinkError>().is_some() => (),
+                    Err(err) => match js_error(&err) {
+                        Some((name, _)) if name == "LinkError" => (),
+                        _ => anyhow::bail!("expected a link error {message:?} but found {err:#}"),
+                    },
+                }
+            },
+            WastDirective::AssertException { .. }
+            | WastDirective::AssertSuspension { .. }
+            | WastDirective::Thread(_)
+            | WastDirective::Wait { .. } => anyhow::bail!("unsupported directive"),
+        }
+
+        Ok(Outcome::Passed)
+    }
+
+    /// Compiles the `bytes` of a module
+    fn compile(&self, bytes: &[u8]) -> anyhow::Result<Module> {
+        Module::new(self.store.engine(), bytes)
+    }
+
+    /// Instantiates the `module` with the spectest and registered imports
+    fn instantiate(&mut self, module: &Module) -> anyhow::Result<Instance> {
+        Instance::new(&mut self.store, module, &self.imports)
+    }
+
+    /// Makes an `instance` the current one and records its `name`, if any
+    fn define_instance(&mut self, name: Option<String>, instance: Instance) {
+        if let Some(name) = name {
+            self.instances.insert(name, instance.clone());
+        }
+        self.current_instance = Some(instance);
+    }
+
+    /// Returns the instance with the given `name`, or the current instance
+    fn instance(&self, name: Option<&str>) -> anyhow::Result<&Instance> {
+        name.map_or(self.current_instance.as_ref(), |name| {
+            self.instances.get(name)
+        })
+        .ok_or_else(|| anyhow::anyhow!("unknown instance"))
+    }
+
+    /// Asserts that a malformed or invalid `module` is rejected
+    fn assert_invalid(&self, mut module: QuoteWat) -> anyhow::Result<Outcome> {
+        // malformed text is already rejected by the text format parser and
+        // never reaches the backend
+        let Ok(bytes) = module.encode() else {
+            return Ok(Outcome::Skipped);
+        };
+
+        if self.compile(&bytes).is_ok() {
+            anyhow::bail!("expected the module to be rejected");
+        }
+
+        Ok(Outcome::Passed)
+    }
+
+    /// Executes an invocation, global access, or module instantiation
+    fn execute(&mut self, exec: WastExecute) -> anyhow::Result<Vec<Value>> {
+        match exec {
+            WastExecute::Invoke(invoke) => self.invoke(&invoke),
+            WastExecute::Wat(mut wat) => {
+                let module = self.compile(&wat.encode()?)?;
+                self.instantiate(&module)?;
+                Ok(Vec::new())
+            },
+            WastExecute::Get { module, global, .. } => {
+                let global = self
+                    .instance(module.map(|id| id.name()))?
+                    .get_export(&self.store, global)
+                    .and_then(Extern::into_global)
+                    .ok_or_else(|| anyhow::anyhow!("unknown global export {global:?}"))?;
+                Ok(vec![global.get(&mut self.store)])
+            },
+        }
+    }
+
+    /// Invokes an exported function and returns its results
+    fn invoke(&mut self, invoke: &WastInvoke) -> anyhow::Result<Vec<Value>> {
+        let func = self
+            .instance(invoke.module.map(|id| id.name()))?
+            .get_export(&self.store, invoke.name)
+            .and_then(Extern::into_func)
+            .ok_or_else(|| anyhow::anyhow!("unknown function export {:?}", invoke.name))?;
+
+        let args = invoke
+            .args
+            .iter()
+            .map(|arg| self.value(arg))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut results = vec![Value::I32(0); func.ty(&self.store).results().len()];
+        func.call(&mut self.store, &args, &mut results)?;
+
+        Ok(results)
+    }
+
+    /// Converts an argument into a value
+    fn value(&mut self, arg: &WastArg) -> anyhow::Result<Value> {
+        let WastArg::Core(arg) = arg else {
+            anyhow::bail!("unsupported component argument");
+        };
+
+        match arg {
+            WastArgCore::I32(x) => Ok(Value::I32(*x)),
+            WastArgCore::I64(x) => Ok(Value::I64(*x)),
+            WastArgCore::F32(x) => Ok(Value::F32(f32::from_bits(x.bits))),
+            WastArgCore::F64(x) => Ok(Value::F64(f64::from_bits(x.bits))),
+            WastArgCore::RefNull(HeapType::Abstract {
+                ty: AbstractHeapType::Func,
+                ..
+            }) => Ok(Value::FuncRef(None)),
+            WastArgCore::RefNull(HeapType::Abstract {
+                ty: AbstractHeapType::Extern,
+                ..
+            }) => Ok(Value::ExternRef(None)),
+            WastArgCore::RefExtern(x) => {
+                Ok(Value::ExternRef(Some(ExternRef::new(&mut self.store, *x))))
+            },
+            WastArgCore::V128(_) => anyhow::bail!("unsupported v128 argument"),
+            WastArgCore::RefNull(_) | WastArgCore::RefHost(_) => {
+                anyhow::bail!("unsupported reference argument")
+            },
+        }
+    }
+
+    /// Asserts that the `values` match the `expected` results
+    fn assert_results(&self, values: &[Value], expected: &[WastRet]) -> anyhow::Result<()> {
+        if values.len() != expected.len() {
+            anyhow::bail!(
+                "expected {} results but found {}",
+                expected.len(),
+                values.len()
+            );
+        }
+
+        for (i, (value, expected)) in values.iter().zip(expected).enumerate() {
+            let WastRet::Core(expected) = expected else {
+                anyhow::bail!("unsupported component result");
+            };
+
+            if !self.matches(value, expected) {
+                anyhow::bail!("result {i}: expected {expected:?} but found {value:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether a `value` matches an `expected` result
+    fn matches(&self, value: &Value, expected: &WastRetCore) -> bool {
+        match (value, expected) {
+            (Value::I32(x), WastRetCore::I32(y)) => x == y,
+            (Value::I64(x), WastRetCore::I64(y)) => x == y,
+            (Value::F32(x), WastRetCore::F32(pattern)) => match pattern {
+                NanPattern::CanonicalNan => x.to_bits() & 0x7fff_ffff == 0x7fc0_0000,
+                NanPattern::ArithmeticNan => x.to_bits() & 0x7fc0_0000 == 0x7fc0_0000,
+                NanPattern::Value(y) => x.to_bits() == y.bits,
+            },
+            (Value::F64(x), WastRetCore::F64(pattern)) => match pattern {
+                NanPattern::CanonicalNan => {
+                    x.to_bits() & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000
+                },
+                NanPattern::ArithmeticNan => {
+                    x.to_bits() & 0x7ff8_0000_0000_0000 == 0x7ff8_0000_0000_0000
+                },
+                NanPattern::Value(y) => x.to_bits() == y.bits,
+            },
+            (Value::FuncRef(None) | Value::ExternRef(None), WastRetCore::RefNull(_))
+            | (Value::FuncRef(Some(_)), WastRetCore::RefFunc(_))
+            | (Value::ExternRef(Some(_)), WastRetCore::RefExtern(None)) => true,
+            (Value::ExternRef(Some(x)), WastRetCore::RefExtern(Some(y))) => x
+                .downcast::<u32, _, _>(self.store.as_context())
+                .is_ok_and(|x| x == y),
+            (value, WastRetCore::Either(patterns)) => patterns
+                .iter()
+                .any(|expected| self.matches(value, expected)),
+            _ => false,
+        }
+    }
+}
+
+/// Asserts that an `err`or is a trap, i.e. a JavaScript `RuntimeError` or a
+/// `RangeError`, whose message matches the testsuite's `expected` message
+/// where the web browser's message for it is known
+fn assert_trap(err: &anyhow::Error, expected: &str) -> anyhow::Result<()> {
+    let Some((name, message)) = js_error(err) else {
+        anyhow::bail!("expected a trap {expected:?} but found {err:#}");
+    };
+
+    if name != "RuntimeError" && name != "RangeError" {
+        anyhow::bail!("expected a trap {expected:?} but found {name}: {message}");
+    }
+
+    match trap_messages(expected) {
+        Some(messages) if !messages.iter().any(|m| message.contains(m)) => {
+            anyhow::bail!("expected a trap {expected:?} but found {name}: {message}")
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Returns the `name` and `message` of the JavaScript error that caused
+/// an `err`or, if any
+fn js_error(err: &anyhow::Error) -> Option<(String, String)> {
+    let err = err.chain().find_map(|err| err.downcast_ref::<PyErr>())?;
+
+    Python::with_gil(|py| {
+        let err = err.value(py);
+
+        let name = err.getattr(intern!(py, "name")).ok()?.extract().ok()?;
+        let message = err.getattr(intern!(py, "message")).ok()?.extract().ok()?;
+
+        Some((name, message))
+    })
+}
+
+/// Returns the messages with which V8 reports the trap that the testsuite
+/// describes with the `expected` message, if they are known
+fn trap_messages(expected: &str) -> Option<&'static [&'static str]> {
+    let messages: &'static [&'static str] = match expected {
+        "unreachable" | "unreachable executed" => &["unreachable"],
+        "integer divide by zero" => &["divide by zero", "remainder by zero"],
+        "integer overflow" => &["divide result unrepresentable"],
+        "invalid conversion to integer" => &["float unrepresentable in integer range"],
+        "out of bounds memory access" => &["memory access out of bounds", "data segment"],
+        "out of bounds table access" | "undefined element" => {
+            &["table index is out of bounds", "element segment"]
+        },
+        "uninitialized element" | "uninitialized element 2" | "indirect call type mismatch" => {
+            &["null function", "function signature mismatch"]
+        },
+        "call stack exhausted" => &["call stack"],
+        _ => return None,
+    };
+
+    Some(messages)
+}
+
+/// Returns the source span of a `directive`
+fn directive_span(directive: &WastDirective) -> Span {
+    match directive {
+        WastDirective::Module(wat) | WastDirective::ModuleDefinition(wat) => wat.span(),
+        WastDirective::Invoke(invoke) => invoke.span,
+        WastDirective::Thread(thread) => thread.span,
+        WastDirective::ModuleInstance { span, .. }
+        | WastDirective::AssertMalformed { span, .. }
+        | WastDirective::AssertInvalid { span, .. }
+        | WastDirective::Register { span, .. }
+        | WastDirective::AssertTrap { span, .. }
+        | WastDirective::AssertReturn { span, .. }
+        | WastDirective::AssertExhaustion { span, .. }
+        | WastDirective::AssertUnlinkable { span, .. }
+        | WastDirective::AssertException { span, .. }
+        | WastDirective::AssertSuspension { span, .. }
+        | WastDirective::Wait { span, .. } => *span,
+    }
+}
+
+/// Returns the name of the kind of a `directive`
+const fn directive_kind(directive: &WastDirective) -> &'static str {
+    match directive {
+        WastDirective::Module(_) => "module",
+        WastDirective::ModuleDefinition(_) => "module definition",
+        WastDirective::ModuleInstance { .. } => "module instance",
+        WastDirective::AssertMalformed { .. } => "assert_malformed",
+        WastDirective::AssertInvalid { .. } => "assert_invalid",
+        WastDirective::Register { .. } => "register",
+        WastDirective::Invoke(_) => "invoke",
+        WastDirective::AssertTrap { .. } => "assert_trap",
+        WastDirective::AssertReturn { .. } => "assert_return",
+        WastDirective::AssertExhaustion { .. } => "assert_exhaustion",
+        WastDirective::AssertUnlinkable { .. } => "assert_unlinkable",
+        WastDirective::AssertException { .. } => "assert_exception",
+        WastDirective::AssertSuspension { .. } => "assert_suspension",
+        WastDirective::Thread(_) => "thread",
+        WastDirective::Wait { .. } => "wait",
+    }
+}
+
+/// Returns the first line of the message of an `error`, which keeps reports
+/// compact and stable
+fn first_line(error: &dyn std::fmt::Display) -> String {
+    let message = format!("{error:#}");
+    String::from(message.lines().next().unwrap_or_default())
+}
@@ -0,0 +1,86 @@
+// Runs the WebAssembly spec testsuite inside Pyodide under Node.js and writes
+// a per-proposal conformance report
+//
+// Usage: node --experimental-wasm-type-reflection wast.mjs <wheel> <testsuite> <report>
+//
+// The testsuite directory contains the core `.wast` scripts and a
+// `proposals/<proposal>/` directory with the scripts of each proposal, see
+// `fetch-testsuite.sh`. The report lists the passed and failed directives of
+// every script in a stable order, so that reports can be diffed between
+// releases. Directives that never reach the backend, e.g. malformed text
+// modules which the text format parser already rejects, are counted as
+// skipped.
+
+import { execFileSync } from "node:child_process";
+import { writeFile } from "node:fs/promises";
+import path from "node:path";
+import process from "node:process";
+
+import { loadHarness } from "./harness.mjs";
+
+const [wheel, testsuite, report] = process.argv.slice(2);
+
+if (report === undefined) {
+    console.error("usage: node wast.mjs <wheel> <testsuite> <report>");
+    process.exit(2);
+}
+
+let revision = "unknown";
+try {
+    revision = execFileSync("git", ["-C", testsuite, "rev-parse", "HEAD"], {
+        encoding: "utf8",
+    }).trim();
+} catch {
+    // the testsuite is not a git checkout
+}
+
+const pyodide = await loadHarness(wheel);
+
+pyodide.FS.mkdir("/testsuite");
+pyodide.FS.mount(pyodide.FS.filesystems.NODEFS, { root: path.resolve(testsuite) }, "/testsuite");
+
+pyodide.globals.set("testsuite_revision", revision);
+const conformance = pyodide.runPython(`
+import os
+
+from pyodide_webassembly_runtime_layer_tests import run_wast
+
+def scripts(directory):
+    return sorted(
+        script for script in os.listdir(directory) if script.endswith(".wast")
+    )
+
+proposals = [("core", "/testsuite")]
+if os.path.isdir("/testsuite/proposals"):
+    proposals += [
+        (proposal, f"/testsuite/proposals/{proposal}")
+        for proposal in sorted(os.listdir("/testsuite/proposals"))
+        if os.path.isdir(f"/testsuite/proposals/{proposal}")
+    ]
+
+lines = [
+    "# WebAssembly spec testsuite conformance",
+    f"# testsuite revision: {testsuite_revision}",
+    "",
+]
+
+for proposal, directory in proposals:
+    results = [(script, *run_wast(f"{directory}/{script}")) for script in scripts(directory)]
+
+    passed = sum(passed for _, passed, _, _ in results)
+    skipped = sum(skipped for _, _, skipped, _ in results)
+    total = passed + sum(len(failures) for _, _, _, failures in results)
+
+    print(f"{proposal}: {passed}/{total} directives passed, {skipped} skipped")
+
+    lines.append(f"## {proposal}: {passed}/{total} directives passed, {skipped} skipped")
+    for script, passed, skipped, failures in results:
+        lines.append(f"{script}: {passed}/{passed + len(failures)}, {skipped} skipped")
+        lines.extend(f"  {script}:{failure}" for failure in failures)
+    lines.append("")
+
+"\\n".join(lines)
+`);
+
+await writeFile(report, conformance);
+console.log(`wrote the conformance report to ${report}`);
@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+#
+# Builds the integration tests and runs the WebAssembly spec testsuite inside
+# Pyodide under Node.js, which requires the Pyodide distribution to be
+# vendored by `npm install` and the testsuite by `./fetch-testsuite.sh`.
+#
+# Usage: ./wast.sh [report]
+
+set -euo pipefail
+
+cd "$(dirname "$0")"
+
+./build.sh
+# funcref values can only be converted with JS Type Reflection support
+node --experimental-wasm-type-reflection wast.mjs dist/*.whl testsuite "${1:-conformance.txt}"